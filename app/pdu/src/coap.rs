@@ -0,0 +1,677 @@
+use core::convert::TryInto;
+
+use crate::{Error, Result};
+
+#[allow(non_snake_case)]
+pub mod CoapType {
+    pub const CONFIRMABLE: u8 = 0;
+    pub const NON_CONFIRMABLE: u8 = 1;
+    pub const ACKNOWLEDGEMENT: u8 = 2;
+    pub const RESET: u8 = 3;
+}
+
+#[allow(non_snake_case)]
+pub mod CoapCode {
+    pub const EMPTY: u8 = 0x00;
+    pub const GET: u8 = 0x01;
+    pub const POST: u8 = 0x02;
+    pub const PUT: u8 = 0x03;
+    pub const DELETE: u8 = 0x04;
+    pub const CREATED: u8 = 0x41;
+    pub const DELETED: u8 = 0x42;
+    pub const VALID: u8 = 0x43;
+    pub const CHANGED: u8 = 0x44;
+    pub const CONTENT: u8 = 0x45;
+    pub const CONTINUE: u8 = 0x5F;
+    pub const BAD_REQUEST: u8 = 0x80;
+    pub const UNAUTHORIZED: u8 = 0x81;
+    pub const BAD_OPTION: u8 = 0x82;
+    pub const FORBIDDEN: u8 = 0x83;
+    pub const NOT_FOUND: u8 = 0x84;
+    pub const METHOD_NOT_ALLOWED: u8 = 0x85;
+    pub const NOT_ACCEPTABLE: u8 = 0x86;
+    pub const REQUEST_ENTITY_INCOMPLETE: u8 = 0x88;
+    pub const PRECONDITION_FAILED: u8 = 0x8C;
+    pub const REQUEST_ENTITY_TOO_LARGE: u8 = 0x8D;
+    pub const UNSUPPORTED_CONTENT_FORMAT: u8 = 0x8F;
+    pub const INTERNAL_SERVER_ERROR: u8 = 0xA0;
+    pub const NOT_IMPLEMENTED: u8 = 0xA1;
+    pub const BAD_GATEWAY: u8 = 0xA2;
+    pub const SERVICE_UNAVAILABLE: u8 = 0xA3;
+    pub const GATEWAY_TIMEOUT: u8 = 0xA4;
+    pub const PROXYING_NOT_SUPPORTED: u8 = 0xA5;
+}
+
+#[allow(non_snake_case)]
+pub mod CoapOptionNumber {
+    pub const IF_MATCH: u16 = 1;
+    pub const URI_HOST: u16 = 3;
+    pub const ETAG: u16 = 4;
+    pub const IF_NONE_MATCH: u16 = 5;
+    pub const OBSERVE: u16 = 6;
+    pub const URI_PORT: u16 = 7;
+    pub const LOCATION_PATH: u16 = 8;
+    pub const URI_PATH: u16 = 11;
+    pub const CONTENT_FORMAT: u16 = 12;
+    pub const MAX_AGE: u16 = 14;
+    pub const URI_QUERY: u16 = 15;
+    pub const ACCEPT: u16 = 17;
+    pub const LOCATION_QUERY: u16 = 20;
+    pub const BLOCK2: u16 = 23;
+    pub const BLOCK1: u16 = 27;
+    pub const SIZE2: u16 = 28;
+    pub const PROXY_URI: u16 = 35;
+    pub const PROXY_SCHEME: u16 = 39;
+    pub const SIZE1: u16 = 60;
+}
+
+#[allow(non_snake_case)]
+pub mod CoapContentFormat {
+    pub const TEXT_PLAIN: u16 = 0;
+    pub const LINK_FORMAT: u16 = 40;
+    pub const XML: u16 = 41;
+    pub const OCTET_STREAM: u16 = 42;
+    pub const EXI: u16 = 47;
+    pub const JSON: u16 = 50;
+    pub const CBOR: u16 = 60;
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CoapBlock {
+    pub num: u32,
+    pub more: bool,
+    pub szx: u8,
+}
+
+impl CoapBlock {
+    pub fn size(&self) -> usize {
+        1 << (self.szx + 4)
+    }
+
+    fn from_uint(value: u32) -> Option<Self> {
+        let szx = (value & 0x07) as u8;
+        if szx == 7 {
+            // SZX 7 is reserved (RFC 7959, Section 2.2)
+            return None;
+        }
+        Some(CoapBlock {
+            num: value >> 4,
+            more: value & 0x08 != 0,
+            szx,
+        })
+    }
+
+    fn to_uint(self) -> u32 {
+        self.num << 4 | (self.more as u32) << 3 | self.szx as u32
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum CoapOption<'a> {
+    Raw { number: u16, value: &'a [u8] },
+    UriPath(&'a str),
+    UriQuery(&'a str),
+    ContentFormat(u16),
+    Observe(u32),
+    Block1(CoapBlock),
+    Block2(CoapBlock),
+}
+
+impl<'a> CoapOption<'a> {
+    pub fn number(&self) -> u16 {
+        match self {
+            CoapOption::Raw { number, .. } => *number,
+            CoapOption::UriPath(_) => CoapOptionNumber::URI_PATH,
+            CoapOption::UriQuery(_) => CoapOptionNumber::URI_QUERY,
+            CoapOption::ContentFormat(_) => CoapOptionNumber::CONTENT_FORMAT,
+            CoapOption::Observe(_) => CoapOptionNumber::OBSERVE,
+            CoapOption::Block1(_) => CoapOptionNumber::BLOCK1,
+            CoapOption::Block2(_) => CoapOptionNumber::BLOCK2,
+        }
+    }
+}
+
+pub struct CoapPdu {
+    buffer: [u8; 1152],
+    inner_size: usize,
+    last_option: u16,
+    payload_size: usize,
+}
+
+impl CoapPdu {
+    pub fn new() -> Self {
+        let mut coap_pdu = CoapPdu {
+            buffer: [0u8; 1152],
+            inner_size: 0,
+            last_option: 0,
+            payload_size: 0,
+        };
+        coap_pdu.version(0x01);
+        coap_pdu
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..4 + self.inner_size + self.payload_size]
+    }
+
+    pub fn version(&mut self, value: u8) {
+        let version = self.buffer[0] & 0x3F | value << 6;
+        self.buffer[0] = version;
+    }
+
+    pub fn message_type(&mut self, value: u8) {
+        let message_type = self.buffer[0] & 0xCF | (value & 0x3) << 4;
+        self.buffer[0] = message_type;
+    }
+
+    pub fn code(&mut self, value: u8) {
+        self.buffer[1] = value;
+    }
+
+    pub fn message_id(&mut self, value: u16) {
+        self.buffer[2..=3].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn token(&mut self, value: &[u8]) -> Result<()> {
+        let len = value.len();
+        if len > 8 {
+            return Err(Error::Oversized);
+        }
+        // The token comes first, so any options and payload are discarded
+        self.buffer[0] = self.buffer[0] & 0xF0 | len as u8;
+        self.buffer[4..4 + len].copy_from_slice(value);
+        self.inner_size = len;
+        self.last_option = 0;
+        self.payload_size = 0;
+        Ok(())
+    }
+
+    pub fn option(&mut self, value: &CoapOption) -> Result<()> {
+        let number = value.number();
+        if number < self.last_option || self.payload_size > 0 {
+            // Options must be added in ascending order and before the payload
+            return Err(Error::Malformed);
+        }
+        let mut uint = [0u8; 4];
+        let value = match value {
+            CoapOption::Raw { value, .. } => value,
+            CoapOption::UriPath(value) | CoapOption::UriQuery(value) => value.as_bytes(),
+            CoapOption::ContentFormat(value) => encode_uint(*value as u32, &mut uint),
+            CoapOption::Observe(value) => {
+                if *value > 0x00FF_FFFF {
+                    return Err(Error::Oversized);
+                }
+                encode_uint(*value, &mut uint)
+            }
+            CoapOption::Block1(block) | CoapOption::Block2(block) => {
+                if block.szx > 6 || block.num > 0x000F_FFFF {
+                    return Err(Error::Malformed);
+                }
+                encode_uint(block.to_uint(), &mut uint)
+            }
+        };
+        let delta = number - self.last_option;
+        let len = value.len();
+        let mut header = [0u8; 5];
+        let mut header_size = 1;
+        let delta_nibble = encode_nibble(delta, &mut header, &mut header_size);
+        let len_nibble = encode_nibble(len as u16, &mut header, &mut header_size);
+        header[0] = delta_nibble << 4 | len_nibble;
+        let pos = 4 + self.inner_size;
+        if len > 0xFFFF || pos + header_size + len > 1152 {
+            return Err(Error::Oversized);
+        }
+        self.buffer[pos..pos + header_size].copy_from_slice(&header[..header_size]);
+        self.buffer[pos + header_size..pos + header_size + len].copy_from_slice(value);
+        self.inner_size += header_size + len;
+        self.last_option = number;
+        Ok(())
+    }
+
+    pub fn payload(&mut self, value: &[u8]) -> Result<()> {
+        let pos = 4 + self.inner_size;
+        let len = value.len();
+        if len == 0 {
+            // An empty payload is sent without the marker (RFC 7252, Section 3)
+            self.payload_size = 0;
+            return Ok(());
+        }
+        if pos + 1 + len > 1152 {
+            return Err(Error::Oversized);
+        }
+        self.buffer[pos] = 0xFF;
+        self.buffer[pos + 1..pos + 1 + len].copy_from_slice(value);
+        self.payload_size = 1 + len;
+        Ok(())
+    }
+}
+
+impl Default for CoapPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn encode_uint(value: u32, buffer: &mut [u8; 4]) -> &[u8] {
+    *buffer = value.to_be_bytes();
+    let skip = value.leading_zeros() as usize / 8;
+    &buffer[skip..]
+}
+
+fn encode_nibble(value: u16, header: &mut [u8; 5], header_size: &mut usize) -> u8 {
+    match value {
+        0..=12 => value as u8,
+        13..=268 => {
+            header[*header_size] = (value - 13) as u8;
+            *header_size += 1;
+            13
+        }
+        _ => {
+            header[*header_size..*header_size + 2].copy_from_slice(&(value - 269).to_be_bytes());
+            *header_size += 2;
+            14
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct CoapParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> CoapParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 4 {
+            return Err(Error::Truncated);
+        }
+        let pdu = CoapParser { buffer };
+        if pdu.version() != 1 || pdu.token_length() > 8 {
+            return Err(Error::Malformed);
+        }
+        if buffer.len() < 4 + pdu.token_length() as usize {
+            return Err(Error::Truncated);
+        }
+        if pdu.code() == CoapCode::EMPTY && buffer.len() != 4 {
+            // An Empty message must be nothing but the header (RFC 7252, Section 4.1)
+            return Err(Error::Malformed);
+        }
+        let mut options = pdu.options();
+        while options.pos < options.buffer.len() {
+            if options.buffer[options.pos] == 0xFF {
+                if options.pos + 1 == options.buffer.len() {
+                    // A marker followed by a zero-length payload is a format error
+                    return Err(Error::Malformed);
+                }
+                break;
+            }
+            options.read()?;
+        }
+        Ok(pdu)
+    }
+
+    pub fn version(&'a self) -> u8 {
+        self.buffer[0] >> 6
+    }
+
+    pub fn message_type(&'a self) -> u8 {
+        (self.buffer[0] >> 4) & 0x3
+    }
+
+    pub fn token_length(&'a self) -> u8 {
+        self.buffer[0] & 0xF
+    }
+
+    pub fn code(&'a self) -> u8 {
+        self.buffer[1]
+    }
+
+    pub fn message_id(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[2..=3].try_into().unwrap())
+    }
+
+    pub fn token(&'a self) -> &'a [u8] {
+        &self.buffer[4..4 + self.token_length() as usize]
+    }
+
+    pub fn options(&self) -> CoapOptionIterator<'a> {
+        CoapOptionIterator {
+            buffer: self.buffer,
+            pos: 4 + (self.buffer[0] & 0xF) as usize,
+            number: 0,
+        }
+    }
+
+    pub fn payload(&'a self) -> &'a [u8] {
+        let mut options = self.options();
+        while options.next().is_some() {}
+        if options.pos < self.buffer.len() {
+            &self.buffer[options.pos + 1..]
+        } else {
+            &[]
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct CoapOptionIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+    number: u16,
+}
+
+impl<'a> CoapOptionIterator<'a> {
+    fn read(&mut self) -> Result<(u16, &'a [u8])> {
+        let buffer = self.buffer;
+        let header = buffer[self.pos];
+        let mut pos = self.pos + 1;
+        let delta = self.read_nibble(header >> 4, &mut pos)?;
+        let len = self.read_nibble(header & 0x0F, &mut pos)? as usize;
+        let number = self.number.checked_add(delta).ok_or(Error::Malformed)?;
+        if pos + len > buffer.len() {
+            return Err(Error::Truncated);
+        }
+        self.pos = pos + len;
+        self.number = number;
+        Ok((number, &buffer[pos..pos + len]))
+    }
+
+    fn read_nibble(&self, nibble: u8, pos: &mut usize) -> Result<u16> {
+        let buffer = self.buffer;
+        match nibble {
+            0..=12 => Ok(nibble as u16),
+            13 => {
+                let value = *buffer.get(*pos).ok_or(Error::Truncated)?;
+                *pos += 1;
+                Ok(value as u16 + 13)
+            }
+            14 => {
+                if *pos + 2 > buffer.len() {
+                    return Err(Error::Truncated);
+                }
+                let value = u16::from_be_bytes(buffer[*pos..*pos + 2].try_into().unwrap());
+                *pos += 2;
+                value.checked_add(269).ok_or(Error::Malformed)
+            }
+            // 15 is reserved for the payload marker
+            _ => Err(Error::Malformed),
+        }
+    }
+}
+
+impl<'a> Iterator for CoapOptionIterator<'a> {
+    type Item = CoapOption<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.buffer.len() || self.buffer[self.pos] == 0xFF {
+            return None;
+        }
+        let (number, value) = self.read().ok()?;
+        let raw = CoapOption::Raw { number, value };
+        let uint = value.iter().fold(0u32, |acc, &b| acc << 8 | b as u32);
+        Some(match number {
+            CoapOptionNumber::URI_PATH => match core::str::from_utf8(value) {
+                Ok(value) => CoapOption::UriPath(value),
+                Err(_) => raw,
+            },
+            CoapOptionNumber::URI_QUERY => match core::str::from_utf8(value) {
+                Ok(value) => CoapOption::UriQuery(value),
+                Err(_) => raw,
+            },
+            CoapOptionNumber::CONTENT_FORMAT if value.len() <= 2 => {
+                CoapOption::ContentFormat(uint as u16)
+            }
+            CoapOptionNumber::OBSERVE if value.len() <= 3 => CoapOption::Observe(uint),
+            CoapOptionNumber::BLOCK1 if value.len() <= 3 => match CoapBlock::from_uint(uint) {
+                Some(block) => CoapOption::Block1(block),
+                None => raw,
+            },
+            CoapOptionNumber::BLOCK2 if value.len() <= 3 => match CoapBlock::from_uint(uint) {
+                Some(block) => CoapOption::Block2(block),
+                None => raw,
+            },
+            _ => raw,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_round_trip() {
+        let mut coap_pdu = CoapPdu::new();
+        coap_pdu.message_type(CoapType::CONFIRMABLE);
+        coap_pdu.code(CoapCode::GET);
+        coap_pdu.message_id(0x1234);
+        coap_pdu.token(&[0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+        coap_pdu.option(&CoapOption::UriPath("sensors")).unwrap();
+        coap_pdu.option(&CoapOption::UriPath("temp")).unwrap();
+        coap_pdu.option(&CoapOption::UriQuery("unit=c")).unwrap();
+        coap_pdu
+            .option(&CoapOption::Raw {
+                number: CoapOptionNumber::ACCEPT,
+                value: &[CoapContentFormat::JSON as u8],
+            })
+            .unwrap();
+        coap_pdu
+            .option(&CoapOption::Block2(CoapBlock {
+                num: 3,
+                more: true,
+                szx: 2,
+            }))
+            .unwrap();
+        let mut udp_pdu = crate::UdpPdu::new();
+        udp_pdu.source_port(40000);
+        udp_pdu.destination_port(crate::UdpPort::COAP);
+        udp_pdu.inner(coap_pdu.as_bytes()).unwrap();
+        let udp = crate::UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(crate::Udp::Coap(coap)) = udp.inner() else {
+            panic!("expected CoAP");
+        };
+        assert_eq!(coap.version(), 1);
+        assert_eq!(coap.message_type(), CoapType::CONFIRMABLE);
+        assert_eq!(coap.code(), CoapCode::GET);
+        assert_eq!(coap.message_id(), 0x1234);
+        assert_eq!(coap.token(), [0xDE, 0xAD, 0xBE, 0xEF]);
+        let mut options = coap.options();
+        assert!(matches!(
+            options.next(),
+            Some(CoapOption::UriPath("sensors"))
+        ));
+        assert!(matches!(options.next(), Some(CoapOption::UriPath("temp"))));
+        assert!(matches!(
+            options.next(),
+            Some(CoapOption::UriQuery("unit=c"))
+        ));
+        assert!(matches!(
+            options.next(),
+            Some(CoapOption::Raw {
+                number: CoapOptionNumber::ACCEPT,
+                value: &[50],
+            })
+        ));
+        let Some(CoapOption::Block2(block)) = options.next() else {
+            panic!("expected Block2");
+        };
+        assert_eq!(
+            block,
+            CoapBlock {
+                num: 3,
+                more: true,
+                szx: 2,
+            }
+        );
+        assert_eq!(block.size(), 64);
+        assert!(options.next().is_none());
+        assert!(coap.payload().is_empty());
+    }
+
+    #[test]
+    fn response_round_trip() {
+        let mut coap_pdu = CoapPdu::new();
+        coap_pdu.message_type(CoapType::ACKNOWLEDGEMENT);
+        coap_pdu.code(CoapCode::CONTENT);
+        coap_pdu.message_id(0x1234);
+        coap_pdu.token(&[0x01]).unwrap();
+        coap_pdu.option(&CoapOption::Observe(0x0001_0000)).unwrap();
+        coap_pdu
+            .option(&CoapOption::ContentFormat(CoapContentFormat::TEXT_PLAIN))
+            .unwrap();
+        // Needs the extended forms of both the delta and the length
+        coap_pdu
+            .option(&CoapOption::Raw {
+                number: 300,
+                value: &[0x55; 20],
+            })
+            .unwrap();
+        coap_pdu.payload(b"21.5 C").unwrap();
+        let bytes = coap_pdu.as_bytes();
+        assert_eq!(bytes[4..10], [0x01, 0x63, 0x01, 0x00, 0x00, 0x60]);
+        assert_eq!(bytes[10..14], [0xED, 0x00, 0x13, 0x07]);
+        let coap = CoapParser::parse(bytes).unwrap();
+        let mut options = coap.options();
+        assert!(matches!(
+            options.next(),
+            Some(CoapOption::Observe(0x0001_0000))
+        ));
+        assert!(matches!(
+            options.next(),
+            Some(CoapOption::ContentFormat(CoapContentFormat::TEXT_PLAIN))
+        ));
+        let Some(CoapOption::Raw { number: 300, value }) = options.next() else {
+            panic!("expected option 300");
+        };
+        assert_eq!(value, [0x55; 20]);
+        assert!(options.next().is_none());
+        assert_eq!(coap.payload(), b"21.5 C");
+    }
+
+    #[test]
+    fn empty_message() {
+        let mut coap_pdu = CoapPdu::new();
+        coap_pdu.message_type(CoapType::RESET);
+        coap_pdu.message_id(0x1234);
+        assert_eq!(coap_pdu.as_bytes(), [0x70, 0x00, 0x12, 0x34]);
+        let coap = CoapParser::parse(coap_pdu.as_bytes()).unwrap();
+        assert_eq!(coap.message_type(), CoapType::RESET);
+        assert_eq!(coap.code(), CoapCode::EMPTY);
+        // An Empty message must not carry a token
+        coap_pdu.token(&[0x01]).unwrap();
+        assert!(matches!(
+            CoapParser::parse(coap_pdu.as_bytes()),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn builder_rejects_bad_options() {
+        let mut coap_pdu = CoapPdu::new();
+        coap_pdu.code(CoapCode::GET);
+        assert!(matches!(
+            coap_pdu.option(&CoapOption::Observe(0x0100_0000)),
+            Err(Error::Oversized)
+        ));
+        coap_pdu.option(&CoapOption::UriQuery("a")).unwrap();
+        assert!(matches!(
+            coap_pdu.option(&CoapOption::UriPath("b")),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            coap_pdu.option(&CoapOption::Block2(CoapBlock {
+                num: 0,
+                more: false,
+                szx: 7,
+            })),
+            Err(Error::Malformed)
+        ));
+        coap_pdu.payload(b"x").unwrap();
+        assert!(matches!(
+            coap_pdu.option(&CoapOption::Block2(CoapBlock {
+                num: 0,
+                more: false,
+                szx: 6,
+            })),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn oversized() {
+        let mut coap_pdu = CoapPdu::new();
+        coap_pdu.code(CoapCode::POST);
+        assert!(matches!(coap_pdu.token(&[0; 9]), Err(Error::Oversized)));
+        assert!(matches!(
+            coap_pdu.payload(&[0; 1148]),
+            Err(Error::Oversized)
+        ));
+        assert!(matches!(
+            coap_pdu.option(&CoapOption::Raw {
+                number: CoapOptionNumber::SIZE1,
+                value: &[0; 1152],
+            }),
+            Err(Error::Oversized)
+        ));
+        coap_pdu.payload(&[0; 1147]).unwrap();
+        assert_eq!(coap_pdu.as_bytes().len(), 1152);
+    }
+
+    #[test]
+    fn truncated_and_malformed() {
+        let mut coap_pdu = CoapPdu::new();
+        coap_pdu.code(CoapCode::GET);
+        coap_pdu.token(&[0x01, 0x02]).unwrap();
+        coap_pdu.option(&CoapOption::UriPath("temp")).unwrap();
+        let bytes = coap_pdu.as_bytes();
+        assert!(matches!(
+            CoapParser::parse(&bytes[..3]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            CoapParser::parse(&bytes[..5]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            CoapParser::parse(&bytes[..bytes.len() - 1]),
+            Err(Error::Truncated)
+        ));
+        // Version 2
+        assert!(matches!(
+            CoapParser::parse(&[0x80, 0x01, 0x00, 0x00]),
+            Err(Error::Malformed)
+        ));
+        // Token length 9
+        assert!(matches!(
+            CoapParser::parse(&[0x49, 0x01, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            Err(Error::Malformed)
+        ));
+        // Payload marker without a payload
+        assert!(matches!(
+            CoapParser::parse(&[0x40, 0x01, 0x00, 0x00, 0xFF]),
+            Err(Error::Malformed)
+        ));
+        // Option delta nibble 15 with a non-zero length
+        assert!(matches!(
+            CoapParser::parse(&[0x40, 0x01, 0x00, 0x00, 0xF1, 0x00]),
+            Err(Error::Malformed)
+        ));
+        // Extended option delta cut short
+        assert!(matches!(
+            CoapParser::parse(&[0x40, 0x01, 0x00, 0x00, 0xE0, 0x00]),
+            Err(Error::Truncated)
+        ));
+    }
+
+    #[test]
+    fn reserved_block_size_is_raw() {
+        // Block2 with SZX 7
+        let coap = CoapParser::parse(&[0x40, 0x01, 0x00, 0x00, 0xD1, 0x0A, 0x07]).unwrap();
+        assert!(matches!(
+            coap.options().next(),
+            Some(CoapOption::Raw {
+                number: CoapOptionNumber::BLOCK2,
+                value: &[0x07],
+            })
+        ));
+    }
+}
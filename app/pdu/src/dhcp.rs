@@ -0,0 +1,574 @@
+use core::convert::TryInto;
+
+use crate::{Error, Result};
+
+#[allow(non_snake_case)]
+pub mod DhcpOp {
+    pub const BOOTREQUEST: u8 = 1;
+    pub const BOOTREPLY: u8 = 2;
+}
+
+#[allow(non_snake_case)]
+pub mod DhcpMessageType {
+    pub const DISCOVER: u8 = 1;
+    pub const OFFER: u8 = 2;
+    pub const REQUEST: u8 = 3;
+    pub const DECLINE: u8 = 4;
+    pub const ACK: u8 = 5;
+    pub const NAK: u8 = 6;
+    pub const RELEASE: u8 = 7;
+    pub const INFORM: u8 = 8;
+}
+
+#[allow(non_snake_case)]
+pub mod DhcpOptionCode {
+    pub const PAD: u8 = 0;
+    pub const SUBNET_MASK: u8 = 1;
+    pub const ROUTER: u8 = 3;
+    pub const DNS_SERVER: u8 = 6;
+    pub const HOST_NAME: u8 = 12;
+    pub const REQUESTED_IP_ADDRESS: u8 = 50;
+    pub const LEASE_TIME: u8 = 51;
+    pub const MESSAGE_TYPE: u8 = 53;
+    pub const SERVER_IDENTIFIER: u8 = 54;
+    pub const PARAMETER_REQUEST_LIST: u8 = 55;
+    pub const CLIENT_IDENTIFIER: u8 = 61;
+    pub const END: u8 = 255;
+}
+
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+
+pub struct DhcpPdu {
+    buffer: [u8; 576],
+    inner_size: usize,
+}
+
+impl DhcpPdu {
+    pub fn new() -> Self {
+        let mut dhcp_pdu = DhcpPdu {
+            buffer: [0u8; 576],
+            inner_size: 0,
+        };
+        dhcp_pdu.hardware_type(0x01);
+        dhcp_pdu.hardware_length(0x06);
+        dhcp_pdu.buffer[236..=239].copy_from_slice(&MAGIC_COOKIE);
+        dhcp_pdu
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        // Some BOOTP relay agents drop messages shorter than 300 octets (RFC 1542)
+        let len = 240 + self.inner_size;
+        &self.buffer[..if len < 300 { 300 } else { len }]
+    }
+
+    pub fn op(&mut self, value: u8) {
+        self.buffer[0] = value;
+    }
+
+    pub fn hardware_type(&mut self, value: u8) {
+        self.buffer[1] = value;
+    }
+
+    pub fn hardware_length(&mut self, value: u8) {
+        self.buffer[2] = value;
+    }
+
+    pub fn hops(&mut self, value: u8) {
+        self.buffer[3] = value;
+    }
+
+    pub fn xid(&mut self, value: u32) {
+        self.buffer[4..=7].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn secs(&mut self, value: u16) {
+        self.buffer[8..=9].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn broadcast(&mut self, value: bool) {
+        let broadcast = if value {
+            self.buffer[10] | 0x80
+        } else {
+            self.buffer[10] & 0x7F
+        };
+        self.buffer[10] = broadcast;
+    }
+
+    pub fn client_ip_address(&mut self, value: [u8; 4]) {
+        self.buffer[12..=15].copy_from_slice(&value);
+    }
+
+    pub fn your_ip_address(&mut self, value: [u8; 4]) {
+        self.buffer[16..=19].copy_from_slice(&value);
+    }
+
+    pub fn server_ip_address(&mut self, value: [u8; 4]) {
+        self.buffer[20..=23].copy_from_slice(&value);
+    }
+
+    pub fn gateway_ip_address(&mut self, value: [u8; 4]) {
+        self.buffer[24..=27].copy_from_slice(&value);
+    }
+
+    pub fn client_hardware_address(&mut self, value: [u8; 6]) {
+        self.buffer[28..=33].copy_from_slice(&value);
+    }
+
+    pub fn option(&mut self, code: u8, data: &[u8]) -> Result<()> {
+        let pos = 240 + self.inner_size;
+        let len = data.len();
+        if len > 255 {
+            return Err(Error::Malformed);
+        }
+        if pos + 2 + len > 576 {
+            return Err(Error::Oversized);
+        }
+        self.buffer[pos] = code;
+        self.buffer[pos + 1] = len as u8;
+        self.buffer[pos + 2..pos + 2 + len].copy_from_slice(data);
+        self.inner_size += 2 + len;
+        Ok(())
+    }
+
+    fn address_option(&mut self, code: u8, value: &[[u8; 4]]) -> Result<()> {
+        let mut data = [0u8; 252];
+        if value.len() > 63 {
+            return Err(Error::Malformed);
+        }
+        for (i, address) in value.iter().enumerate() {
+            data[i * 4..i * 4 + 4].copy_from_slice(address);
+        }
+        self.option(code, &data[..value.len() * 4])
+    }
+
+    pub fn subnet_mask(&mut self, value: [u8; 4]) -> Result<()> {
+        self.option(DhcpOptionCode::SUBNET_MASK, &value)
+    }
+
+    pub fn router(&mut self, value: &[[u8; 4]]) -> Result<()> {
+        self.address_option(DhcpOptionCode::ROUTER, value)
+    }
+
+    pub fn dns_server(&mut self, value: &[[u8; 4]]) -> Result<()> {
+        self.address_option(DhcpOptionCode::DNS_SERVER, value)
+    }
+
+    pub fn requested_ip_address(&mut self, value: [u8; 4]) -> Result<()> {
+        self.option(DhcpOptionCode::REQUESTED_IP_ADDRESS, &value)
+    }
+
+    pub fn lease_time(&mut self, value: u32) -> Result<()> {
+        self.option(DhcpOptionCode::LEASE_TIME, &value.to_be_bytes())
+    }
+
+    pub fn message_type(&mut self, value: u8) -> Result<()> {
+        self.option(DhcpOptionCode::MESSAGE_TYPE, &[value])
+    }
+
+    pub fn server_identifier(&mut self, value: [u8; 4]) -> Result<()> {
+        self.option(DhcpOptionCode::SERVER_IDENTIFIER, &value)
+    }
+
+    pub fn parameter_request_list(&mut self, value: &[u8]) -> Result<()> {
+        self.option(DhcpOptionCode::PARAMETER_REQUEST_LIST, value)
+    }
+
+    pub fn client_identifier(&mut self, value: &[u8]) -> Result<()> {
+        self.option(DhcpOptionCode::CLIENT_IDENTIFIER, value)
+    }
+
+    pub fn end(&mut self) -> Result<()> {
+        let pos = 240 + self.inner_size;
+        if pos + 1 > 576 {
+            return Err(Error::Oversized);
+        }
+        self.buffer[pos] = DhcpOptionCode::END;
+        self.inner_size += 1;
+        Ok(())
+    }
+}
+
+impl Default for DhcpPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct DhcpParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> DhcpParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 240 {
+            return Err(Error::Truncated);
+        }
+        let pdu = DhcpParser { buffer };
+        if pdu.hardware_length() != 6 {
+            // Supports only 6-octet hardware addresses
+            return Err(Error::Malformed);
+        }
+        if buffer[236..=239] != MAGIC_COOKIE {
+            return Err(Error::Malformed);
+        }
+        Ok(pdu)
+    }
+
+    pub fn op(&'a self) -> u8 {
+        self.buffer[0]
+    }
+
+    pub fn hardware_type(&'a self) -> u8 {
+        self.buffer[1]
+    }
+
+    pub fn hardware_length(&'a self) -> u8 {
+        self.buffer[2]
+    }
+
+    pub fn hops(&'a self) -> u8 {
+        self.buffer[3]
+    }
+
+    pub fn xid(&'a self) -> u32 {
+        u32::from_be_bytes(self.buffer[4..=7].try_into().unwrap())
+    }
+
+    pub fn secs(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[8..=9].try_into().unwrap())
+    }
+
+    pub fn broadcast(&'a self) -> bool {
+        self.buffer[10] & 0x80 != 0
+    }
+
+    pub fn client_ip_address(&'a self) -> [u8; 4] {
+        let mut client_ip_address = [0u8; 4];
+        client_ip_address.copy_from_slice(&self.buffer[12..=15]);
+        client_ip_address
+    }
+
+    pub fn your_ip_address(&'a self) -> [u8; 4] {
+        let mut your_ip_address = [0u8; 4];
+        your_ip_address.copy_from_slice(&self.buffer[16..=19]);
+        your_ip_address
+    }
+
+    pub fn server_ip_address(&'a self) -> [u8; 4] {
+        let mut server_ip_address = [0u8; 4];
+        server_ip_address.copy_from_slice(&self.buffer[20..=23]);
+        server_ip_address
+    }
+
+    pub fn gateway_ip_address(&'a self) -> [u8; 4] {
+        let mut gateway_ip_address = [0u8; 4];
+        gateway_ip_address.copy_from_slice(&self.buffer[24..=27]);
+        gateway_ip_address
+    }
+
+    pub fn client_hardware_address(&'a self) -> [u8; 6] {
+        let mut client_hardware_address = [0u8; 6];
+        client_hardware_address.copy_from_slice(&self.buffer[28..=33]);
+        client_hardware_address
+    }
+
+    pub fn options(&'a self) -> DhcpOptionIterator<'a> {
+        DhcpOptionIterator {
+            buffer: self.buffer,
+            pos: 240,
+        }
+    }
+
+    pub fn message_type(&'a self) -> Option<u8> {
+        self.options().find_map(|option| match option {
+            DhcpOption::MessageType { message_type } => Some(message_type),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum DhcpOption<'a> {
+    Raw { option: u8, data: &'a [u8] },
+    Pad,
+    SubnetMask { mask: [u8; 4] },
+    Router { addresses: DhcpAddressIterator<'a> },
+    DnsServer { addresses: DhcpAddressIterator<'a> },
+    RequestedIpAddress { address: [u8; 4] },
+    LeaseTime { seconds: u32 },
+    MessageType { message_type: u8 },
+    ServerIdentifier { address: [u8; 4] },
+    ParameterRequestList { parameters: &'a [u8] },
+    ClientIdentifier { identifier: &'a [u8] },
+}
+
+#[derive(Copy, Clone)]
+pub struct DhcpOptionIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for DhcpOptionIterator<'a> {
+    type Item = DhcpOption<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos < self.buffer.len() {
+            let pos = self.pos;
+            let option = self.buffer[pos];
+            match option {
+                DhcpOptionCode::PAD => {
+                    self.pos += 1;
+                    return Some(DhcpOption::Pad);
+                }
+                DhcpOptionCode::END => {
+                    self.pos = self.buffer.len();
+                    return None;
+                }
+                _ => {}
+            }
+            if self.buffer.len() <= (pos + 1) {
+                return None;
+            }
+            let len = self.buffer[pos + 1] as usize;
+            if self.buffer.len() < (pos + 2 + len) {
+                return None;
+            }
+            self.pos += 2 + len;
+            let data = &self.buffer[pos + 2..pos + 2 + len];
+            match option {
+                DhcpOptionCode::SUBNET_MASK if len == 4 => Some(DhcpOption::SubnetMask {
+                    mask: data.try_into().unwrap(),
+                }),
+                DhcpOptionCode::ROUTER if len % 4 == 0 => Some(DhcpOption::Router {
+                    addresses: DhcpAddressIterator {
+                        buffer: data,
+                        pos: 0,
+                    },
+                }),
+                DhcpOptionCode::DNS_SERVER if len % 4 == 0 => Some(DhcpOption::DnsServer {
+                    addresses: DhcpAddressIterator {
+                        buffer: data,
+                        pos: 0,
+                    },
+                }),
+                DhcpOptionCode::REQUESTED_IP_ADDRESS if len == 4 => {
+                    Some(DhcpOption::RequestedIpAddress {
+                        address: data.try_into().unwrap(),
+                    })
+                }
+                DhcpOptionCode::LEASE_TIME if len == 4 => Some(DhcpOption::LeaseTime {
+                    seconds: u32::from_be_bytes(data.try_into().unwrap()),
+                }),
+                DhcpOptionCode::MESSAGE_TYPE if len == 1 => Some(DhcpOption::MessageType {
+                    message_type: data[0],
+                }),
+                DhcpOptionCode::SERVER_IDENTIFIER if len == 4 => {
+                    Some(DhcpOption::ServerIdentifier {
+                        address: data.try_into().unwrap(),
+                    })
+                }
+                DhcpOptionCode::PARAMETER_REQUEST_LIST => {
+                    Some(DhcpOption::ParameterRequestList { parameters: data })
+                }
+                DhcpOptionCode::CLIENT_IDENTIFIER => {
+                    Some(DhcpOption::ClientIdentifier { identifier: data })
+                }
+                _ => Some(DhcpOption::Raw {
+                    option,
+                    data: &self.buffer[pos..(pos + 2 + len)],
+                }),
+            }
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct DhcpAddressIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for DhcpAddressIterator<'a> {
+    type Item = [u8; 4];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.len() < self.pos + 4 {
+            return None;
+        }
+        let pos = self.pos;
+        self.pos += 4;
+        Some(self.buffer[pos..pos + 4].try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offer_round_trip() {
+        let mut dhcp_pdu = DhcpPdu::new();
+        dhcp_pdu.op(DhcpOp::BOOTREPLY);
+        dhcp_pdu.xid(0xDEADBEEF);
+        dhcp_pdu.broadcast(true);
+        dhcp_pdu.your_ip_address([192, 168, 0, 10]);
+        dhcp_pdu.client_hardware_address([0x02, 0, 0, 0, 0, 0x01]);
+        dhcp_pdu.message_type(DhcpMessageType::OFFER).unwrap();
+        dhcp_pdu.server_identifier([192, 168, 0, 1]).unwrap();
+        dhcp_pdu.lease_time(86400).unwrap();
+        dhcp_pdu.subnet_mask([255, 255, 255, 0]).unwrap();
+        dhcp_pdu.router(&[[192, 168, 0, 1]]).unwrap();
+        dhcp_pdu.dns_server(&[[8, 8, 8, 8], [8, 8, 4, 4]]).unwrap();
+        dhcp_pdu
+            .option(DhcpOptionCode::HOST_NAME, b"nucleo")
+            .unwrap();
+        dhcp_pdu.end().unwrap();
+        // Padded up to the minimum BOOTP message size
+        assert_eq!(dhcp_pdu.as_bytes().len(), 300);
+
+        let dhcp = DhcpParser::parse(dhcp_pdu.as_bytes()).unwrap();
+        assert_eq!(dhcp.op(), DhcpOp::BOOTREPLY);
+        assert_eq!(dhcp.xid(), 0xDEADBEEF);
+        assert!(dhcp.broadcast());
+        assert_eq!(dhcp.your_ip_address(), [192, 168, 0, 10]);
+        assert_eq!(dhcp.client_hardware_address(), [0x02, 0, 0, 0, 0, 0x01]);
+        assert_eq!(dhcp.message_type(), Some(DhcpMessageType::OFFER));
+        let mut options = dhcp.options();
+        assert!(matches!(
+            options.next(),
+            Some(DhcpOption::MessageType {
+                message_type: DhcpMessageType::OFFER
+            })
+        ));
+        assert!(matches!(
+            options.next(),
+            Some(DhcpOption::ServerIdentifier {
+                address: [192, 168, 0, 1]
+            })
+        ));
+        assert!(matches!(
+            options.next(),
+            Some(DhcpOption::LeaseTime { seconds: 86400 })
+        ));
+        assert!(matches!(
+            options.next(),
+            Some(DhcpOption::SubnetMask {
+                mask: [255, 255, 255, 0]
+            })
+        ));
+        let Some(DhcpOption::Router { mut addresses }) = options.next() else {
+            panic!("expected Router");
+        };
+        assert_eq!(addresses.next(), Some([192, 168, 0, 1]));
+        assert_eq!(addresses.next(), None);
+        let Some(DhcpOption::DnsServer { mut addresses }) = options.next() else {
+            panic!("expected DNS Server");
+        };
+        assert_eq!(addresses.next(), Some([8, 8, 8, 8]));
+        assert_eq!(addresses.next(), Some([8, 8, 4, 4]));
+        assert_eq!(addresses.next(), None);
+        assert!(matches!(
+            options.next(),
+            Some(DhcpOption::Raw {
+                option: DhcpOptionCode::HOST_NAME,
+                data: &[
+                    DhcpOptionCode::HOST_NAME,
+                    6,
+                    b'n',
+                    b'u',
+                    b'c',
+                    b'l',
+                    b'e',
+                    b'o'
+                ],
+            })
+        ));
+        // The padding after End is not parsed as options
+        assert!(options.next().is_none());
+    }
+
+    #[test]
+    fn discover_over_udp() {
+        let mut dhcp_pdu = DhcpPdu::new();
+        dhcp_pdu.op(DhcpOp::BOOTREQUEST);
+        dhcp_pdu.message_type(DhcpMessageType::DISCOVER).unwrap();
+        dhcp_pdu
+            .parameter_request_list(&[DhcpOptionCode::SUBNET_MASK, DhcpOptionCode::ROUTER])
+            .unwrap();
+        dhcp_pdu.end().unwrap();
+        let mut udp_pdu = crate::UdpPdu::new();
+        udp_pdu.source_port(crate::UdpPort::DHCP_CLIENT);
+        udp_pdu.destination_port(crate::UdpPort::DHCP_SERVER);
+        udp_pdu.inner(dhcp_pdu.as_bytes()).unwrap();
+        let udp = crate::UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(crate::Udp::Dhcp(dhcp)) = udp.inner() else {
+            panic!("expected DHCP");
+        };
+        assert_eq!(dhcp.message_type(), Some(DhcpMessageType::DISCOVER));
+        assert!(matches!(
+            dhcp.options().nth(1),
+            Some(DhcpOption::ParameterRequestList {
+                parameters: &[DhcpOptionCode::SUBNET_MASK, DhcpOptionCode::ROUTER],
+            })
+        ));
+    }
+
+    #[test]
+    fn truncated_and_malformed() {
+        let dhcp_pdu = DhcpPdu::new();
+        let bytes = dhcp_pdu.as_bytes();
+        assert!(matches!(
+            DhcpParser::parse(&bytes[..239]),
+            Err(Error::Truncated)
+        ));
+        let mut buffer = [0u8; 300];
+        buffer.copy_from_slice(bytes);
+        buffer[236] = 0;
+        assert!(matches!(DhcpParser::parse(&buffer), Err(Error::Malformed)));
+        let mut dhcp_pdu = DhcpPdu::new();
+        dhcp_pdu.hardware_length(16);
+        assert!(matches!(
+            DhcpParser::parse(dhcp_pdu.as_bytes()),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn truncated_option() {
+        let mut dhcp_pdu = DhcpPdu::new();
+        dhcp_pdu
+            .option(DhcpOptionCode::HOST_NAME, &[b'a'; 100])
+            .unwrap();
+        let bytes = dhcp_pdu.as_bytes();
+        let dhcp = DhcpParser::parse(&bytes[..300]).unwrap();
+        assert!(dhcp.options().next().is_none());
+    }
+
+    #[test]
+    fn oversized_options() {
+        let mut dhcp_pdu = DhcpPdu::new();
+        assert!(matches!(
+            dhcp_pdu.option(DhcpOptionCode::HOST_NAME, &[0u8; 256]),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            dhcp_pdu.router(&[[0u8; 4]; 64]),
+            Err(Error::Malformed)
+        ));
+        // 240 + 255 + 2 + 77 + 2 leaves no room for End
+        dhcp_pdu
+            .option(DhcpOptionCode::HOST_NAME, &[0u8; 255])
+            .unwrap();
+        dhcp_pdu
+            .option(DhcpOptionCode::HOST_NAME, &[0u8; 77])
+            .unwrap();
+        assert!(matches!(
+            dhcp_pdu.option(DhcpOptionCode::HOST_NAME, &[]),
+            Err(Error::Oversized)
+        ));
+        assert!(matches!(dhcp_pdu.end(), Err(Error::Oversized)));
+    }
+}
@@ -0,0 +1,1021 @@
+use core::convert::TryInto;
+
+use crate::{Error, Result};
+
+#[allow(non_snake_case)]
+pub mod DnsType {
+    pub const A: u16 = 1;
+    pub const NS: u16 = 2;
+    pub const CNAME: u16 = 5;
+    pub const SOA: u16 = 6;
+    pub const PTR: u16 = 12;
+    pub const MX: u16 = 15;
+    pub const TXT: u16 = 16;
+    pub const AAAA: u16 = 28;
+    pub const SRV: u16 = 33;
+    pub const OPT: u16 = 41;
+    pub const ANY: u16 = 255;
+}
+
+#[allow(non_snake_case)]
+pub mod DnsClass {
+    pub const IN: u16 = 1;
+    pub const ANY: u16 = 255;
+    // mDNS reuses the top bit as unicast-response (questions) or cache-flush (records)
+    pub const MDNS_FLAG: u16 = 0x8000;
+}
+
+#[allow(non_snake_case)]
+pub mod DnsOpcode {
+    pub const QUERY: u8 = 0;
+    pub const IQUERY: u8 = 1;
+    pub const STATUS: u8 = 2;
+    pub const NOTIFY: u8 = 4;
+    pub const UPDATE: u8 = 5;
+}
+
+#[allow(non_snake_case)]
+pub mod DnsRcode {
+    pub const NO_ERROR: u8 = 0;
+    pub const FORMAT_ERROR: u8 = 1;
+    pub const SERVER_FAILURE: u8 = 2;
+    pub const NAME_ERROR: u8 = 3;
+    pub const NOT_IMPLEMENTED: u8 = 4;
+    pub const REFUSED: u8 = 5;
+}
+
+pub struct DnsPdu {
+    buffer: [u8; 512],
+    inner_size: usize,
+    section: usize,
+    names: [u16; 32],
+    names_size: usize,
+}
+
+impl DnsPdu {
+    pub fn new() -> Self {
+        DnsPdu {
+            buffer: [0u8; 512],
+            inner_size: 0,
+            section: 0,
+            names: [0u16; 32],
+            names_size: 0,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..12 + self.inner_size]
+    }
+
+    pub fn id(&mut self, value: u16) {
+        self.buffer[0..=1].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn qr(&mut self, value: bool) {
+        let qr = if value {
+            self.buffer[2] | 0x80
+        } else {
+            self.buffer[2] & 0x7F
+        };
+        self.buffer[2] = qr;
+    }
+
+    pub fn opcode(&mut self, value: u8) {
+        let opcode = self.buffer[2] & 0x87 | (value & 0xF) << 3;
+        self.buffer[2] = opcode;
+    }
+
+    pub fn aa(&mut self, value: bool) {
+        let aa = if value {
+            self.buffer[2] | 0x4
+        } else {
+            self.buffer[2] & 0xFB
+        };
+        self.buffer[2] = aa;
+    }
+
+    pub fn tc(&mut self, value: bool) {
+        let tc = if value {
+            self.buffer[2] | 0x2
+        } else {
+            self.buffer[2] & 0xFD
+        };
+        self.buffer[2] = tc;
+    }
+
+    pub fn rd(&mut self, value: bool) {
+        let rd = if value {
+            self.buffer[2] | 0x1
+        } else {
+            self.buffer[2] & 0xFE
+        };
+        self.buffer[2] = rd;
+    }
+
+    pub fn ra(&mut self, value: bool) {
+        let ra = if value {
+            self.buffer[3] | 0x80
+        } else {
+            self.buffer[3] & 0x7F
+        };
+        self.buffer[3] = ra;
+    }
+
+    pub fn rcode(&mut self, value: u8) {
+        let rcode = self.buffer[3] & 0xF0 | value & 0xF;
+        self.buffer[3] = rcode;
+    }
+
+    pub fn question(&mut self, name: &DnsName, qtype: u16, qclass: u16) -> Result<()> {
+        self.enter_section(0)?;
+        let inner_size = self.inner_size;
+        let names_size = self.names_size;
+        let result = self.write_name(name, true).and_then(|_| {
+            let mut fixed = [0u8; 4];
+            fixed[0..=1].copy_from_slice(&qtype.to_be_bytes());
+            fixed[2..=3].copy_from_slice(&qclass.to_be_bytes());
+            self.write(&fixed)
+        });
+        self.finish_entry(result, inner_size, names_size)
+    }
+
+    pub fn answer(
+        &mut self,
+        name: &DnsName,
+        rclass: u16,
+        ttl: u32,
+        rdata: &DnsRData,
+    ) -> Result<()> {
+        self.record(1, name, rclass, ttl, rdata)
+    }
+
+    pub fn authority(
+        &mut self,
+        name: &DnsName,
+        rclass: u16,
+        ttl: u32,
+        rdata: &DnsRData,
+    ) -> Result<()> {
+        self.record(2, name, rclass, ttl, rdata)
+    }
+
+    pub fn additional(
+        &mut self,
+        name: &DnsName,
+        rclass: u16,
+        ttl: u32,
+        rdata: &DnsRData,
+    ) -> Result<()> {
+        self.record(3, name, rclass, ttl, rdata)
+    }
+
+    fn record(
+        &mut self,
+        section: usize,
+        name: &DnsName,
+        rclass: u16,
+        ttl: u32,
+        rdata: &DnsRData,
+    ) -> Result<()> {
+        self.enter_section(section)?;
+        let inner_size = self.inner_size;
+        let names_size = self.names_size;
+        let result = self.write_name(name, true).and_then(|_| {
+            let mut fixed = [0u8; 10];
+            fixed[0..=1].copy_from_slice(&rdata.rtype().to_be_bytes());
+            fixed[2..=3].copy_from_slice(&rclass.to_be_bytes());
+            fixed[4..=7].copy_from_slice(&ttl.to_be_bytes());
+            self.write(&fixed)?;
+            let rdata_start = 12 + self.inner_size;
+            self.write_rdata(rdata)?;
+            let rdlength = (12 + self.inner_size - rdata_start) as u16;
+            self.buffer[rdata_start - 2..rdata_start].copy_from_slice(&rdlength.to_be_bytes());
+            Ok(())
+        });
+        self.finish_entry(result, inner_size, names_size)
+    }
+
+    fn enter_section(&mut self, section: usize) -> Result<()> {
+        // Sections must be written in wire order: question, answer, authority, additional
+        if section < self.section {
+            return Err(Error::Malformed);
+        }
+        self.section = section;
+        Ok(())
+    }
+
+    fn finish_entry(
+        &mut self,
+        result: Result<()>,
+        inner_size: usize,
+        names_size: usize,
+    ) -> Result<()> {
+        match result {
+            Ok(()) => {
+                let pos = 4 + self.section * 2;
+                let count = u16::from_be_bytes(self.buffer[pos..pos + 2].try_into().unwrap());
+                self.buffer[pos..pos + 2].copy_from_slice(&(count + 1).to_be_bytes());
+                Ok(())
+            }
+            Err(error) => {
+                // Roll back a partially written entry so the message stays well-formed
+                self.inner_size = inner_size;
+                self.names_size = names_size;
+                Err(error)
+            }
+        }
+    }
+
+    fn write(&mut self, value: &[u8]) -> Result<()> {
+        let pos = 12 + self.inner_size;
+        let len = value.len();
+        if pos + len > 512 {
+            return Err(Error::Oversized);
+        }
+        self.buffer[pos..pos + len].copy_from_slice(value);
+        self.inner_size += len;
+        Ok(())
+    }
+
+    fn write_rdata(&mut self, rdata: &DnsRData) -> Result<()> {
+        match rdata {
+            DnsRData::Raw { data, .. } => self.write(data),
+            DnsRData::A(address) => self.write(address),
+            DnsRData::Aaaa(address) => self.write(address),
+            DnsRData::Ptr(name) | DnsRData::Cname(name) => self.write_name(name, true),
+            DnsRData::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => {
+                let mut fixed = [0u8; 6];
+                fixed[0..=1].copy_from_slice(&priority.to_be_bytes());
+                fixed[2..=3].copy_from_slice(&weight.to_be_bytes());
+                fixed[4..=5].copy_from_slice(&port.to_be_bytes());
+                self.write(&fixed)?;
+                // The SRV target must not be compressed (RFC 2782)
+                self.write_name(target, false)
+            }
+            DnsRData::Txt(strings) => {
+                for string in strings {
+                    if string.len() > 255 {
+                        return Err(Error::Malformed);
+                    }
+                    self.write(&[string.len() as u8])?;
+                    self.write(string)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn write_name(&mut self, name: &DnsName, compress: bool) -> Result<()> {
+        let mut labels: [&[u8]; 128] = [&[]; 128];
+        let mut count = 0;
+        let mut len = 1;
+        for label in name.labels() {
+            if label.is_empty() || label.len() > 63 || count == 127 {
+                return Err(Error::Malformed);
+            }
+            len += 1 + label.len();
+            labels[count] = label;
+            count += 1;
+        }
+        if len > 255 {
+            return Err(Error::Malformed);
+        }
+
+        for i in 0..count {
+            if compress {
+                if let Some(offset) = self.find_name(&labels[i..count]) {
+                    return self.write(&(0xC000 | offset).to_be_bytes());
+                }
+            }
+            let pos = 12 + self.inner_size;
+            if pos <= 0x3FFF && self.names_size < self.names.len() {
+                self.names[self.names_size] = pos as u16;
+                self.names_size += 1;
+            }
+            self.write(&[labels[i].len() as u8])?;
+            self.write(labels[i])?;
+        }
+        self.write(&[0x00])
+    }
+
+    fn find_name(&self, labels: &[&[u8]]) -> Option<u16> {
+        let written = &self.buffer[..12 + self.inner_size];
+        self.names[..self.names_size]
+            .iter()
+            .copied()
+            .find(|&offset| {
+                let mut candidate = DnsName::wire(written, offset as usize).labels();
+                labels.iter().all(
+                    |label| matches!(candidate.next(), Some(c) if c.eq_ignore_ascii_case(label)),
+                ) && candidate.next().is_none()
+            })
+    }
+}
+
+impl Default for DnsPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct DnsParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> DnsParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 12 {
+            return Err(Error::Truncated);
+        }
+        let pdu = DnsParser { buffer };
+        // Validates every name and record up front so that the iterators never see garbage
+        let mut pos = 12;
+        for _ in 0..pdu.qdcount() {
+            pos += name_length(buffer, pos)? + 4;
+            if buffer.len() < pos {
+                return Err(Error::Truncated);
+            }
+        }
+        let records = pdu.ancount() as usize + pdu.nscount() as usize + pdu.arcount() as usize;
+        for _ in 0..records {
+            pos += name_length(buffer, pos)?;
+            if buffer.len() < pos + 10 {
+                return Err(Error::Truncated);
+            }
+            let rtype = u16::from_be_bytes(buffer[pos..pos + 2].try_into().unwrap());
+            let rdlength = u16::from_be_bytes(buffer[pos + 8..pos + 10].try_into().unwrap());
+            pos += 10;
+            let end = pos + rdlength as usize;
+            if buffer.len() < end {
+                return Err(Error::Truncated);
+            }
+            let name_pos = match rtype {
+                DnsType::PTR | DnsType::CNAME => Some(pos),
+                DnsType::SRV if rdlength >= 6 => Some(pos + 6),
+                _ => None,
+            };
+            if let Some(name_pos) = name_pos {
+                if name_pos + name_length(&buffer[..end], name_pos)? != end {
+                    return Err(Error::Malformed);
+                }
+            }
+            pos = end;
+        }
+        Ok(pdu)
+    }
+
+    pub fn id(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[0..=1].try_into().unwrap())
+    }
+
+    pub fn qr(&'a self) -> bool {
+        self.buffer[2] & 0x80 != 0
+    }
+
+    pub fn opcode(&'a self) -> u8 {
+        (self.buffer[2] >> 3) & 0xF
+    }
+
+    pub fn aa(&'a self) -> bool {
+        self.buffer[2] & 0x4 != 0
+    }
+
+    pub fn tc(&'a self) -> bool {
+        self.buffer[2] & 0x2 != 0
+    }
+
+    pub fn rd(&'a self) -> bool {
+        self.buffer[2] & 0x1 != 0
+    }
+
+    pub fn ra(&'a self) -> bool {
+        self.buffer[3] & 0x80 != 0
+    }
+
+    pub fn rcode(&'a self) -> u8 {
+        self.buffer[3] & 0xF
+    }
+
+    pub fn qdcount(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[4..=5].try_into().unwrap())
+    }
+
+    pub fn ancount(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[6..=7].try_into().unwrap())
+    }
+
+    pub fn nscount(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[8..=9].try_into().unwrap())
+    }
+
+    pub fn arcount(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[10..=11].try_into().unwrap())
+    }
+
+    pub fn questions(&'a self) -> DnsQuestionIterator<'a> {
+        DnsQuestionIterator {
+            buffer: self.buffer,
+            pos: 12,
+            remaining: self.qdcount(),
+        }
+    }
+
+    pub fn answers(&'a self) -> DnsRecordIterator<'a> {
+        self.records(self.questions().end(), self.ancount())
+    }
+
+    pub fn authorities(&'a self) -> DnsRecordIterator<'a> {
+        self.records(self.answers().end(), self.nscount())
+    }
+
+    pub fn additionals(&'a self) -> DnsRecordIterator<'a> {
+        self.records(self.authorities().end(), self.arcount())
+    }
+
+    fn records(&'a self, pos: usize, remaining: u16) -> DnsRecordIterator<'a> {
+        DnsRecordIterator {
+            buffer: self.buffer,
+            pos,
+            remaining,
+        }
+    }
+}
+
+fn name_length(buffer: &[u8], pos: usize) -> Result<usize> {
+    // Only the octets up to the terminator or the first pointer belong to this position;
+    // the rest of the name is validated by walking its labels
+    let mut len = 0;
+    loop {
+        if buffer.len() <= pos + len {
+            return Err(Error::Truncated);
+        }
+        match buffer[pos + len] {
+            0 => {
+                len += 1;
+                break;
+            }
+            label if label & 0xC0 == 0xC0 => {
+                if buffer.len() <= pos + len + 1 {
+                    return Err(Error::Truncated);
+                }
+                len += 2;
+                break;
+            }
+            label if label & 0xC0 == 0 => len += 1 + label as usize,
+            _ => return Err(Error::Malformed),
+        }
+    }
+    let mut labels = DnsName::wire(buffer, pos).labels();
+    for _ in &mut labels {}
+    if labels.malformed {
+        return Err(Error::Malformed);
+    }
+    Ok(len)
+}
+
+#[derive(Copy, Clone)]
+pub struct DnsQuestion<'a> {
+    pub name: DnsName<'a>,
+    pub qtype: u16,
+    pub qclass: u16,
+}
+
+#[derive(Copy, Clone)]
+pub struct DnsQuestionIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+    remaining: u16,
+}
+
+impl<'a> DnsQuestionIterator<'a> {
+    fn end(mut self) -> usize {
+        for _ in &mut self {}
+        self.pos
+    }
+}
+
+impl<'a> Iterator for DnsQuestionIterator<'a> {
+    type Item = DnsQuestion<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let pos = self.pos;
+        let fixed = pos + name_length(self.buffer, pos).ok()?;
+        if self.buffer.len() < fixed + 4 {
+            return None;
+        }
+        self.pos = fixed + 4;
+        self.remaining -= 1;
+        Some(DnsQuestion {
+            name: DnsName::wire(self.buffer, pos),
+            qtype: u16::from_be_bytes(self.buffer[fixed..fixed + 2].try_into().unwrap()),
+            qclass: u16::from_be_bytes(self.buffer[fixed + 2..fixed + 4].try_into().unwrap()),
+        })
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct DnsRecord<'a> {
+    pub name: DnsName<'a>,
+    pub rtype: u16,
+    pub rclass: u16,
+    pub ttl: u32,
+    pub rdata: DnsRData<'a>,
+}
+
+#[derive(Copy, Clone)]
+pub struct DnsRecordIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+    remaining: u16,
+}
+
+impl<'a> DnsRecordIterator<'a> {
+    fn end(mut self) -> usize {
+        for _ in &mut self {}
+        self.pos
+    }
+}
+
+impl<'a> Iterator for DnsRecordIterator<'a> {
+    type Item = DnsRecord<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let buffer = self.buffer;
+        let pos = self.pos;
+        let fixed = pos + name_length(buffer, pos).ok()?;
+        if buffer.len() < fixed + 10 {
+            return None;
+        }
+        let rtype = u16::from_be_bytes(buffer[fixed..fixed + 2].try_into().unwrap());
+        let rdlength = u16::from_be_bytes(buffer[fixed + 8..fixed + 10].try_into().unwrap());
+        let start = fixed + 10;
+        let end = start + rdlength as usize;
+        if buffer.len() < end {
+            return None;
+        }
+        self.pos = end;
+        self.remaining -= 1;
+        let data = &buffer[start..end];
+        let rdata = match rtype {
+            DnsType::A if data.len() == 4 => DnsRData::A(data.try_into().unwrap()),
+            DnsType::AAAA if data.len() == 16 => DnsRData::Aaaa(data.try_into().unwrap()),
+            // Names in rdata may point anywhere in the message, so they keep the full buffer
+            DnsType::PTR => DnsRData::Ptr(DnsName::wire(&buffer[..end], start)),
+            DnsType::CNAME => DnsRData::Cname(DnsName::wire(&buffer[..end], start)),
+            DnsType::SRV if data.len() > 6 => DnsRData::Srv {
+                priority: u16::from_be_bytes(data[0..=1].try_into().unwrap()),
+                weight: u16::from_be_bytes(data[2..=3].try_into().unwrap()),
+                port: u16::from_be_bytes(data[4..=5].try_into().unwrap()),
+                target: DnsName::wire(&buffer[..end], start + 6),
+            },
+            DnsType::TXT => DnsRData::Txt(DnsTxt {
+                repr: DnsTxtRepr::Wire(data),
+            }),
+            _ => DnsRData::Raw { rtype, data },
+        };
+        Some(DnsRecord {
+            name: DnsName::wire(buffer, pos),
+            rtype,
+            rclass: u16::from_be_bytes(buffer[fixed + 2..fixed + 4].try_into().unwrap()),
+            ttl: u32::from_be_bytes(buffer[fixed + 4..fixed + 8].try_into().unwrap()),
+            rdata,
+        })
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum DnsRData<'a> {
+    Raw {
+        rtype: u16,
+        data: &'a [u8],
+    },
+    A([u8; 4]),
+    Aaaa([u8; 16]),
+    Ptr(DnsName<'a>),
+    Cname(DnsName<'a>),
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: DnsName<'a>,
+    },
+    Txt(DnsTxt<'a>),
+}
+
+impl<'a> DnsRData<'a> {
+    pub fn rtype(&self) -> u16 {
+        match self {
+            DnsRData::Raw { rtype, .. } => *rtype,
+            DnsRData::A(_) => DnsType::A,
+            DnsRData::Aaaa(_) => DnsType::AAAA,
+            DnsRData::Ptr(_) => DnsType::PTR,
+            DnsRData::Cname(_) => DnsType::CNAME,
+            DnsRData::Srv { .. } => DnsType::SRV,
+            DnsRData::Txt(_) => DnsType::TXT,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct DnsName<'a> {
+    repr: DnsNameRepr<'a>,
+}
+
+#[derive(Copy, Clone)]
+enum DnsNameRepr<'a> {
+    Wire { buffer: &'a [u8], pos: usize },
+    Dotted(&'a str),
+}
+
+impl<'a> DnsName<'a> {
+    pub fn dotted(value: &'a str) -> Self {
+        DnsName {
+            repr: DnsNameRepr::Dotted(value),
+        }
+    }
+
+    fn wire(buffer: &'a [u8], pos: usize) -> Self {
+        DnsName {
+            repr: DnsNameRepr::Wire { buffer, pos },
+        }
+    }
+
+    pub fn labels(&self) -> DnsLabelIterator<'a> {
+        DnsLabelIterator {
+            repr: self.repr,
+            limit: match self.repr {
+                DnsNameRepr::Wire { pos, .. } => pos,
+                DnsNameRepr::Dotted(_) => 0,
+            },
+            len: 0,
+            malformed: false,
+        }
+    }
+
+    pub fn matches(&self, other: &DnsName) -> bool {
+        let mut other = other.labels();
+        self.labels()
+            .all(|label| matches!(other.next(), Some(o) if o.eq_ignore_ascii_case(label)))
+            && other.next().is_none()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct DnsLabelIterator<'a> {
+    repr: DnsNameRepr<'a>,
+    limit: usize,
+    len: usize,
+    malformed: bool,
+}
+
+impl<'a> Iterator for DnsLabelIterator<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.repr {
+            DnsNameRepr::Dotted(value) => {
+                let value = value.strip_prefix('.').unwrap_or(value);
+                if value.is_empty() {
+                    return None;
+                }
+                let (label, rest) = value.split_at(value.find('.').unwrap_or(value.len()));
+                self.repr = DnsNameRepr::Dotted(rest);
+                Some(label.as_bytes())
+            }
+            DnsNameRepr::Wire { buffer, mut pos } => loop {
+                if self.malformed || buffer.len() <= pos {
+                    self.malformed = true;
+                    return None;
+                }
+                let label = buffer[pos];
+                match label & 0xC0 {
+                    0xC0 => {
+                        if buffer.len() <= pos + 1 {
+                            self.malformed = true;
+                            return None;
+                        }
+                        let target = u16::from_be_bytes([label & 0x3F, buffer[pos + 1]]) as usize;
+                        // Every jump must land before the previous one, which rules out loops
+                        if target >= self.limit {
+                            self.malformed = true;
+                            return None;
+                        }
+                        self.limit = target;
+                        pos = target;
+                    }
+                    0x00 if label == 0 => {
+                        self.repr = DnsNameRepr::Wire { buffer, pos };
+                        return None;
+                    }
+                    0x00 => {
+                        let len = label as usize;
+                        self.len += 1 + len;
+                        if buffer.len() < pos + 1 + len || self.len > 254 {
+                            self.malformed = true;
+                            return None;
+                        }
+                        self.repr = DnsNameRepr::Wire {
+                            buffer,
+                            pos: pos + 1 + len,
+                        };
+                        return Some(&buffer[pos + 1..pos + 1 + len]);
+                    }
+                    _ => {
+                        self.malformed = true;
+                        return None;
+                    }
+                }
+            },
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct DnsTxt<'a> {
+    repr: DnsTxtRepr<'a>,
+}
+
+#[derive(Copy, Clone)]
+enum DnsTxtRepr<'a> {
+    Wire(&'a [u8]),
+    Strings(&'a [&'a [u8]]),
+}
+
+impl<'a> DnsTxt<'a> {
+    pub fn strings(value: &'a [&'a [u8]]) -> Self {
+        DnsTxt {
+            repr: DnsTxtRepr::Strings(value),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &DnsTxt<'a> {
+    type Item = &'a [u8];
+    type IntoIter = DnsTxtIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        DnsTxtIterator { repr: self.repr }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct DnsTxtIterator<'a> {
+    repr: DnsTxtRepr<'a>,
+}
+
+impl<'a> Iterator for DnsTxtIterator<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.repr {
+            DnsTxtRepr::Wire(buffer) => {
+                let len = *buffer.first()? as usize;
+                if buffer.len() < 1 + len {
+                    return None;
+                }
+                self.repr = DnsTxtRepr::Wire(&buffer[1 + len..]);
+                Some(&buffer[1..1 + len])
+            }
+            DnsTxtRepr::Strings(strings) => {
+                let (string, rest) = strings.split_first()?;
+                self.repr = DnsTxtRepr::Strings(rest);
+                Some(string)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_round_trip() {
+        let mut dns_pdu = DnsPdu::new();
+        dns_pdu.id(0xBEEF);
+        dns_pdu.rd(true);
+        dns_pdu
+            .question(&DnsName::dotted("example.com"), DnsType::A, DnsClass::IN)
+            .unwrap();
+        let dns = DnsParser::parse(dns_pdu.as_bytes()).unwrap();
+        assert_eq!(dns.id(), 0xBEEF);
+        assert!(!dns.qr());
+        assert!(dns.rd());
+        assert_eq!(dns.opcode(), DnsOpcode::QUERY);
+        assert_eq!(dns.qdcount(), 1);
+        let mut questions = dns.questions();
+        let question = questions.next().unwrap();
+        assert!(question.name.matches(&DnsName::dotted("Example.COM.")));
+        assert_eq!(question.qtype, DnsType::A);
+        assert_eq!(question.qclass, DnsClass::IN);
+        assert!(questions.next().is_none());
+    }
+
+    #[test]
+    fn response_round_trip_with_compression() {
+        let mut dns_pdu = DnsPdu::new();
+        dns_pdu.qr(true);
+        dns_pdu.aa(true);
+        dns_pdu.rcode(DnsRcode::NO_ERROR);
+        dns_pdu
+            .question(
+                &DnsName::dotted("www.example.com"),
+                DnsType::A,
+                DnsClass::IN,
+            )
+            .unwrap();
+        dns_pdu
+            .answer(
+                &DnsName::dotted("www.example.com"),
+                DnsClass::IN,
+                300,
+                &DnsRData::Cname(DnsName::dotted("example.com")),
+            )
+            .unwrap();
+        dns_pdu
+            .answer(
+                &DnsName::dotted("example.com"),
+                DnsClass::IN,
+                300,
+                &DnsRData::A([93, 184, 216, 34]),
+            )
+            .unwrap();
+        dns_pdu
+            .additional(
+                &DnsName::dotted("example.com"),
+                DnsClass::IN,
+                300,
+                &DnsRData::Txt(DnsTxt::strings(&[b"v=spf1", b"-all"])),
+            )
+            .unwrap();
+        let bytes = dns_pdu.as_bytes();
+        // Question: 17 + 4; CNAME: 2 + 10 + 2; A: 2 + 10 + 4; TXT: 2 + 10 + 12
+        assert_eq!(bytes.len(), 12 + 21 + 14 + 16 + 24);
+        // The answer's owner name points back at the question name
+        assert_eq!(bytes[33..35], [0xC0, 0x0C]);
+
+        let dns = DnsParser::parse(bytes).unwrap();
+        assert!(dns.qr());
+        assert!(dns.aa());
+        assert_eq!(dns.ancount(), 2);
+        assert_eq!(dns.nscount(), 0);
+        assert_eq!(dns.arcount(), 1);
+        let mut answers = dns.answers();
+        let answer = answers.next().unwrap();
+        assert!(answer.name.matches(&DnsName::dotted("www.example.com")));
+        assert_eq!(answer.rtype, DnsType::CNAME);
+        assert_eq!(answer.ttl, 300);
+        let DnsRData::Cname(cname) = answer.rdata else {
+            panic!("expected CNAME");
+        };
+        assert!(cname.matches(&DnsName::dotted("example.com")));
+        let answer = answers.next().unwrap();
+        assert!(answer.name.matches(&DnsName::dotted("example.com")));
+        assert!(matches!(answer.rdata, DnsRData::A([93, 184, 216, 34])));
+        assert!(answers.next().is_none());
+        assert!(dns.authorities().next().is_none());
+        let additional = dns.additionals().next().unwrap();
+        let DnsRData::Txt(txt) = additional.rdata else {
+            panic!("expected TXT");
+        };
+        let mut strings = txt.into_iter();
+        assert_eq!(strings.next(), Some(&b"v=spf1"[..]));
+        assert_eq!(strings.next(), Some(&b"-all"[..]));
+        assert_eq!(strings.next(), None);
+    }
+
+    #[test]
+    fn srv_target_is_not_compressed() {
+        let mut dns_pdu = DnsPdu::new();
+        dns_pdu
+            .question(&DnsName::dotted("example.com"), DnsType::SRV, DnsClass::IN)
+            .unwrap();
+        dns_pdu
+            .answer(
+                &DnsName::dotted("_http._tcp.example.com"),
+                DnsClass::IN,
+                60,
+                &DnsRData::Srv {
+                    priority: 10,
+                    weight: 5,
+                    port: 8080,
+                    target: DnsName::dotted("example.com"),
+                },
+            )
+            .unwrap();
+        let bytes = dns_pdu.as_bytes();
+        assert!(bytes.ends_with(b"\x07example\x03com\x00"));
+        let dns = DnsParser::parse(bytes).unwrap();
+        let Some(DnsRecord {
+            rdata:
+                DnsRData::Srv {
+                    priority: 10,
+                    weight: 5,
+                    port: 8080,
+                    target,
+                },
+            ..
+        }) = dns.answers().next()
+        else {
+            panic!("expected SRV");
+        };
+        assert!(target.matches(&DnsName::dotted("example.com")));
+    }
+
+    #[test]
+    fn truncated() {
+        let mut dns_pdu = DnsPdu::new();
+        dns_pdu
+            .question(&DnsName::dotted("example.com"), DnsType::A, DnsClass::IN)
+            .unwrap();
+        let bytes = dns_pdu.as_bytes();
+        assert!(matches!(
+            DnsParser::parse(&bytes[..11]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            DnsParser::parse(&bytes[..20]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            DnsParser::parse(&bytes[..bytes.len() - 1]),
+            Err(Error::Truncated)
+        ));
+    }
+
+    #[test]
+    fn malformed_pointers() {
+        // A name that points at itself
+        let buffer = [0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0xC0, 0x0C, 0, 1, 0, 1];
+        assert!(matches!(DnsParser::parse(&buffer), Err(Error::Malformed)));
+        // A name that points forward
+        let buffer = [
+            0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0xC0, 0x12, 0, 1, 0, 1, 1, b'a', 0,
+        ];
+        assert!(matches!(DnsParser::parse(&buffer), Err(Error::Malformed)));
+        // A reserved label type
+        let buffer = [0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0x40, 0, 0, 1, 0, 1];
+        assert!(matches!(DnsParser::parse(&buffer), Err(Error::Malformed)));
+    }
+
+    #[test]
+    fn malformed_builder_input() {
+        let mut dns_pdu = DnsPdu::new();
+        let label = core::str::from_utf8(&[b'a'; 64]).unwrap();
+        assert!(matches!(
+            dns_pdu.question(&DnsName::dotted(label), DnsType::A, DnsClass::IN),
+            Err(Error::Malformed)
+        ));
+        // An empty label would terminate the name early
+        assert!(matches!(
+            dns_pdu.question(&DnsName::dotted("a..b"), DnsType::A, DnsClass::IN),
+            Err(Error::Malformed)
+        ));
+        assert_eq!(dns_pdu.as_bytes().len(), 12);
+        dns_pdu
+            .answer(&DnsName::dotted("a"), DnsClass::IN, 0, &DnsRData::A([0; 4]))
+            .unwrap();
+        // Sections are written in wire order
+        assert!(matches!(
+            dns_pdu.question(&DnsName::dotted("a"), DnsType::A, DnsClass::IN),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn oversized_record_is_rolled_back() {
+        let mut dns_pdu = DnsPdu::new();
+        let mut answers = 0;
+        let result = loop {
+            let result = dns_pdu.answer(
+                &DnsName::dotted("example.com"),
+                DnsClass::IN,
+                0,
+                &DnsRData::Raw {
+                    rtype: DnsType::TXT,
+                    data: &[0u8; 100],
+                },
+            );
+            if result.is_err() {
+                break result;
+            }
+            answers += 1;
+        };
+        assert!(matches!(result, Err(Error::Oversized)));
+        let dns = DnsParser::parse(dns_pdu.as_bytes()).unwrap();
+        assert_eq!(dns.ancount(), answers);
+        assert_eq!(dns.answers().count(), answers as usize);
+    }
+}
@@ -5,8 +5,13 @@ use crate::{util, Error, Result};
 #[allow(non_snake_case)]
 pub mod EtherType {
     pub const ARP: u16 = 0x0806;
+    pub const WAKE_ON_LAN: u16 = 0x0842;
     pub const IPV4: u16 = 0x0800;
+    pub const IPV6: u16 = 0x86DD;
     pub const DOT1Q: u16 = 0x8100;
+    pub const DOT1AD: u16 = 0x88A8;
+    pub const MAC_CONTROL: u16 = 0x8808;
+    pub const LLDP: u16 = 0x88CC;
 }
 
 #[derive(Copy, Clone)]
@@ -14,20 +19,67 @@ pub enum Ethernet<'a> {
     Raw(&'a [u8]),
     Arp(super::ArpParser<'a>),
     Ipv4(super::Ipv4Parser<'a>),
+    Ipv6(super::Ipv6Parser<'a>),
+    Lldp(super::LldpParser<'a>),
+    Llc(super::LlcParser<'a>),
+    MacControl(super::MacControlParser<'a>),
+    Wol(super::WolParser<'a>),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct VlanTag {
+    pub tpid: u16,
+    pub pcp: u8,
+    pub dei: bool,
+    pub vid: u16,
+}
+
+impl VlanTag {
+    fn from_bytes(value: &[u8]) -> Self {
+        let tci = u16::from_be_bytes(value[2..=3].try_into().unwrap());
+        VlanTag {
+            tpid: u16::from_be_bytes(value[0..=1].try_into().unwrap()),
+            pcp: (tci >> 13) as u8,
+            dei: tci & 0x1000 != 0,
+            vid: tci & 0x0FFF,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 4] {
+        let tci = (self.pcp as u16 & 0x7) << 13 | (self.dei as u16) << 12 | self.vid & 0x0FFF;
+        let mut bytes = [0u8; 4];
+        bytes[0..=1].copy_from_slice(&self.tpid.to_be_bytes());
+        bytes[2..=3].copy_from_slice(&tci.to_be_bytes());
+        bytes
+    }
+}
+
+fn is_vlan_tpid(value: u16) -> bool {
+    value == EtherType::DOT1Q || value == EtherType::DOT1AD
+}
+
+fn computed_ihl(buffer: &[u8]) -> usize {
+    let mut pos = 12;
+    while pos + 2 <= buffer.len()
+        && is_vlan_tpid(u16::from_be_bytes(buffer[pos..pos + 2].try_into().unwrap()))
+    {
+        pos += 4;
+    }
+    pos + 2
 }
 
 pub struct EthernetPdu {
-    buffer: [u8; 1522],
+    buffer: [u8; 1534],
     inner_size: usize,
 }
 
 impl EthernetPdu {
     pub fn new(ethertype: u16) -> Self {
         let mut ethernet_pdu = EthernetPdu {
-            buffer: [0u8; 1522],
+            buffer: [0u8; 1534],
             inner_size: 0,
         };
-        if ethertype == EtherType::DOT1Q {
+        if is_vlan_tpid(ethertype) {
             ethernet_pdu.tpid(ethertype);
         } else {
             ethernet_pdu.ethertype(ethertype);
@@ -46,10 +98,7 @@ impl EthernetPdu {
     }
 
     fn computed_ihl(&self) -> usize {
-        match self.get_tpid() {
-            EtherType::DOT1Q => 18,
-            _ => 14,
-        }
+        computed_ihl(&self.buffer)
     }
 
     pub fn destination_address(&mut self, value: [u8; 6]) {
@@ -69,15 +118,46 @@ impl EthernetPdu {
     }
 
     pub fn ethertype(&mut self, value: u16) {
-        if self.get_tpid() == EtherType::DOT1Q {
-            self.buffer[16..=17].copy_from_slice(&value.to_be_bytes());
-        } else {
-            self.buffer[12..=13].copy_from_slice(&value.to_be_bytes());
+        let ihl = self.computed_ihl();
+        self.buffer[ihl - 2..ihl].copy_from_slice(&value.to_be_bytes());
+    }
+
+    fn get_ethertype(&self) -> u16 {
+        let ihl = self.computed_ihl();
+        u16::from_be_bytes(self.buffer[ihl - 2..ihl].try_into().unwrap())
+    }
+
+    pub fn push_tag(&mut self, value: VlanTag) -> Result<()> {
+        if !is_vlan_tpid(value.tpid) {
+            return Err(Error::Malformed);
+        }
+        let ihl = self.computed_ihl();
+        let len = ihl + 4 + self.inner_size.max(46) + 4;
+        if len > 1534 {
+            return Err(Error::Oversized);
+        }
+        let end = ihl + self.inner_size;
+        // The new tag becomes the outermost one
+        self.buffer.copy_within(12..end, 16);
+        self.buffer[12..=15].copy_from_slice(&value.to_bytes());
+        // The old padding and FCS would otherwise show through the new padding
+        self.buffer[end + 4..len].fill(0);
+        Ok(())
+    }
+
+    pub fn pop_tag(&mut self) -> Option<VlanTag> {
+        if !is_vlan_tpid(self.get_tpid()) {
+            return None;
         }
+        let tag = VlanTag::from_bytes(&self.buffer[12..=15]);
+        let end = self.computed_ihl() + self.inner_size;
+        self.buffer.copy_within(16..end, 12);
+        self.buffer[end - 4..end].fill(0);
+        Some(tag)
     }
 
     pub fn vlan(&mut self, value: u16) {
-        if self.get_tpid() == EtherType::DOT1Q {
+        if is_vlan_tpid(self.get_tpid()) {
             let vlan = u16::from_be_bytes(self.buffer[14..=15].try_into().unwrap()) & 0xF000
                 | value & 0x0FFF;
             self.buffer[14..=15].copy_from_slice(&vlan.to_be_bytes());
@@ -85,14 +165,14 @@ impl EthernetPdu {
     }
 
     pub fn vlan_pcp(&mut self, value: u8) {
-        if self.get_tpid() == EtherType::DOT1Q {
+        if is_vlan_tpid(self.get_tpid()) {
             let vlan_pcp = self.buffer[14] & 0x1F | value << 5;
             self.buffer[14] = vlan_pcp;
         }
     }
 
     pub fn vlan_dei(&mut self, value: bool) {
-        if self.get_tpid() == EtherType::DOT1Q {
+        if is_vlan_tpid(self.get_tpid()) {
             let vlan_dei = if value {
                 self.buffer[14] | 0x10
             } else {
@@ -114,12 +194,16 @@ impl EthernetPdu {
 
     pub fn inner(&mut self, value: &[u8]) -> Result<()> {
         let len = value.len();
-        if len > 1500 {
+        let ihl = self.computed_ihl();
+        if len > 1500 || ihl + len + 4 > 1534 {
             return Err(Error::Oversized);
         }
         self.inner_size = len;
-        let ihl = self.computed_ihl();
         self.buffer[ihl..ihl + len].copy_from_slice(value);
+        if self.get_ethertype() < 0x0600 {
+            // A type field below 0x0600 is an 802.3 length, which excludes the padding
+            self.ethertype(len as u16);
+        }
         Ok(())
     }
 }
@@ -135,12 +219,17 @@ impl<'a> EthernetParser<'a> {
             return Err(Error::Truncated);
         }
         let pdu = EthernetParser { buffer };
-        if pdu.tpid() == EtherType::DOT1Q && buffer.len() < 18 {
+        if buffer.len() < pdu.computed_ihl() {
             return Err(Error::Truncated);
         }
-        if pdu.ethertype() < 0x0600 {
-            // 802.3 (LLC) frames are not supported
-            return Err(Error::Malformed);
+        if let Some(length) = pdu.length() {
+            if length > 1500 {
+                // Values between 1501 and 1535 are neither a length nor an EtherType
+                return Err(Error::Malformed);
+            }
+            if buffer.len() < pdu.computed_ihl() + length as usize {
+                return Err(Error::Truncated);
+            }
         }
         Ok(pdu)
     }
@@ -151,18 +240,23 @@ impl<'a> EthernetParser<'a> {
 
     pub fn into_inner(self) -> Result<Ethernet<'a>> {
         let rest = &self.buffer[self.computed_ihl()..];
+        if let Some(length) = self.length() {
+            let rest = &rest[..length as usize];
+            return Ok(Ethernet::Llc(super::LlcParser::parse(rest)?));
+        }
         Ok(match self.ethertype() {
             EtherType::ARP => Ethernet::Arp(super::ArpParser::parse(rest)?),
             EtherType::IPV4 => Ethernet::Ipv4(super::Ipv4Parser::parse(rest)?),
+            EtherType::IPV6 => Ethernet::Ipv6(super::Ipv6Parser::parse(rest)?),
+            EtherType::LLDP => Ethernet::Lldp(super::LldpParser::parse(rest)?),
+            EtherType::WAKE_ON_LAN => Ethernet::Wol(super::WolParser::parse(rest)?),
+            EtherType::MAC_CONTROL => Ethernet::MacControl(super::MacControlParser::parse(rest)?),
             _ => Ethernet::Raw(rest),
         })
     }
 
     pub fn computed_ihl(&'a self) -> usize {
-        match self.tpid() {
-            EtherType::DOT1Q => 18,
-            _ => 14,
-        }
+        computed_ihl(self.buffer)
     }
 
     pub fn destination_address(&'a self) -> [u8; 6] {
@@ -182,15 +276,26 @@ impl<'a> EthernetParser<'a> {
     }
 
     pub fn ethertype(&'a self) -> u16 {
-        match self.tpid() {
-            EtherType::DOT1Q => u16::from_be_bytes(self.buffer[16..=17].try_into().unwrap()),
-            ethertype => ethertype,
+        let ihl = self.computed_ihl();
+        u16::from_be_bytes(self.buffer[ihl - 2..ihl].try_into().unwrap())
+    }
+
+    pub fn vlan_tags(&self) -> VlanTagIterator<'a> {
+        VlanTagIterator {
+            buffer: &self.buffer[12..computed_ihl(self.buffer) - 2],
+        }
+    }
+
+    pub fn length(&'a self) -> Option<u16> {
+        match self.ethertype() {
+            length if length < 0x0600 => Some(length),
+            _ => None,
         }
     }
 
     pub fn vlan(&'a self) -> Option<u16> {
         match self.tpid() {
-            EtherType::DOT1Q => {
+            EtherType::DOT1Q | EtherType::DOT1AD => {
                 Some(u16::from_be_bytes(self.buffer[14..=15].try_into().unwrap()) & 0x0FFF)
             }
             _ => None,
@@ -199,15 +304,175 @@ impl<'a> EthernetParser<'a> {
 
     pub fn vlan_pcp(&'a self) -> Option<u8> {
         match self.tpid() {
-            EtherType::DOT1Q => Some((self.buffer[14] & 0xE0) >> 5),
+            EtherType::DOT1Q | EtherType::DOT1AD => Some((self.buffer[14] & 0xE0) >> 5),
             _ => None,
         }
     }
 
     pub fn vlan_dei(&'a self) -> Option<bool> {
         match self.tpid() {
-            EtherType::DOT1Q => Some(((self.buffer[14] & 0x10) >> 4) > 0),
+            EtherType::DOT1Q | EtherType::DOT1AD => Some(((self.buffer[14] & 0x10) >> 4) > 0),
             _ => None,
         }
     }
 }
+
+#[derive(Copy, Clone)]
+pub struct VlanTagIterator<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Iterator for VlanTagIterator<'a> {
+    type Item = VlanTag;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.len() < 4 {
+            return None;
+        }
+        let tag = VlanTag::from_bytes(&self.buffer[0..=3]);
+        self.buffer = &self.buffer[4..];
+        Some(tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stacked_tags_round_trip() {
+        let c_tag = VlanTag {
+            tpid: EtherType::DOT1Q,
+            pcp: 5,
+            dei: false,
+            vid: 100,
+        };
+        let s_tag = VlanTag {
+            tpid: EtherType::DOT1AD,
+            pcp: 3,
+            dei: true,
+            vid: 200,
+        };
+        let mut ethernet_pdu = EthernetPdu::new(0x9000);
+        ethernet_pdu.inner(&[0x5A; 50]).unwrap();
+        ethernet_pdu.push_tag(c_tag).unwrap();
+        ethernet_pdu.push_tag(s_tag).unwrap();
+        let bytes = ethernet_pdu.as_bytes();
+        assert_eq!(bytes.len(), 22 + 50 + 4);
+        assert_eq!(
+            bytes[12..22],
+            [0x88, 0xA8, 0x70, 0xC8, 0x81, 0x00, 0xA0, 0x64, 0x90, 0x00]
+        );
+        let ethernet = EthernetParser::parse(bytes).unwrap();
+        assert_eq!(ethernet.computed_ihl(), 22);
+        assert_eq!(ethernet.tpid(), EtherType::DOT1AD);
+        assert_eq!(ethernet.ethertype(), 0x9000);
+        assert_eq!(ethernet.vlan(), Some(200));
+        assert_eq!(ethernet.vlan_pcp(), Some(3));
+        assert_eq!(ethernet.vlan_dei(), Some(true));
+        assert!(ethernet.vlan_tags().eq([s_tag, c_tag]));
+        let Ok(Ethernet::Raw(rest)) = ethernet.inner() else {
+            panic!("expected raw payload");
+        };
+        assert_eq!(rest[..50], [0x5A; 50]);
+
+        assert_eq!(ethernet_pdu.pop_tag(), Some(s_tag));
+        assert_eq!(ethernet_pdu.pop_tag(), Some(c_tag));
+        assert_eq!(ethernet_pdu.pop_tag(), None);
+        let bytes = ethernet_pdu.as_bytes();
+        assert_eq!(bytes.len(), 14 + 50 + 4);
+        let ethernet = EthernetParser::parse(bytes).unwrap();
+        assert_eq!(ethernet.ethertype(), 0x9000);
+        assert_eq!(ethernet.vlan(), None);
+        assert_eq!(ethernet.vlan_tags().count(), 0);
+        let Ok(Ethernet::Raw(rest)) = ethernet.inner() else {
+            panic!("expected raw payload");
+        };
+        assert_eq!(rest[..50], [0x5A; 50]);
+    }
+
+    #[test]
+    fn single_tag() {
+        let mut ethernet_pdu = EthernetPdu::new(EtherType::DOT1Q);
+        ethernet_pdu.vlan(10);
+        ethernet_pdu.vlan_pcp(7);
+        ethernet_pdu.vlan_dei(true);
+        ethernet_pdu.ethertype(0x9000);
+        let ethernet = EthernetParser::parse(ethernet_pdu.as_bytes()).unwrap();
+        assert_eq!(ethernet.computed_ihl(), 18);
+        assert_eq!(ethernet.ethertype(), 0x9000);
+        assert!(ethernet.vlan_tags().eq([VlanTag {
+            tpid: EtherType::DOT1Q,
+            pcp: 7,
+            dei: true,
+            vid: 10,
+        }]));
+    }
+
+    #[test]
+    fn push_tag_limits() {
+        let tag = VlanTag {
+            tpid: EtherType::DOT1Q,
+            pcp: 0,
+            dei: false,
+            vid: 1,
+        };
+        let mut ethernet_pdu = EthernetPdu::new(0x9000);
+        assert!(matches!(
+            ethernet_pdu.push_tag(VlanTag {
+                tpid: 0x9100,
+                ..tag
+            }),
+            Err(Error::Malformed)
+        ));
+        ethernet_pdu.inner(&[0; 1500]).unwrap();
+        // Four tags still leave room for the full payload and FCS
+        for _ in 0..4 {
+            ethernet_pdu.push_tag(tag).unwrap();
+        }
+        assert!(matches!(ethernet_pdu.push_tag(tag), Err(Error::Oversized)));
+        assert_eq!(ethernet_pdu.as_bytes().len(), 1534);
+    }
+
+    #[test]
+    fn truncated() {
+        let mut ethernet_pdu = EthernetPdu::new(0x9000);
+        ethernet_pdu
+            .push_tag(VlanTag {
+                tpid: EtherType::DOT1AD,
+                pcp: 0,
+                dei: false,
+                vid: 1,
+            })
+            .unwrap();
+        let bytes = ethernet_pdu.as_bytes();
+        assert!(matches!(
+            EthernetParser::parse(&bytes[..13]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            EthernetParser::parse(&bytes[..16]),
+            Err(Error::Truncated)
+        ));
+        EthernetParser::parse(&bytes[..18]).unwrap();
+    }
+    #[test]
+    fn push_tag_clears_the_padding() {
+        let mut ethernet_pdu = EthernetPdu::new(0x9000);
+        ethernet_pdu.inner(&[0x5A; 10]).unwrap();
+        // Writes an FCS after the padding
+        ethernet_pdu.as_bytes();
+        ethernet_pdu
+            .push_tag(VlanTag {
+                tpid: EtherType::DOT1Q,
+                pcp: 0,
+                dei: false,
+                vid: 1,
+            })
+            .unwrap();
+        let bytes = ethernet_pdu.as_bytes();
+        assert_eq!(bytes.len(), 18 + 46 + 4);
+        assert_eq!(bytes[18..28], [0x5A; 10]);
+        assert_eq!(bytes[28..64], [0; 36]);
+    }
+}
@@ -1,5 +1,62 @@
 use crate::{util, Error, Result};
 
+#[allow(non_snake_case)]
+pub mod HttpMethod {
+    pub const GET: &str = "GET";
+    pub const HEAD: &str = "HEAD";
+    pub const POST: &str = "POST";
+    pub const PUT: &str = "PUT";
+    pub const DELETE: &str = "DELETE";
+    pub const CONNECT: &str = "CONNECT";
+    pub const OPTIONS: &str = "OPTIONS";
+    pub const TRACE: &str = "TRACE";
+    pub const PATCH: &str = "PATCH";
+}
+
+#[allow(non_snake_case)]
+pub mod HttpStatus {
+    pub const CONTINUE: u16 = 100;
+    pub const SWITCHING_PROTOCOLS: u16 = 101;
+    pub const OK: u16 = 200;
+    pub const CREATED: u16 = 201;
+    pub const ACCEPTED: u16 = 202;
+    pub const NO_CONTENT: u16 = 204;
+    pub const PARTIAL_CONTENT: u16 = 206;
+    pub const MOVED_PERMANENTLY: u16 = 301;
+    pub const FOUND: u16 = 302;
+    pub const SEE_OTHER: u16 = 303;
+    pub const NOT_MODIFIED: u16 = 304;
+    pub const TEMPORARY_REDIRECT: u16 = 307;
+    pub const PERMANENT_REDIRECT: u16 = 308;
+    pub const BAD_REQUEST: u16 = 400;
+    pub const UNAUTHORIZED: u16 = 401;
+    pub const FORBIDDEN: u16 = 403;
+    pub const NOT_FOUND: u16 = 404;
+    pub const METHOD_NOT_ALLOWED: u16 = 405;
+    pub const REQUEST_TIMEOUT: u16 = 408;
+    pub const LENGTH_REQUIRED: u16 = 411;
+    pub const PAYLOAD_TOO_LARGE: u16 = 413;
+    pub const URI_TOO_LONG: u16 = 414;
+    pub const UNSUPPORTED_MEDIA_TYPE: u16 = 415;
+    pub const INTERNAL_SERVER_ERROR: u16 = 500;
+    pub const NOT_IMPLEMENTED: u16 = 501;
+    pub const SERVICE_UNAVAILABLE: u16 = 503;
+    pub const HTTP_VERSION_NOT_SUPPORTED: u16 = 505;
+}
+
+#[allow(non_snake_case)]
+pub mod HttpContentType {
+    pub const TEXT_HTML: &str = "text/html; charset=utf-8";
+    pub const TEXT_PLAIN: &str = "text/plain; charset=utf-8";
+    pub const TEXT_CSS: &str = "text/css";
+    pub const TEXT_JAVASCRIPT: &str = "text/javascript";
+    pub const APPLICATION_JSON: &str = "application/json";
+    pub const APPLICATION_OCTET_STREAM: &str = "application/octet-stream";
+    pub const IMAGE_PNG: &str = "image/png";
+    pub const IMAGE_SVG: &str = "image/svg+xml";
+    pub const IMAGE_ICON: &str = "image/x-icon";
+}
+
 #[derive(Copy, Clone)]
 pub enum Http<'a> {
     Raw(&'a [u8]),
@@ -7,65 +64,480 @@ pub enum Http<'a> {
 
 pub struct HttpPdu {
     buffer: [u8; 1460],
+    header_size: usize,
     inner_size: usize,
+    status: u16,
+    has_content_length: bool,
+    is_chunked: bool,
+    is_terminated: bool,
 }
 
 impl HttpPdu {
     pub fn new() -> Self {
-        HttpPdu {
+        let mut http_pdu = HttpPdu {
             buffer: [0u8; 1460],
+            header_size: 0,
             inner_size: 0,
-        }
+            status: 0,
+            has_content_length: false,
+            is_chunked: false,
+            is_terminated: false,
+        };
+        http_pdu.status_line(HttpStatus::OK, reason_phrase(HttpStatus::OK));
+        http_pdu
     }
 
     pub fn as_bytes(&self) -> &[u8] {
-        &self.buffer[..self.computed_ihl() + self.inner_size]
+        &self.buffer[..self.header_size + self.inner_size]
     }
 
-    fn get_header(&self) -> &[u8] {
-        let str = core::str::from_utf8(&self.buffer).unwrap();
-        if let Some(body_start) = str.find("\n\n") {
-            str[..body_start + 2].as_bytes()
-        } else if let Some(body_start) = str.find("\r\n\r\n") {
-            str[..body_start + 4].as_bytes()
-        } else {
-            &[]
+    pub fn status(&mut self, code: u16) -> Result<()> {
+        self.status_with_reason(code, reason_phrase(code))
+    }
+
+    pub fn status_with_reason(&mut self, code: u16, reason: &str) -> Result<()> {
+        if !(100..=999).contains(&code) || !is_field_value(reason) || reason.len() > 256 {
+            return Err(Error::Malformed);
+        }
+        self.status_line(code, reason);
+        Ok(())
+    }
+
+    fn status_line(&mut self, code: u16, reason: &str) {
+        // Starts the response over, dropping any header or body written so far
+        self.header_size = 0;
+        self.inner_size = 0;
+        self.status = code;
+        self.has_content_length = false;
+        self.is_chunked = false;
+        self.is_terminated = false;
+        let mut digits = [0u8; 20];
+        for value in [
+            "HTTP/1.1 ".as_bytes(),
+            util::u64_to_ascii(code as u64, &mut digits),
+            " ".as_bytes(),
+            reason.as_bytes(),
+            "\r\n".as_bytes(),
+        ] {
+            self.buffer[self.header_size..self.header_size + value.len()].copy_from_slice(value);
+            self.header_size += value.len();
+        }
+    }
+
+    pub fn header(&mut self, name: &str, value: &str) -> Result<()> {
+        if self.is_terminated
+            || name.is_empty()
+            || !name.bytes().all(is_tchar)
+            || !is_field_value(value)
+        {
+            return Err(Error::Malformed);
+        }
+        let len = name.len() + 2 + value.len() + 2;
+        // Room is kept for the blank line that ends the header
+        if self.header_size + len + 2 > self.buffer.len() {
+            return Err(Error::Oversized);
         }
+        for value in [
+            name.as_bytes(),
+            ": ".as_bytes(),
+            value.as_bytes(),
+            "\r\n".as_bytes(),
+        ] {
+            self.buffer[self.header_size..self.header_size + value.len()].copy_from_slice(value);
+            self.header_size += value.len();
+        }
+        if name.eq_ignore_ascii_case("content-length") {
+            self.has_content_length = true;
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            self.is_chunked = is_chunked_coding(value);
+        }
+        Ok(())
+    }
+
+    pub fn content_type(&mut self, value: &str) -> Result<()> {
+        self.header("Content-Type", value)
     }
 
-    fn computed_ihl(&self) -> usize {
-        self.get_header().len()
+    pub fn content_length(&mut self, value: u64) -> Result<()> {
+        let mut digits = [0u8; 20];
+        let value = util::u64_to_ascii(value, &mut digits);
+        // Digits are always ASCII
+        self.header("Content-Length", core::str::from_utf8(value).unwrap())
+    }
+
+    pub fn location(&mut self, value: &str) -> Result<()> {
+        self.header("Location", value)
+    }
+
+    pub fn connection(&mut self, value: &str) -> Result<()> {
+        self.header("Connection", value)
+    }
+
+    pub fn cache_control(&mut self, value: &str) -> Result<()> {
+        self.header("Cache-Control", value)
+    }
+
+    pub fn chunked(&mut self) -> Result<()> {
+        self.header("Transfer-Encoding", "chunked")
     }
 
     pub fn inner(&mut self, value: &[u8]) -> Result<()> {
         let len = value.len();
-        let header = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: ".as_bytes();
-        self.buffer[..header.len()].copy_from_slice(header);
-        let tmp = util::usize_to_bytes(len);
-        let content_length = &tmp[4 - util::get_digit_from_usize(len) as usize..tmp.len()];
-        let content_length_index = header.len() + content_length.len();
-        self.buffer[header.len()..content_length_index].copy_from_slice(content_length);
-        self.buffer[content_length_index..content_length_index + 4]
-            .copy_from_slice("\r\n\r\n".as_bytes());
-
-        let ihl = self.computed_ihl();
-        if len > 1460 - ihl {
+        let mut header_size = self.header_size;
+        if !self.is_terminated {
+            let mut digits = [0u8; 20];
+            if self.needs_content_length() {
+                header_size += "Content-Length: \r\n".len()
+                    + util::u64_to_ascii(len as u64, &mut digits).len();
+            }
+            header_size += 2;
+        }
+        if header_size + len > self.buffer.len() {
             return Err(Error::Oversized);
         }
+        self.body(value)?;
+        Ok(())
+    }
+
+    pub fn body(&mut self, value: &[u8]) -> Result<usize> {
+        if !self.is_terminated {
+            if self.needs_content_length() {
+                // The whole body is expected here unless Content-Length was given beforehand
+                self.content_length(value.len() as u64)?;
+            }
+            self.buffer[self.header_size..self.header_size + 2].copy_from_slice("\r\n".as_bytes());
+            self.header_size += 2;
+            self.is_terminated = true;
+        }
+        let len = value.len().min(self.buffer.len() - self.header_size);
         self.inner_size = len;
-        self.buffer[ihl..ihl + len].copy_from_slice(value);
+        self.buffer[self.header_size..self.header_size + len].copy_from_slice(&value[..len]);
+        Ok(len)
+    }
+
+    pub fn continuation(&mut self, value: &[u8]) -> usize {
+        // Later segments of a body carry no header at all
+        self.header_size = 0;
+        self.is_terminated = true;
+        let len = value.len().min(self.buffer.len());
+        self.inner_size = len;
+        self.buffer[..len].copy_from_slice(&value[..len]);
+        len
+    }
+
+    pub fn chunk<F>(&mut self, producer: F) -> Result<usize>
+    where
+        F: FnOnce(&mut [u8]) -> usize,
+    {
+        if !self.is_chunked {
+            return Err(Error::Malformed);
+        }
+        if self.is_terminated {
+            // Every chunk after the first one starts a segment of its own
+            self.header_size = 0;
+        } else {
+            self.buffer[self.header_size..self.header_size + 2].copy_from_slice("\r\n".as_bytes());
+            self.header_size += 2;
+            self.is_terminated = true;
+        }
+        // The chunk size is written as three hex digits, leading zeros being allowed
+        let start = self.header_size + 5;
+        let end = self.buffer.len() - 2;
+        if start >= end {
+            return Err(Error::Oversized);
+        }
+        let len = producer(&mut self.buffer[start..end]).min(end - start);
+        if len == 0 {
+            // The last chunk has no data and an empty trailer section
+            self.buffer[self.header_size..self.header_size + 5]
+                .copy_from_slice("0\r\n\r\n".as_bytes());
+            self.inner_size = 5;
+            return Ok(0);
+        }
+        for (i, b) in self.buffer[self.header_size..self.header_size + 3]
+            .iter_mut()
+            .enumerate()
+        {
+            let digit = (len >> ((2 - i) * 4)) & 0xF;
+            *b = b"0123456789abcdef"[digit];
+        }
+        self.buffer[start - 2..start].copy_from_slice("\r\n".as_bytes());
+        self.buffer[start + len..start + len + 2].copy_from_slice("\r\n".as_bytes());
+        self.inner_size = 5 + len + 2;
+        Ok(len)
+    }
+
+    fn needs_content_length(&self) -> bool {
+        // 1xx, 204 and 304 responses never carry a body (RFC 7230, Section 3.3.2)
+        !self.has_content_length
+            && !self.is_chunked
+            && !(100..=199).contains(&self.status)
+            && self.status != HttpStatus::NO_CONTENT
+            && self.status != HttpStatus::NOT_MODIFIED
+    }
+}
+
+fn reason_phrase(code: u16) -> &'static str {
+    match code {
+        HttpStatus::CONTINUE => "Continue",
+        HttpStatus::SWITCHING_PROTOCOLS => "Switching Protocols",
+        HttpStatus::OK => "OK",
+        HttpStatus::CREATED => "Created",
+        HttpStatus::ACCEPTED => "Accepted",
+        HttpStatus::NO_CONTENT => "No Content",
+        HttpStatus::PARTIAL_CONTENT => "Partial Content",
+        HttpStatus::MOVED_PERMANENTLY => "Moved Permanently",
+        HttpStatus::FOUND => "Found",
+        HttpStatus::SEE_OTHER => "See Other",
+        HttpStatus::NOT_MODIFIED => "Not Modified",
+        HttpStatus::TEMPORARY_REDIRECT => "Temporary Redirect",
+        HttpStatus::PERMANENT_REDIRECT => "Permanent Redirect",
+        HttpStatus::BAD_REQUEST => "Bad Request",
+        HttpStatus::UNAUTHORIZED => "Unauthorized",
+        HttpStatus::FORBIDDEN => "Forbidden",
+        HttpStatus::NOT_FOUND => "Not Found",
+        HttpStatus::METHOD_NOT_ALLOWED => "Method Not Allowed",
+        HttpStatus::REQUEST_TIMEOUT => "Request Timeout",
+        HttpStatus::LENGTH_REQUIRED => "Length Required",
+        HttpStatus::PAYLOAD_TOO_LARGE => "Payload Too Large",
+        HttpStatus::URI_TOO_LONG => "URI Too Long",
+        HttpStatus::UNSUPPORTED_MEDIA_TYPE => "Unsupported Media Type",
+        HttpStatus::INTERNAL_SERVER_ERROR => "Internal Server Error",
+        HttpStatus::NOT_IMPLEMENTED => "Not Implemented",
+        HttpStatus::SERVICE_UNAVAILABLE => "Service Unavailable",
+        HttpStatus::HTTP_VERSION_NOT_SUPPORTED => "HTTP Version Not Supported",
+        // The reason phrase may be empty (RFC 7230, Section 3.1.2)
+        _ => "",
+    }
+}
+
+fn is_field_value(value: &str) -> bool {
+    // Anything but control characters, which keeps CR and LF from splitting the header
+    value
+        .bytes()
+        .all(|b| b == b'\t' || (b >= 0x20 && b != 0x7F))
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum HttpChunkedState {
+    Size,
+    Extension,
+    SizeLf,
+    Data,
+    DataCr,
+    DataLf,
+    Trailer,
+    Done,
+}
+
+// Far more than the board could ever store, yet small enough to never overflow
+const MAX_CHUNK_SIZE: u64 = 0x0100_0000;
+
+pub struct HttpChunkedDecoder {
+    state: HttpChunkedState,
+    size: u64,
+    digits: usize,
+    trailer: [u8; 512],
+    trailer_size: usize,
+    line_start: usize,
+}
+
+impl HttpChunkedDecoder {
+    pub fn new() -> Self {
+        HttpChunkedDecoder {
+            state: HttpChunkedState::Size,
+            size: 0,
+            digits: 0,
+            trailer: [0u8; 512],
+            trailer_size: 0,
+            line_start: 0,
+        }
+    }
+
+    pub fn decode<'a, 'b>(&'a mut self, buffer: &'b [u8]) -> HttpChunkedIterator<'a, 'b> {
+        HttpChunkedIterator {
+            decoder: self,
+            buffer,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == HttpChunkedState::Done
+    }
+
+    pub fn trailers(&self) -> HttpHeaderIterator<'_> {
+        HttpHeaderIterator {
+            lines: HttpLineIterator {
+                buffer: &self.trailer[..self.trailer_size],
+            },
+        }
+    }
+
+    fn step(&mut self, b: u8) -> Result<()> {
+        self.state = match (self.state, b) {
+            (HttpChunkedState::Size, b) if b.is_ascii_hexdigit() => {
+                let digit = (b as char).to_digit(16).unwrap() as u64;
+                self.size = self.size * 16 + digit;
+                if self.size > MAX_CHUNK_SIZE {
+                    return Err(Error::Oversized);
+                }
+                self.digits += 1;
+                HttpChunkedState::Size
+            }
+            (HttpChunkedState::Size, b';' | b' ' | b'\t') if self.digits > 0 => {
+                // Chunk extensions are skipped (RFC 7230, Section 4.1.1)
+                HttpChunkedState::Extension
+            }
+            (HttpChunkedState::Size, b'\r') if self.digits > 0 => HttpChunkedState::SizeLf,
+            (HttpChunkedState::Size, b'\n') if self.digits > 0 => self.size_line_end(),
+            (HttpChunkedState::Extension, b'\n') | (HttpChunkedState::SizeLf, b'\n') => {
+                self.size_line_end()
+            }
+            (HttpChunkedState::Extension, b) if b == b'\t' || (b >= 0x20 && b != 0x7F) => {
+                HttpChunkedState::Extension
+            }
+            (HttpChunkedState::Extension, b'\r') => HttpChunkedState::SizeLf,
+            (HttpChunkedState::DataCr, b'\r') => HttpChunkedState::DataLf,
+            (HttpChunkedState::DataCr, b'\n') | (HttpChunkedState::DataLf, b'\n') => {
+                HttpChunkedState::Size
+            }
+            (HttpChunkedState::Trailer, b) => {
+                if self.trailer_size == self.trailer.len() {
+                    return Err(Error::Oversized);
+                }
+                self.trailer[self.trailer_size] = b;
+                self.trailer_size += 1;
+                if b != b'\n' {
+                    return Ok(());
+                }
+                let (line, _) = split_line(&self.trailer[self.line_start..self.trailer_size]);
+                if line.is_empty() {
+                    HttpChunkedState::Done
+                } else {
+                    split_header(line)?;
+                    self.line_start = self.trailer_size;
+                    HttpChunkedState::Trailer
+                }
+            }
+            _ => return Err(Error::Malformed),
+        };
         Ok(())
     }
+
+    fn size_line_end(&mut self) -> HttpChunkedState {
+        self.digits = 0;
+        if self.size == 0 {
+            // The last chunk is followed by the trailer section
+            HttpChunkedState::Trailer
+        } else {
+            HttpChunkedState::Data
+        }
+    }
+}
+
+impl Default for HttpChunkedDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct HttpChunkedIterator<'a, 'b> {
+    decoder: &'a mut HttpChunkedDecoder,
+    buffer: &'b [u8],
+}
+
+impl<'a, 'b> HttpChunkedIterator<'a, 'b> {
+    pub fn remainder(&self) -> &'b [u8] {
+        // Whatever follows the last chunk belongs to the next message
+        self.buffer
+    }
+}
+
+impl<'a, 'b> Iterator for HttpChunkedIterator<'a, 'b> {
+    type Item = Result<&'b [u8]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((&b, rest)) = self.buffer.split_first() {
+            match self.decoder.state {
+                HttpChunkedState::Done => return None,
+                HttpChunkedState::Data => {
+                    // Compared as u64 so that large sizes do not truncate on 32-bit targets
+                    let len = (self.buffer.len() as u64).min(self.decoder.size) as usize;
+                    let data = &self.buffer[..len];
+                    self.buffer = &self.buffer[len..];
+                    self.decoder.size -= len as u64;
+                    if self.decoder.size == 0 {
+                        self.decoder.state = HttpChunkedState::DataCr;
+                    }
+                    return Some(Ok(data));
+                }
+                _ => {
+                    if let Err(err) = self.decoder.step(b) {
+                        self.buffer = &[];
+                        return Some(Err(err));
+                    }
+                    self.buffer = rest;
+                }
+            }
+        }
+        None
+    }
 }
 
 #[derive(Copy, Clone)]
 pub struct HttpParser<'a> {
     buffer: &'a [u8],
+    header_length: usize,
 }
 
 impl<'a> HttpParser<'a> {
     pub fn parse(buffer: &'a [u8]) -> Result<Self> {
-        Ok(HttpParser { buffer })
+        // The rest of the header may still be on its way in the next segment
+        let header_length = header_end(buffer).ok_or(Error::Incomplete)?;
+        let pdu = HttpParser {
+            buffer,
+            header_length,
+        };
+        let (request_line, _) = split_line(buffer);
+        let mut parts = request_line.split(|&b| b == b' ');
+        let method = parts.next().ok_or(Error::Malformed)?;
+        let target = parts.next().ok_or(Error::Malformed)?;
+        let version = parts.next().ok_or(Error::Malformed)?;
+        if parts.next().is_some()
+            || method.is_empty()
+            || !method.iter().all(|&b| is_tchar(b))
+            || target.is_empty()
+            || !target.iter().all(|&b| b.is_ascii_graphic())
+            || !matches!(version, [b'H', b'T', b'T', b'P', b'/', b'1', b'.', minor] if minor.is_ascii_digit())
+        {
+            return Err(Error::Malformed);
+        }
+        let mut content_length = None;
+        let mut chunked = false;
+        for line in pdu.header_lines() {
+            let (name, value) = split_header(line)?;
+            if name.eq_ignore_ascii_case("content-length") {
+                // Only 1*DIGIT is allowed, so no sign (RFC 7230, Section 3.3.2)
+                if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(Error::Malformed);
+                }
+                let value = value.parse::<usize>().map_err(|_| Error::Malformed)?;
+                if content_length.replace(value).unwrap_or(value) != value {
+                    return Err(Error::Malformed);
+                }
+            } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                // Only a final chunked coding delimits a request body (RFC 7230, Section 3.3.3)
+                if !is_chunked_coding(value) {
+                    return Err(Error::Malformed);
+                }
+                chunked = true;
+            }
+        }
+        if chunked && content_length.is_some() {
+            // Ambiguous framing is a request smuggling vector
+            return Err(Error::Malformed);
+        }
+        Ok(pdu)
     }
 
     pub fn inner(&'a self) -> Result<Http<'a>> {
@@ -73,32 +545,1030 @@ impl<'a> HttpParser<'a> {
     }
 
     pub fn into_inner(self) -> Result<Http<'a>> {
-        let str = core::str::from_utf8(self.buffer).unwrap();
-        if let Some(body_start) = str.find("\n\n") {
-            Ok(Http::Raw(str[(body_start + 2)..].as_bytes()))
-        } else if let Some(body_start) = str.find("\r\n\r\n") {
-            Ok(Http::Raw(str[(body_start + 4)..].as_bytes()))
-        } else {
-            Ok(Http::Raw(&[]))
+        Ok(Http::Raw(self.body()))
+    }
+
+    pub fn method(&'a self) -> &'a str {
+        self.request_line_part(0)
+    }
+
+    pub fn target(&'a self) -> &'a str {
+        self.request_line_part(1)
+    }
+
+    pub fn path(&'a self) -> &'a str {
+        let target = self.target();
+        match target.find('?') {
+            Some(query_start) => &target[..query_start],
+            None => target,
+        }
+    }
+
+    pub fn query(&'a self) -> Option<&'a str> {
+        let target = self.target();
+        target
+            .find('?')
+            .map(|query_start| &target[query_start + 1..])
+    }
+
+    pub fn version(&'a self) -> &'a str {
+        self.request_line_part(2)
+    }
+
+    pub fn query_fields(&'a self) -> HttpFormIterator<'a> {
+        HttpFormIterator {
+            buffer: self.query().unwrap_or(""),
+        }
+    }
+
+    pub fn headers(&self) -> HttpHeaderIterator<'a> {
+        HttpHeaderIterator {
+            lines: self.header_lines(),
+        }
+    }
+
+    pub fn header(&'a self, name: &str) -> Option<&'a str> {
+        self.find_header(name)
+    }
+
+    pub fn content_length(&'a self) -> Option<usize> {
+        self.find_content_length()
+    }
+
+    pub fn multipart_boundary(&'a self) -> Option<&'a str> {
+        let content_type = self.find_header("content-type")?;
+        let media_type = content_type.split(';').next().unwrap_or("");
+        if !media_type
+            .trim_matches(is_ows)
+            .eq_ignore_ascii_case("multipart/form-data")
+        {
+            return None;
         }
+        parameter(content_type, "boundary")
+    }
+
+    pub fn is_chunked(&'a self) -> bool {
+        // parse() only lets through a Transfer-Encoding that ends in chunked
+        self.find_header("transfer-encoding").is_some()
+    }
+
+    pub fn computed_header_length(&'a self) -> usize {
+        self.header_length
+    }
+
+    pub fn body(&self) -> &'a [u8] {
+        let rest = &self.buffer[self.header_length..];
+        if self.find_header("transfer-encoding").is_some() {
+            // Left to the chunked decoder
+            return rest;
+        }
+        // Without Content-Length a request has no body (RFC 7230, Section 3.3.3)
+        let len = self.find_content_length().unwrap_or(0);
+        &rest[..rest.len().min(len)]
+    }
+
+    fn find_header(&self, name: &str) -> Option<&'a str> {
+        self.headers()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .map(|header| header.value)
+    }
+
+    fn find_content_length(&self) -> Option<usize> {
+        self.find_header("content-length")
+            .and_then(|value| value.parse().ok())
     }
 
-    pub fn header(&'a self) -> &[u8] {
-        let str = core::str::from_utf8(self.buffer).unwrap();
-        if let Some(body_start) = str.find("\n\n") {
-            str[..body_start + 2].as_bytes()
-        } else if let Some(body_start) = str.find("\r\n\r\n") {
-            str[..body_start + 4].as_bytes()
+    fn request_line_part(&self, index: usize) -> &'a str {
+        let (request_line, _) = split_line(self.buffer);
+        let part = request_line.split(|&b| b == b' ').nth(index).unwrap_or(&[]);
+        // Validated as ASCII by parse()
+        core::str::from_utf8(part).unwrap_or("")
+    }
+
+    fn header_lines(&self) -> HttpLineIterator<'a> {
+        let header = &self.buffer[..self.header_length];
+        HttpLineIterator {
+            buffer: split_line(header).1,
+        }
+    }
+}
+
+fn is_tchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+fn is_ows(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+fn is_chunked_coding(value: &str) -> bool {
+    let last = value.rsplit(',').next().unwrap_or("").trim_matches(is_ows);
+    last.eq_ignore_ascii_case("chunked")
+}
+
+fn header_end(buffer: &[u8]) -> Option<usize> {
+    // A bare LF is accepted as a line terminator (RFC 7230, Section 3.5)
+    buffer.iter().enumerate().find_map(|(pos, &b)| {
+        if b != b'\n' {
+            None
+        } else if buffer[pos + 1..].starts_with(b"\r\n") {
+            Some(pos + 3)
+        } else if buffer[pos + 1..].starts_with(b"\n") {
+            Some(pos + 2)
         } else {
-            &[]
+            None
+        }
+    })
+}
+
+fn split_line(buffer: &[u8]) -> (&[u8], &[u8]) {
+    let (line, rest) = match buffer.iter().position(|&b| b == b'\n') {
+        Some(end) => (&buffer[..end], &buffer[end + 1..]),
+        None => (buffer, &buffer[buffer.len()..]),
+    };
+    (line.strip_suffix(b"\r").unwrap_or(line), rest)
+}
+
+fn split_header(line: &[u8]) -> Result<(&str, &str)> {
+    let line = core::str::from_utf8(line).map_err(|_| Error::Malformed)?;
+    let (name, value) = line.split_once(':').ok_or(Error::Malformed)?;
+    // Whitespace before the colon and obsolete line folding are rejected (RFC 7230, Section 3.2.4)
+    if name.is_empty() || !name.bytes().all(is_tchar) {
+        return Err(Error::Malformed);
+    }
+    Ok((name, value.trim_matches(is_ows)))
+}
+
+#[derive(Copy, Clone)]
+struct HttpLineIterator<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Iterator for HttpLineIterator<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (line, rest) = split_line(self.buffer);
+        if line.is_empty() {
+            return None;
+        }
+        self.buffer = rest;
+        Some(line)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct HttpHeader<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+#[derive(Copy, Clone)]
+pub struct HttpHeaderIterator<'a> {
+    lines: HttpLineIterator<'a>,
+}
+
+impl<'a> Iterator for HttpHeaderIterator<'a> {
+    type Item = HttpHeader<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (name, value) = split_header(self.lines.next()?).ok()?;
+        Some(HttpHeader { name, value })
+    }
+}
+
+fn parameter<'a>(value: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = value.split_once(';')?.1;
+    loop {
+        let (key, after) = rest.split_once('=')?;
+        let after = after.trim_start_matches(is_ows);
+        // A quoted value may itself contain semicolons (RFC 7231, Section 3.1.1.1)
+        let (param, next) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                let next = quoted[end + 1..]
+                    .split_once(';')
+                    .map_or("", |(_, next)| next);
+                (&quoted[..end], next)
+            }
+            None => match after.split_once(';') {
+                Some((param, next)) => (param.trim_end_matches(is_ows), next),
+                None => (after.trim_end_matches(is_ows), ""),
+            },
+        };
+        if key.trim_matches(is_ows).eq_ignore_ascii_case(name) {
+            return Some(param);
+        }
+        rest = next;
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct HttpPercentDecoder<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> HttpPercentDecoder<'a> {
+    pub fn decode_into(self, buffer: &mut [u8]) -> Result<&[u8]> {
+        let mut len = 0;
+        for b in self {
+            if len == buffer.len() {
+                return Err(Error::Oversized);
+            }
+            buffer[len] = b;
+            len += 1;
+        }
+        Ok(&buffer[..len])
+    }
+}
+
+impl<'a> Iterator for HttpPercentDecoder<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (b, rest) = match self.buffer {
+            [b'%', high, low, rest @ ..] if high.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+                let high = (*high as char).to_digit(16).unwrap() as u8;
+                let low = (*low as char).to_digit(16).unwrap() as u8;
+                (high << 4 | low, rest)
+            }
+            // Form encoding turns spaces into plus signs
+            [b'+', rest @ ..] => (b' ', rest),
+            // A stray percent sign is kept as it is, as browsers do
+            [b, rest @ ..] => (*b, rest),
+            [] => return None,
+        };
+        self.buffer = rest;
+        Some(b)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct HttpFormField<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+impl<'a> HttpFormField<'a> {
+    pub fn decoded_name(&self) -> HttpPercentDecoder<'a> {
+        HttpPercentDecoder {
+            buffer: self.name.as_bytes(),
+        }
+    }
+
+    pub fn decoded_value(&self) -> HttpPercentDecoder<'a> {
+        HttpPercentDecoder {
+            buffer: self.value.as_bytes(),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct HttpFormIterator<'a> {
+    buffer: &'a str,
+}
+
+impl<'a> Iterator for HttpFormIterator<'a> {
+    type Item = HttpFormField<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.buffer.is_empty() {
+                return None;
+            }
+            let (pair, rest) = self.buffer.split_once('&').unwrap_or((self.buffer, ""));
+            self.buffer = rest;
+            if pair.is_empty() {
+                continue;
+            }
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            return Some(HttpFormField { name, value });
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct HttpFormParser<'a> {
+    buffer: &'a str,
+}
+
+impl<'a> HttpFormParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        let buffer = core::str::from_utf8(buffer).map_err(|_| Error::Malformed)?;
+        Ok(HttpFormParser { buffer })
+    }
+
+    pub fn fields(&self) -> HttpFormIterator<'a> {
+        HttpFormIterator {
+            buffer: self.buffer,
+        }
+    }
+
+    pub fn field(&'a self, name: &str) -> Option<HttpFormField<'a>> {
+        self.fields()
+            .find(|field| field.decoded_name().eq(name.bytes()))
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct HttpPart<'a> {
+    lines: HttpLineIterator<'a>,
+}
+
+impl<'a> HttpPart<'a> {
+    pub fn headers(&self) -> HttpHeaderIterator<'a> {
+        HttpHeaderIterator { lines: self.lines }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        self.headers()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .map(|header| header.value)
+    }
+
+    pub fn name(&self) -> Option<&'a str> {
+        parameter(self.header("content-disposition")?, "name")
+    }
+
+    pub fn filename(&self) -> Option<&'a str> {
+        parameter(self.header("content-disposition")?, "filename")
+    }
+
+    pub fn content_type(&self) -> Option<&'a str> {
+        self.header("content-type")
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum HttpMultipart<'a> {
+    Part(HttpPart<'a>),
+    Data(&'a [u8]),
+    PartEnd,
+    End,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum HttpMultipartState {
+    Preamble,
+    Delimiter,
+    Padding,
+    DelimiterLf,
+    Close,
+    Headers,
+    Body,
+    Epilogue,
+}
+
+pub struct HttpMultipartDecoder {
+    state: HttpMultipartState,
+    delimiter: [u8; 74],
+    delimiter_size: usize,
+    held: [u8; 74],
+    held_size: usize,
+    header: [u8; 512],
+    header_size: usize,
+    line_start: usize,
+}
+
+impl HttpMultipartDecoder {
+    pub fn new(boundary: &str) -> Result<Self> {
+        // Boundaries are 1 to 70 characters long (RFC 2046, Section 5.1.1)
+        if boundary.is_empty() || boundary.len() > 70 || !is_field_value(boundary) {
+            return Err(Error::Malformed);
+        }
+        let mut decoder = HttpMultipartDecoder {
+            state: HttpMultipartState::Preamble,
+            delimiter: [0u8; 74],
+            delimiter_size: 4 + boundary.len(),
+            held: [0u8; 74],
+            held_size: 2,
+            header: [0u8; 512],
+            header_size: 0,
+            line_start: 0,
+        };
+        decoder.delimiter[0..4].copy_from_slice("\r\n--".as_bytes());
+        decoder.delimiter[4..decoder.delimiter_size].copy_from_slice(boundary.as_bytes());
+        // The first delimiter is not preceded by a line break, so one is made up
+        decoder.held[0..2].copy_from_slice("\r\n".as_bytes());
+        Ok(decoder)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == HttpMultipartState::Epilogue
+    }
+
+    pub fn decode<'a>(
+        &'a mut self,
+        buffer: &'a [u8],
+    ) -> Result<(usize, Option<HttpMultipart<'a>>)> {
+        let mut pos = 0;
+        while pos < buffer.len() {
+            let rest = &buffer[pos..];
+            match self.state {
+                HttpMultipartState::Preamble | HttpMultipartState::Body => {
+                    let is_body = self.state == HttpMultipartState::Body;
+                    let delimiter = &self.delimiter[..self.delimiter_size];
+                    if self.held_size > 0 {
+                        let len = rest.len().min(self.delimiter_size - self.held_size);
+                        if rest[..len] != delimiter[self.held_size..self.held_size + len] {
+                            // The delimiter only ever starts at a CR, which cannot occur inside it
+                            let held_size = self.held_size;
+                            self.held_size = 0;
+                            if is_body {
+                                return Ok((
+                                    pos,
+                                    Some(HttpMultipart::Data(&self.held[..held_size])),
+                                ));
+                            }
+                            continue;
+                        }
+                        self.held[self.held_size..self.held_size + len]
+                            .copy_from_slice(&rest[..len]);
+                        pos += len;
+                        self.held_size += len;
+                        if self.held_size == self.delimiter_size {
+                            self.held_size = 0;
+                            self.state = HttpMultipartState::Delimiter;
+                            if is_body {
+                                return Ok((pos, Some(HttpMultipart::PartEnd)));
+                            }
+                        }
+                        continue;
+                    }
+                    let start = rest
+                        .iter()
+                        .enumerate()
+                        .filter(|&(_, &b)| b == b'\r')
+                        .map(|(start, _)| start)
+                        .find(|&start| {
+                            let len = (rest.len() - start).min(self.delimiter_size);
+                            rest[start..start + len] == delimiter[..len]
+                        })
+                        .unwrap_or(rest.len());
+                    if start == 0 {
+                        // Held back until it is known whether this is the delimiter
+                        let len = rest.len().min(self.delimiter_size);
+                        self.held[..len].copy_from_slice(&rest[..len]);
+                        self.held_size = len;
+                        pos += len;
+                        if len == self.delimiter_size {
+                            self.held_size = 0;
+                            self.state = HttpMultipartState::Delimiter;
+                            if is_body {
+                                return Ok((pos, Some(HttpMultipart::PartEnd)));
+                            }
+                        }
+                        continue;
+                    }
+                    pos += start;
+                    if is_body {
+                        return Ok((pos, Some(HttpMultipart::Data(&rest[..start]))));
+                    }
+                }
+                HttpMultipartState::Headers => {
+                    if self.header_size == self.header.len() {
+                        return Err(Error::Oversized);
+                    }
+                    self.header[self.header_size] = rest[0];
+                    self.header_size += 1;
+                    pos += 1;
+                    if rest[0] != b'\n' {
+                        continue;
+                    }
+                    let (line, _) = split_line(&self.header[self.line_start..self.header_size]);
+                    if line.is_empty() {
+                        self.state = HttpMultipartState::Body;
+                        let part = HttpPart {
+                            lines: HttpLineIterator {
+                                buffer: &self.header[..self.header_size],
+                            },
+                        };
+                        return Ok((pos, Some(HttpMultipart::Part(part))));
+                    }
+                    split_header(line)?;
+                    self.line_start = self.header_size;
+                }
+                HttpMultipartState::Epilogue => {
+                    // Anything after the close delimiter is to be ignored
+                    pos = buffer.len();
+                }
+                state => {
+                    pos += 1;
+                    self.state = match (state, rest[0]) {
+                        (HttpMultipartState::Delimiter, b'-') => HttpMultipartState::Close,
+                        (HttpMultipartState::Close, b'-') => {
+                            self.state = HttpMultipartState::Epilogue;
+                            return Ok((pos, Some(HttpMultipart::End)));
+                        }
+                        (
+                            HttpMultipartState::Delimiter | HttpMultipartState::Padding,
+                            b' ' | b'\t',
+                        ) => HttpMultipartState::Padding,
+                        (HttpMultipartState::Delimiter | HttpMultipartState::Padding, b'\r') => {
+                            HttpMultipartState::DelimiterLf
+                        }
+                        (
+                            HttpMultipartState::Delimiter
+                            | HttpMultipartState::Padding
+                            | HttpMultipartState::DelimiterLf,
+                            b'\n',
+                        ) => {
+                            self.header_size = 0;
+                            self.line_start = 0;
+                            HttpMultipartState::Headers
+                        }
+                        _ => return Err(Error::Malformed),
+                    };
+                }
+            }
+        }
+        Ok((pos, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_line_and_headers() {
+        let request =
+            b"GET /api/status?verbose=1 HTTP/1.1\r\nHost: nucleo\r\nAccept:  */*\t\r\n\r\n";
+        let http = HttpParser::parse(request).unwrap();
+        assert_eq!(http.method(), HttpMethod::GET);
+        assert_eq!(http.target(), "/api/status?verbose=1");
+        assert_eq!(http.path(), "/api/status");
+        assert_eq!(http.query(), Some("verbose=1"));
+        assert_eq!(http.version(), "HTTP/1.1");
+        assert_eq!(http.computed_header_length(), request.len());
+        assert_eq!(http.header("host"), Some("nucleo"));
+        assert_eq!(http.header("ACCEPT"), Some("*/*"));
+        assert_eq!(http.header("content-length"), None);
+        assert_eq!(http.headers().count(), 2);
+        assert!(!http.is_chunked());
+        assert!(http.body().is_empty());
+
+        // A bare LF is accepted as a line terminator
+        let http = HttpParser::parse(b"GET / HTTP/1.0\nHost: nucleo\n\n").unwrap();
+        assert_eq!(http.path(), "/");
+        assert_eq!(http.query(), None);
+        assert_eq!(http.header("host"), Some("nucleo"));
+    }
+
+    #[test]
+    fn request_body() {
+        let request = b"POST /form HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello, pipelined";
+        let http = HttpParser::parse(request).unwrap();
+        assert_eq!(http.content_length(), Some(5));
+        assert_eq!(http.body(), b"hello");
+        assert!(matches!(http.inner(), Ok(Http::Raw(b"hello"))));
+        // Only the part of the body received so far
+        let http = HttpParser::parse(&request[..request.len() - 14]).unwrap();
+        assert_eq!(http.body(), b"he");
+        // The same Content-Length twice is fine
+        let http = HttpParser::parse(
+            b"POST / HTTP/1.1\r\nContent-Length: 1\r\nContent-Length: 1\r\n\r\nx",
+        )
+        .unwrap();
+        assert_eq!(http.body(), b"x");
+
+        let request =
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip, chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n";
+        let http = HttpParser::parse(request).unwrap();
+        assert!(http.is_chunked());
+        assert_eq!(http.body(), b"5\r\nhello\r\n0\r\n\r\n");
+    }
+
+    #[test]
+    fn request_incomplete() {
+        let request = b"GET / HTTP/1.1\r\nHost: nucleo\r\n\r\n";
+        for len in 0..request.len() - 1 {
+            assert!(matches!(
+                HttpParser::parse(&request[..len]),
+                Err(Error::Incomplete)
+            ));
+        }
+    }
+
+    #[test]
+    fn request_malformed() {
+        for request in [
+            &b"GET\r\n\r\n"[..],
+            b"GET /\r\n\r\n",
+            b"GET / HTTP/1.1 extra\r\n\r\n",
+            b"G(T / HTTP/1.1\r\n\r\n",
+            b"GET / HTTP/2.0\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost : nucleo\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length: +123\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length: -1\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length: \r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length: 99999999999999999999999\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length: 1\r\nContent-Length: 2\r\n\r\n",
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked, gzip\r\n\r\n",
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nContent-Length: 1\r\n\r\n",
+        ] {
+            assert!(
+                matches!(HttpParser::parse(request), Err(Error::Malformed)),
+                "{:?}",
+                core::str::from_utf8(request)
+            );
+        }
+    }
+
+    #[test]
+    fn response_with_body() {
+        let mut http_pdu = HttpPdu::new();
+        http_pdu.content_type(HttpContentType::TEXT_PLAIN).unwrap();
+        http_pdu.inner(b"hello").unwrap();
+        assert_eq!(
+            http_pdu.as_bytes(),
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: 5\r\n\r\nhello"
+        );
+        // Setting the status starts the response over
+        http_pdu.status(HttpStatus::NOT_FOUND).unwrap();
+        http_pdu.connection("close").unwrap();
+        http_pdu.inner(b"").unwrap();
+        assert_eq!(
+            http_pdu.as_bytes(),
+            b"HTTP/1.1 404 Not Found\r\nConnection: close\r\nContent-Length: 0\r\n\r\n"
+        );
+        http_pdu.status_with_reason(599, "").unwrap();
+        http_pdu.inner(b"").unwrap();
+        assert_eq!(
+            http_pdu.as_bytes(),
+            b"HTTP/1.1 599 \r\nContent-Length: 0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn response_without_body() {
+        let mut http_pdu = HttpPdu::new();
+        http_pdu.status(HttpStatus::NO_CONTENT).unwrap();
+        http_pdu.inner(b"").unwrap();
+        assert_eq!(http_pdu.as_bytes(), b"HTTP/1.1 204 No Content\r\n\r\n");
+        http_pdu.status(HttpStatus::SWITCHING_PROTOCOLS).unwrap();
+        http_pdu.header("Upgrade", "websocket").unwrap();
+        http_pdu.inner(b"").unwrap();
+        assert_eq!(
+            http_pdu.as_bytes(),
+            b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn response_streamed_body() {
+        let body = [b'a'; 3000];
+        let mut http_pdu = HttpPdu::new();
+        http_pdu.content_length(body.len() as u64).unwrap();
+        let mut sent = http_pdu.body(&body).unwrap();
+        let header = b"HTTP/1.1 200 OK\r\nContent-Length: 3000\r\n\r\n";
+        assert_eq!(sent, 1460 - header.len());
+        assert_eq!(http_pdu.as_bytes()[..header.len()], header[..]);
+        assert_eq!(http_pdu.as_bytes().len(), 1460);
+        while sent < body.len() {
+            let len = http_pdu.continuation(&body[sent..]);
+            assert_eq!(http_pdu.as_bytes(), &body[sent..sent + len]);
+            sent += len;
+        }
+        assert_eq!(sent, 3000);
+        // No more headers once the body has started
+        assert!(matches!(
+            http_pdu.header("X-Late", "1"),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn response_chunked() {
+        let mut http_pdu = HttpPdu::new();
+        assert!(matches!(http_pdu.chunk(|_| 0), Err(Error::Malformed)));
+        http_pdu.chunked().unwrap();
+        let len = http_pdu
+            .chunk(|buffer| {
+                buffer[..5].copy_from_slice(b"hello");
+                5
+            })
+            .unwrap();
+        assert_eq!(len, 5);
+        assert_eq!(
+            http_pdu.as_bytes(),
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n005\r\nhello\r\n"
+        );
+        let len = http_pdu
+            .chunk(|buffer| {
+                buffer.fill(b'x');
+                buffer.len()
+            })
+            .unwrap();
+        assert_eq!(len, 1460 - 7);
+        assert_eq!(http_pdu.as_bytes()[..5], *b"5ad\r\n");
+        assert_eq!(http_pdu.chunk(|_| 0).unwrap(), 0);
+        assert_eq!(http_pdu.as_bytes(), b"0\r\n\r\n");
+
+        // Only a final chunked coding frames the body
+        http_pdu.status(HttpStatus::OK).unwrap();
+        http_pdu
+            .header("Transfer-Encoding", "gzip, chunked")
+            .unwrap();
+        assert!(http_pdu.chunk(|_| 0).is_ok());
+        http_pdu.status(HttpStatus::OK).unwrap();
+        http_pdu
+            .header("Transfer-Encoding", "chunked, gzip")
+            .unwrap();
+        assert!(matches!(http_pdu.chunk(|_| 0), Err(Error::Malformed)));
+    }
+
+    #[test]
+    fn response_malformed_and_oversized() {
+        let mut http_pdu = HttpPdu::new();
+        assert!(matches!(http_pdu.status(99), Err(Error::Malformed)));
+        assert!(matches!(http_pdu.status(1000), Err(Error::Malformed)));
+        assert!(matches!(
+            http_pdu.status_with_reason(200, "OK\r\nX-Injected: 1"),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            http_pdu.header("X-Value", "a\r\nb"),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            http_pdu.header("Bad Name", "a"),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(http_pdu.header("", "a"), Err(Error::Malformed)));
+        let value = core::str::from_utf8(&[b'a'; 1460]).unwrap();
+        assert!(matches!(
+            http_pdu.header("X-Value", value),
+            Err(Error::Oversized)
+        ));
+        assert!(matches!(http_pdu.inner(&[0; 1460]), Err(Error::Oversized)));
+        assert_eq!(http_pdu.as_bytes(), b"HTTP/1.1 200 OK\r\n");
+    }
+
+    fn decode_chunked(
+        decoder: &mut HttpChunkedDecoder,
+        input: &[u8],
+        output: &mut [u8],
+        len: &mut usize,
+    ) -> Result<()> {
+        for data in decoder.decode(input) {
+            let data = data?;
+            output[*len..*len + data.len()].copy_from_slice(data);
+            *len += data.len();
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn chunked_decode() {
+        let input =
+            b"5\r\nhello\r\nB;name=\"val\"\r\n world, hi!\r\n0\r\nX-Checksum: abc\r\n\r\nGET /next";
+        let mut decoder = HttpChunkedDecoder::new();
+        let mut chunks = decoder.decode(input);
+        assert_eq!(chunks.next().unwrap().unwrap(), b"hello");
+        assert_eq!(chunks.next().unwrap().unwrap(), b" world, hi!");
+        assert!(chunks.next().is_none());
+        assert_eq!(chunks.remainder(), b"GET /next");
+        assert!(decoder.is_done());
+        let mut trailers = decoder.trailers();
+        let trailer = trailers.next().unwrap();
+        assert_eq!((trailer.name, trailer.value), ("X-Checksum", "abc"));
+        assert!(trailers.next().is_none());
+
+        // The same body fed a byte at a time, with bare LFs
+        let input = b"5\nhello\nB\n world, hi!\n0\n\n";
+        let mut decoder = HttpChunkedDecoder::new();
+        let mut output = [0u8; 16];
+        let mut len = 0;
+        for b in input.chunks(1) {
+            decode_chunked(&mut decoder, b, &mut output, &mut len).unwrap();
+        }
+        assert!(decoder.is_done());
+        assert_eq!(output[..len], *b"hello world, hi!");
+    }
+
+    #[test]
+    fn chunked_round_trip() {
+        let mut http_pdu = HttpPdu::new();
+        http_pdu.chunked().unwrap();
+        let mut decoder = HttpChunkedDecoder::new();
+        let mut output = [0u8; 2048];
+        let mut len = 0;
+        let mut body = &[0x5A; 2000][..];
+        loop {
+            let sent = http_pdu
+                .chunk(|buffer| {
+                    let len = body.len().min(buffer.len());
+                    buffer[..len].copy_from_slice(&body[..len]);
+                    len
+                })
+                .unwrap();
+            body = &body[sent..];
+            let bytes = http_pdu.as_bytes();
+            // Only the first segment carries the response header
+            let start = match bytes.windows(4).position(|w| w == b"\r\n\r\n") {
+                Some(pos) if bytes.starts_with(b"HTTP/1.1") => pos + 4,
+                _ => 0,
+            };
+            decode_chunked(&mut decoder, &bytes[start..], &mut output, &mut len).unwrap();
+            if sent == 0 {
+                break;
+            }
+        }
+        assert!(decoder.is_done());
+        assert_eq!(output[..len], [0x5A; 2000]);
+    }
+
+    #[test]
+    fn chunked_oversized() {
+        let mut decoder = HttpChunkedDecoder::new();
+        assert!(decoder.decode(b"1000000\r\n").all(|data| data.is_ok()));
+        let mut decoder = HttpChunkedDecoder::new();
+        let mut chunks = decoder.decode(b"100000000\r\n");
+        assert!(matches!(chunks.next(), Some(Err(Error::Oversized))));
+        assert!(chunks.next().is_none());
+        // A trailer section larger than the decoder keeps
+        let mut decoder = HttpChunkedDecoder::new();
+        let mut input = [b'a'; 600];
+        input[..5].copy_from_slice(b"0\r\nX:");
+        assert!(matches!(
+            decoder.decode(&input).next(),
+            Some(Err(Error::Oversized))
+        ));
+    }
+
+    #[test]
+    fn chunked_malformed() {
+        for input in [
+            &b"\r\n"[..],
+            b";ext\r\n",
+            b"G\r\n",
+            b"5\r\nhelloX",
+            b"5\r\r",
+            b"0\r\nbad trailer\r\n\r\n",
+        ] {
+            let mut decoder = HttpChunkedDecoder::new();
+            assert!(
+                decoder
+                    .decode(input)
+                    .any(|data| matches!(data, Err(Error::Malformed))),
+                "{:?}",
+                core::str::from_utf8(input)
+            );
+        }
+    }
+
+    #[test]
+    fn query_fields() {
+        let http =
+            HttpParser::parse(b"GET /search?q=hello+world&lang=en&&flag HTTP/1.1\r\n\r\n").unwrap();
+        let mut fields = http.query_fields();
+        let field = fields.next().unwrap();
+        assert_eq!((field.name, field.value), ("q", "hello+world"));
+        assert!(field.decoded_value().eq(b"hello world".iter().copied()));
+        let field = fields.next().unwrap();
+        assert_eq!((field.name, field.value), ("lang", "en"));
+        let field = fields.next().unwrap();
+        assert_eq!((field.name, field.value), ("flag", ""));
+        assert!(fields.next().is_none());
+        let http = HttpParser::parse(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(http.query_fields().count(), 0);
+    }
+
+    #[test]
+    fn form_fields() {
+        let form = HttpFormParser::parse(b"first%20name=Ren%C3%A9&ratio=100%&x=%zz").unwrap();
+        let field = form.field("first name").unwrap();
+        let mut buffer = [0u8; 16];
+        assert_eq!(
+            field.decoded_value().decode_into(&mut buffer).unwrap(),
+            "René".as_bytes()
+        );
+        assert!(matches!(
+            field.decoded_value().decode_into(&mut buffer[..4]),
+            Err(Error::Oversized)
+        ));
+        // Stray percent signs are kept as they are
+        let field = form.field("ratio").unwrap();
+        assert_eq!(
+            field.decoded_value().decode_into(&mut buffer).unwrap(),
+            b"100%"
+        );
+        let field = form.field("x").unwrap();
+        assert_eq!(
+            field.decoded_value().decode_into(&mut buffer).unwrap(),
+            b"%zz"
+        );
+        assert!(form.field("first%20name").is_none());
+        assert!(matches!(
+            HttpFormParser::parse(&[b'a', b'=', 0xFF]),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn multipart_boundary() {
+        let http = HttpParser::parse(
+            b"POST / HTTP/1.1\r\nContent-Type: multipart/form-data; charset=utf-8; boundary=\"a;b\"\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(http.multipart_boundary(), Some("a;b"));
+        let http = HttpParser::parse(
+            b"POST / HTTP/1.1\r\nContent-Type: Multipart/Form-Data ;boundary=XyZ\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(http.multipart_boundary(), Some("XyZ"));
+        let http = HttpParser::parse(
+            b"POST / HTTP/1.1\r\nContent-Type: multipart/mixed; boundary=XyZ\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(http.multipart_boundary(), None);
+    }
+
+    const MULTIPART: &[u8] = b"preamble\r\n--XyZ\r\nContent-Disposition: form-data; name=\"field\"\r\n\r\nvalue\r\n--XyZ \t\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\nline1\r\n--Xy\r\nline2\r\n--XyZ--\r\nepilogue";
+
+    fn decode_multipart(input: &[u8], step: usize, output: &mut [u8]) -> Result<usize> {
+        let mut decoder = HttpMultipartDecoder::new("XyZ")?;
+        let mut len = 0;
+        for segment in input.chunks(step) {
+            let mut pos = 0;
+            while pos < segment.len() {
+                let (used, event) = decoder.decode(&segment[pos..])?;
+                pos += used;
+                let mut push = |value: &[u8]| {
+                    output[len..len + value.len()].copy_from_slice(value);
+                    len += value.len();
+                };
+                match event {
+                    Some(HttpMultipart::Part(part)) => {
+                        push(b"[");
+                        push(part.name().unwrap_or("").as_bytes());
+                        if let Some(filename) = part.filename() {
+                            push(b";");
+                            push(filename.as_bytes());
+                        }
+                        if let Some(content_type) = part.content_type() {
+                            push(b"=");
+                            push(content_type.as_bytes());
+                        }
+                        push(b"]");
+                    }
+                    Some(HttpMultipart::Data(data)) => push(data),
+                    Some(HttpMultipart::PartEnd) => push(b"|"),
+                    Some(HttpMultipart::End) => push(b"$"),
+                    None => {}
+                }
+            }
+        }
+        assert!(decoder.is_done());
+        Ok(len)
+    }
+
+    #[test]
+    fn multipart_decode() {
+        let expected = b"[field]value|[file;a.txt=text/plain]line1\r\n--Xy\r\nline2|$";
+        // However the body is split into segments
+        for step in [MULTIPART.len(), 1, 2, 7] {
+            let mut output = [0u8; 128];
+            let len = decode_multipart(MULTIPART, step, &mut output).unwrap();
+            assert_eq!(
+                output[..len],
+                expected[..],
+                "{:?}",
+                core::str::from_utf8(&output[..len])
+            );
         }
     }
 
-    pub fn method(&'a self) -> Option<&str> {
-        match self.buffer[0..=3] {
-            [0x47, 0x45, 0x54, 0x20] => Some("GET"),
-            [0x50, 0x4F, 0x53, 0x54] => Some("POST"),
-            _ => None,
+    #[test]
+    fn multipart_malformed_and_oversized() {
+        assert!(matches!(
+            HttpMultipartDecoder::new(""),
+            Err(Error::Malformed)
+        ));
+        let boundary = core::str::from_utf8(&[b'a'; 71]).unwrap();
+        assert!(matches!(
+            HttpMultipartDecoder::new(boundary),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            HttpMultipartDecoder::new("a\r\nb"),
+            Err(Error::Malformed)
+        ));
+        for input in [&b"--XyZx\r\n"[..], b"--XyZ-x", b"--XyZ\r\nno colon\r\n\r\n"] {
+            let mut output = [0u8; 128];
+            assert!(matches!(
+                decode_multipart(input, input.len(), &mut output),
+                Err(Error::Malformed)
+            ));
         }
+        let mut input = [b'a'; 600];
+        input[..10].copy_from_slice(b"--XyZ\r\nX: ");
+        let mut output = [0u8; 128];
+        assert!(matches!(
+            decode_multipart(&input, input.len(), &mut output),
+            Err(Error::Oversized)
+        ));
     }
 }
@@ -5,12 +5,75 @@ use crate::{util, Error, Result};
 #[allow(non_snake_case)]
 pub mod IcmpType {
     pub const ECHO_REPLY: u8 = 0;
+    pub const DESTINATION_UNREACHABLE: u8 = 3;
+    pub const REDIRECT: u8 = 5;
     pub const ECHO_REQUEST: u8 = 8;
+    pub const TIME_EXCEEDED: u8 = 11;
+    pub const PARAMETER_PROBLEM: u8 = 12;
+}
+
+#[allow(non_snake_case)]
+pub mod IcmpUnreachableCode {
+    pub const NET_UNREACHABLE: u8 = 0;
+    pub const HOST_UNREACHABLE: u8 = 1;
+    pub const PROTOCOL_UNREACHABLE: u8 = 2;
+    pub const PORT_UNREACHABLE: u8 = 3;
+    pub const FRAGMENTATION_NEEDED: u8 = 4;
+    pub const SOURCE_ROUTE_FAILED: u8 = 5;
+    pub const DESTINATION_NETWORK_UNKNOWN: u8 = 6;
+    pub const DESTINATION_HOST_UNKNOWN: u8 = 7;
+    pub const SOURCE_HOST_ISOLATED: u8 = 8;
+    pub const NETWORK_PROHIBITED: u8 = 9;
+    pub const HOST_PROHIBITED: u8 = 10;
+    pub const NETWORK_UNREACHABLE_FOR_TOS: u8 = 11;
+    pub const HOST_UNREACHABLE_FOR_TOS: u8 = 12;
+    pub const COMMUNICATION_PROHIBITED: u8 = 13;
+    pub const HOST_PRECEDENCE_VIOLATION: u8 = 14;
+    pub const PRECEDENCE_CUTOFF: u8 = 15;
+}
+
+#[allow(non_snake_case)]
+pub mod IcmpRedirectCode {
+    pub const NETWORK: u8 = 0;
+    pub const HOST: u8 = 1;
+    pub const TOS_AND_NETWORK: u8 = 2;
+    pub const TOS_AND_HOST: u8 = 3;
+}
+
+#[allow(non_snake_case)]
+pub mod IcmpTimeExceededCode {
+    pub const TTL_EXCEEDED: u8 = 0;
+    pub const FRAGMENT_REASSEMBLY: u8 = 1;
 }
 
 #[derive(Copy, Clone)]
 pub enum Icmp<'a> {
     Raw(&'a [u8]),
+    EchoReply {
+        identifier: u16,
+        sequence_number: u16,
+        data: &'a [u8],
+    },
+    EchoRequest {
+        identifier: u16,
+        sequence_number: u16,
+        data: &'a [u8],
+    },
+    DestinationUnreachable {
+        next_hop_mtu: u16,
+        original: super::Ipv4Parser<'a>,
+    },
+    Redirect {
+        gateway_address: [u8; 4],
+        original: super::Ipv4Parser<'a>,
+    },
+    TimeExceeded {
+        original: super::Ipv4Parser<'a>,
+    },
+    ParameterProblem {
+        pointer: u8,
+        original: super::Ipv4Parser<'a>,
+    },
 }
 
 pub struct IcmpPdu {
@@ -49,6 +112,84 @@ impl IcmpPdu {
         ]));
     }
 
+    pub fn echo_reply(&mut self, identifier: u16, sequence_number: u16, data: &[u8]) -> Result<()> {
+        self.message_type(IcmpType::ECHO_REPLY);
+        self.echo(identifier, sequence_number, data)
+    }
+
+    pub fn echo_request(
+        &mut self,
+        identifier: u16,
+        sequence_number: u16,
+        data: &[u8],
+    ) -> Result<()> {
+        self.message_type(IcmpType::ECHO_REQUEST);
+        self.echo(identifier, sequence_number, data)
+    }
+
+    fn echo(&mut self, identifier: u16, sequence_number: u16, data: &[u8]) -> Result<()> {
+        let len = data.len();
+        if len > 1472 {
+            return Err(Error::Oversized);
+        }
+        self.message_code(0);
+        self.buffer[4..=5].copy_from_slice(&identifier.to_be_bytes());
+        self.buffer[6..=7].copy_from_slice(&sequence_number.to_be_bytes());
+        self.buffer[8..8 + len].copy_from_slice(data);
+        self.inner_size = 4 + len;
+        Ok(())
+    }
+
+    pub fn destination_unreachable(
+        &mut self,
+        code: u8,
+        next_hop_mtu: u16,
+        original: &crate::Ipv4Parser,
+    ) -> Result<()> {
+        self.message_type(IcmpType::DESTINATION_UNREACHABLE);
+        self.message_code(code);
+        self.error(
+            [0x00, 0x00, (next_hop_mtu >> 8) as u8, next_hop_mtu as u8],
+            original,
+        )
+    }
+
+    pub fn redirect(
+        &mut self,
+        code: u8,
+        gateway_address: [u8; 4],
+        original: &crate::Ipv4Parser,
+    ) -> Result<()> {
+        self.message_type(IcmpType::REDIRECT);
+        self.message_code(code);
+        self.error(gateway_address, original)
+    }
+
+    pub fn time_exceeded(&mut self, code: u8, original: &crate::Ipv4Parser) -> Result<()> {
+        self.message_type(IcmpType::TIME_EXCEEDED);
+        self.message_code(code);
+        self.error([0x00; 4], original)
+    }
+
+    pub fn parameter_problem(&mut self, pointer: u8, original: &crate::Ipv4Parser) -> Result<()> {
+        self.message_type(IcmpType::PARAMETER_PROBLEM);
+        self.message_code(0);
+        self.error([pointer, 0x00, 0x00, 0x00], original)
+    }
+
+    fn error(&mut self, rest_of_header: [u8; 4], original: &crate::Ipv4Parser) -> Result<()> {
+        // Quotes the original IP header and the first 8 octets of its payload (RFC 792)
+        let quote = original.quote();
+        let len = quote.len();
+        if len > 1472 {
+            return Err(Error::Oversized);
+        }
+        self.buffer[4..=7].copy_from_slice(&rest_of_header);
+        self.buffer[8..8 + len].copy_from_slice(quote);
+        self.inner_size = 4 + len;
+        Ok(())
+    }
+
     pub fn inner(&mut self, value: &[u8]) -> Result<()> {
         let len = value.len();
         if len > 1476 {
@@ -78,8 +219,35 @@ impl<'a> IcmpParser<'a> {
     }
 
     pub fn into_inner(self) -> Result<Icmp<'a>> {
-        let rest = &self.buffer[4..];
-        Ok(Icmp::Raw(rest))
+        let buffer = self.buffer;
+        Ok(match self.message_type() {
+            IcmpType::ECHO_REPLY => Icmp::EchoReply {
+                identifier: u16::from_be_bytes(buffer[4..=5].try_into().unwrap()),
+                sequence_number: u16::from_be_bytes(buffer[6..=7].try_into().unwrap()),
+                data: &buffer[8..],
+            },
+            IcmpType::ECHO_REQUEST => Icmp::EchoRequest {
+                identifier: u16::from_be_bytes(buffer[4..=5].try_into().unwrap()),
+                sequence_number: u16::from_be_bytes(buffer[6..=7].try_into().unwrap()),
+                data: &buffer[8..],
+            },
+            IcmpType::DESTINATION_UNREACHABLE => Icmp::DestinationUnreachable {
+                next_hop_mtu: u16::from_be_bytes(buffer[6..=7].try_into().unwrap()),
+                original: super::Ipv4Parser::parse(&buffer[8..])?,
+            },
+            IcmpType::REDIRECT => Icmp::Redirect {
+                gateway_address: buffer[4..=7].try_into().unwrap(),
+                original: super::Ipv4Parser::parse(&buffer[8..])?,
+            },
+            IcmpType::TIME_EXCEEDED => Icmp::TimeExceeded {
+                original: super::Ipv4Parser::parse(&buffer[8..])?,
+            },
+            IcmpType::PARAMETER_PROBLEM => Icmp::ParameterProblem {
+                pointer: buffer[4],
+                original: super::Ipv4Parser::parse(&buffer[8..])?,
+            },
+            _ => Icmp::Raw(&buffer[4..]),
+        })
     }
 
     pub fn message_type(&'a self) -> u8 {
@@ -98,3 +266,106 @@ impl<'a> IcmpParser<'a> {
         util::checksum(&[&self.buffer[0..=1], &self.buffer[4..]])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn original() -> crate::Ipv4Pdu {
+        let mut udp_pdu = crate::UdpPdu::new();
+        udp_pdu.source_port(40000);
+        udp_pdu.destination_port(40001);
+        udp_pdu.inner(&[0xAA; 100]).unwrap();
+        let mut ipv4_pdu = crate::Ipv4Pdu::new();
+        ipv4_pdu.protocol(crate::IpProto::UDP);
+        ipv4_pdu.source_address([192, 168, 0, 1]);
+        ipv4_pdu.destination_address([192, 168, 0, 2]);
+        ipv4_pdu.inner(udp_pdu.as_bytes()).unwrap();
+        ipv4_pdu
+    }
+
+    #[test]
+    fn destination_unreachable_round_trip() {
+        let ipv4_pdu = original();
+        let original = crate::Ipv4Parser::parse(ipv4_pdu.as_bytes()).unwrap();
+        let mut icmp_pdu = IcmpPdu::new();
+        icmp_pdu
+            .destination_unreachable(IcmpUnreachableCode::FRAGMENTATION_NEEDED, 1400, &original)
+            .unwrap();
+        icmp_pdu.compute_checksum();
+        // The IP header and the first 8 octets of the datagram are quoted
+        assert_eq!(icmp_pdu.as_bytes().len(), 8 + 20 + 8);
+        let icmp = IcmpParser::parse(icmp_pdu.as_bytes()).unwrap();
+        assert_eq!(
+            icmp.message_code(),
+            IcmpUnreachableCode::FRAGMENTATION_NEEDED
+        );
+        assert_eq!(icmp.computed_checksum(), icmp.checksum());
+        let Ok(Icmp::DestinationUnreachable {
+            next_hop_mtu: 1400,
+            original,
+        }) = icmp.inner()
+        else {
+            panic!("expected Destination Unreachable");
+        };
+        assert_eq!(original.destination_address(), [192, 168, 0, 2]);
+        let Ok(crate::Ipv4::Udp(udp)) = original.inner() else {
+            panic!("expected UDP");
+        };
+        assert_eq!(udp.destination_port(), 40001);
+        assert!(matches!(udp.inner(), Err(Error::Truncated)));
+    }
+
+    #[test]
+    fn error_messages_round_trip() {
+        let ipv4_pdu = original();
+        let original = crate::Ipv4Parser::parse(ipv4_pdu.as_bytes()).unwrap();
+        let mut icmp_pdu = IcmpPdu::new();
+        icmp_pdu
+            .redirect(IcmpRedirectCode::HOST, [192, 168, 0, 254], &original)
+            .unwrap();
+        let icmp = IcmpParser::parse(icmp_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            icmp.inner(),
+            Ok(Icmp::Redirect {
+                gateway_address: [192, 168, 0, 254],
+                ..
+            })
+        ));
+        icmp_pdu
+            .time_exceeded(IcmpTimeExceededCode::TTL_EXCEEDED, &original)
+            .unwrap();
+        let icmp = IcmpParser::parse(icmp_pdu.as_bytes()).unwrap();
+        assert!(matches!(icmp.inner(), Ok(Icmp::TimeExceeded { .. })));
+        icmp_pdu.parameter_problem(9, &original).unwrap();
+        let icmp = IcmpParser::parse(icmp_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            icmp.inner(),
+            Ok(Icmp::ParameterProblem { pointer: 9, .. })
+        ));
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(matches!(
+            IcmpParser::parse(&[IcmpType::ECHO_REPLY, 0, 0, 0, 0, 0, 0]),
+            Err(Error::Truncated)
+        ));
+        // A Time Exceeded message quoting less than an IP header
+        let mut buffer = [0u8; 8 + 19];
+        buffer[0] = IcmpType::TIME_EXCEEDED;
+        buffer[8] = 0x45;
+        let icmp = IcmpParser::parse(&buffer).unwrap();
+        assert!(matches!(icmp.inner(), Err(Error::Truncated)));
+    }
+
+    #[test]
+    fn echo_oversized() {
+        let mut icmp_pdu = IcmpPdu::new();
+        assert!(icmp_pdu.echo_request(1, 1, &[0u8; 1472]).is_ok());
+        assert!(matches!(
+            icmp_pdu.echo_request(1, 1, &[0u8; 1473]),
+            Err(Error::Oversized)
+        ));
+    }
+}
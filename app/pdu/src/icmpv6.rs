@@ -0,0 +1,619 @@
+use core::convert::TryInto;
+
+use crate::{util, Error, Result};
+
+#[allow(non_snake_case)]
+pub mod Icmpv6Type {
+    pub const DESTINATION_UNREACHABLE: u8 = 1;
+    pub const PACKET_TOO_BIG: u8 = 2;
+    pub const TIME_EXCEEDED: u8 = 3;
+    pub const PARAMETER_PROBLEM: u8 = 4;
+    pub const ECHO_REQUEST: u8 = 128;
+    pub const ECHO_REPLY: u8 = 129;
+    pub const ROUTER_SOLICITATION: u8 = 133;
+    pub const ROUTER_ADVERTISEMENT: u8 = 134;
+    pub const NEIGHBOR_SOLICITATION: u8 = 135;
+    pub const NEIGHBOR_ADVERTISEMENT: u8 = 136;
+    pub const REDIRECT: u8 = 137;
+}
+
+#[allow(non_snake_case)]
+pub mod NdpOptionType {
+    pub const SOURCE_LINK_LAYER_ADDRESS: u8 = 1;
+    pub const TARGET_LINK_LAYER_ADDRESS: u8 = 2;
+    pub const PREFIX_INFORMATION: u8 = 3;
+    pub const REDIRECTED_HEADER: u8 = 4;
+    pub const MTU: u8 = 5;
+}
+
+#[derive(Copy, Clone)]
+pub enum Icmpv6<'a> {
+    Raw(&'a [u8]),
+    EchoRequest {
+        identifier: u16,
+        sequence_number: u16,
+        data: &'a [u8],
+    },
+    EchoReply {
+        identifier: u16,
+        sequence_number: u16,
+        data: &'a [u8],
+    },
+    RouterSolicitation {
+        options: NdpOptionIterator<'a>,
+    },
+    RouterAdvertisement {
+        cur_hop_limit: u8,
+        managed: bool,
+        other: bool,
+        router_lifetime: u16,
+        reachable_time: u32,
+        retrans_timer: u32,
+        options: NdpOptionIterator<'a>,
+    },
+    NeighborSolicitation {
+        target_address: [u8; 16],
+        options: NdpOptionIterator<'a>,
+    },
+    NeighborAdvertisement {
+        router: bool,
+        solicited: bool,
+        override_flag: bool,
+        target_address: [u8; 16],
+        options: NdpOptionIterator<'a>,
+    },
+}
+
+pub struct Icmpv6Pdu {
+    buffer: [u8; 1460],
+    inner_size: usize,
+}
+
+impl Icmpv6Pdu {
+    pub fn new() -> Self {
+        Icmpv6Pdu {
+            buffer: [0u8; 1460],
+            inner_size: 0,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..4 + self.inner_size]
+    }
+
+    pub fn message_type(&mut self, value: u8) {
+        self.buffer[0] = value;
+    }
+
+    pub fn message_code(&mut self, value: u8) {
+        self.buffer[1] = value;
+    }
+
+    pub fn checksum(&mut self, value: u16) {
+        self.buffer[2..=3].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn compute_checksum(&mut self, ip: &crate::Ipv6PseudoHeader) {
+        self.checksum(util::checksum([
+            ip.source_address.as_ref(),
+            ip.destination_address.as_ref(),
+            ((4 + self.inner_size) as u32).to_be_bytes().as_ref(),
+            [0x00, 0x00, 0x00, ip.next_header].as_ref(),
+            &self.buffer[0..=1],
+            &self.buffer[4..4 + self.inner_size],
+        ]));
+    }
+
+    pub fn echo_request(
+        &mut self,
+        identifier: u16,
+        sequence_number: u16,
+        data: &[u8],
+    ) -> Result<()> {
+        self.message_type(Icmpv6Type::ECHO_REQUEST);
+        self.echo(identifier, sequence_number, data)
+    }
+
+    pub fn echo_reply(&mut self, identifier: u16, sequence_number: u16, data: &[u8]) -> Result<()> {
+        self.message_type(Icmpv6Type::ECHO_REPLY);
+        self.echo(identifier, sequence_number, data)
+    }
+
+    fn echo(&mut self, identifier: u16, sequence_number: u16, data: &[u8]) -> Result<()> {
+        let len = data.len();
+        if len > 1452 {
+            return Err(Error::Oversized);
+        }
+        self.message_code(0);
+        self.buffer[4..=5].copy_from_slice(&identifier.to_be_bytes());
+        self.buffer[6..=7].copy_from_slice(&sequence_number.to_be_bytes());
+        self.buffer[8..8 + len].copy_from_slice(data);
+        self.inner_size = 4 + len;
+        Ok(())
+    }
+
+    pub fn router_solicitation(&mut self) {
+        self.message_type(Icmpv6Type::ROUTER_SOLICITATION);
+        self.message_code(0);
+        self.buffer[4..=7].copy_from_slice(&[0x00; 4]);
+        self.inner_size = 4;
+    }
+
+    pub fn router_advertisement(
+        &mut self,
+        cur_hop_limit: u8,
+        managed: bool,
+        other: bool,
+        router_lifetime: u16,
+        reachable_time: u32,
+        retrans_timer: u32,
+    ) {
+        self.message_type(Icmpv6Type::ROUTER_ADVERTISEMENT);
+        self.message_code(0);
+        self.buffer[4] = cur_hop_limit;
+        self.buffer[5] = (managed as u8) << 7 | (other as u8) << 6;
+        self.buffer[6..=7].copy_from_slice(&router_lifetime.to_be_bytes());
+        self.buffer[8..=11].copy_from_slice(&reachable_time.to_be_bytes());
+        self.buffer[12..=15].copy_from_slice(&retrans_timer.to_be_bytes());
+        self.inner_size = 12;
+    }
+
+    pub fn neighbor_solicitation(&mut self, target_address: [u8; 16]) {
+        self.message_type(Icmpv6Type::NEIGHBOR_SOLICITATION);
+        self.message_code(0);
+        self.buffer[4..=7].copy_from_slice(&[0x00; 4]);
+        self.buffer[8..=23].copy_from_slice(&target_address);
+        self.inner_size = 20;
+    }
+
+    pub fn neighbor_advertisement(
+        &mut self,
+        router: bool,
+        solicited: bool,
+        override_flag: bool,
+        target_address: [u8; 16],
+    ) {
+        self.message_type(Icmpv6Type::NEIGHBOR_ADVERTISEMENT);
+        self.message_code(0);
+        self.buffer[4..=7].copy_from_slice(&[0x00; 4]);
+        self.buffer[4] = (router as u8) << 7 | (solicited as u8) << 6 | (override_flag as u8) << 5;
+        self.buffer[8..=23].copy_from_slice(&target_address);
+        self.inner_size = 20;
+    }
+
+    pub fn ndp_option(&mut self, value: &NdpOption) -> Result<()> {
+        let pos = 4 + self.inner_size;
+        let len = match value {
+            NdpOption::Raw { data, .. } => data.len(),
+            NdpOption::SourceLinkLayerAddress { .. } | NdpOption::TargetLinkLayerAddress { .. } => {
+                8
+            }
+            NdpOption::PrefixInformation { .. } => 32,
+            NdpOption::Mtu { .. } => 8,
+        };
+        if len == 0 || len % 8 != 0 {
+            return Err(Error::Malformed);
+        }
+        if pos + len > 1460 {
+            return Err(Error::Oversized);
+        }
+        let option = &mut self.buffer[pos..pos + len];
+        match value {
+            NdpOption::Raw { option: kind, data } => {
+                option.copy_from_slice(data);
+                option[0] = *kind;
+                option[1] = (len / 8) as u8;
+            }
+            NdpOption::SourceLinkLayerAddress { address } => {
+                option[0..=1].copy_from_slice(&[NdpOptionType::SOURCE_LINK_LAYER_ADDRESS, 1]);
+                option[2..=7].copy_from_slice(address);
+            }
+            NdpOption::TargetLinkLayerAddress { address } => {
+                option[0..=1].copy_from_slice(&[NdpOptionType::TARGET_LINK_LAYER_ADDRESS, 1]);
+                option[2..=7].copy_from_slice(address);
+            }
+            NdpOption::PrefixInformation {
+                prefix_length,
+                on_link,
+                autonomous,
+                valid_lifetime,
+                preferred_lifetime,
+                prefix,
+            } => {
+                option[0..=1].copy_from_slice(&[NdpOptionType::PREFIX_INFORMATION, 4]);
+                option[2] = *prefix_length;
+                option[3] = (*on_link as u8) << 7 | (*autonomous as u8) << 6;
+                option[4..=7].copy_from_slice(&valid_lifetime.to_be_bytes());
+                option[8..=11].copy_from_slice(&preferred_lifetime.to_be_bytes());
+                option[12..=15].copy_from_slice(&[0x00; 4]);
+                option[16..=31].copy_from_slice(prefix);
+            }
+            NdpOption::Mtu { mtu } => {
+                option[0..=3].copy_from_slice(&[NdpOptionType::MTU, 1, 0x00, 0x00]);
+                option[4..=7].copy_from_slice(&mtu.to_be_bytes());
+            }
+        }
+        self.inner_size += len;
+        Ok(())
+    }
+
+    pub fn inner(&mut self, value: &[u8]) -> Result<()> {
+        let len = value.len();
+        if len > 1456 {
+            return Err(Error::Oversized);
+        }
+        self.inner_size = len;
+        self.buffer[4..4 + len].copy_from_slice(value);
+        Ok(())
+    }
+}
+
+impl Default for Icmpv6Pdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Icmpv6Parser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Icmpv6Parser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 8 {
+            return Err(Error::Truncated);
+        }
+        Ok(Icmpv6Parser { buffer })
+    }
+
+    pub fn inner(&'a self) -> Result<Icmpv6<'a>> {
+        (*self).into_inner()
+    }
+
+    pub fn into_inner(self) -> Result<Icmpv6<'a>> {
+        let buffer = self.buffer;
+        let options = |pos: usize| NdpOptionIterator { buffer, pos };
+        let min_len = match self.message_type() {
+            Icmpv6Type::ROUTER_ADVERTISEMENT => 16,
+            Icmpv6Type::NEIGHBOR_SOLICITATION | Icmpv6Type::NEIGHBOR_ADVERTISEMENT => 24,
+            _ => 8,
+        };
+        if buffer.len() < min_len {
+            return Err(Error::Truncated);
+        }
+        Ok(match self.message_type() {
+            Icmpv6Type::ECHO_REQUEST => Icmpv6::EchoRequest {
+                identifier: u16::from_be_bytes(buffer[4..=5].try_into().unwrap()),
+                sequence_number: u16::from_be_bytes(buffer[6..=7].try_into().unwrap()),
+                data: &buffer[8..],
+            },
+            Icmpv6Type::ECHO_REPLY => Icmpv6::EchoReply {
+                identifier: u16::from_be_bytes(buffer[4..=5].try_into().unwrap()),
+                sequence_number: u16::from_be_bytes(buffer[6..=7].try_into().unwrap()),
+                data: &buffer[8..],
+            },
+            Icmpv6Type::ROUTER_SOLICITATION => Icmpv6::RouterSolicitation {
+                options: options(8),
+            },
+            Icmpv6Type::ROUTER_ADVERTISEMENT => Icmpv6::RouterAdvertisement {
+                cur_hop_limit: buffer[4],
+                managed: buffer[5] & 0x80 != 0,
+                other: buffer[5] & 0x40 != 0,
+                router_lifetime: u16::from_be_bytes(buffer[6..=7].try_into().unwrap()),
+                reachable_time: u32::from_be_bytes(buffer[8..=11].try_into().unwrap()),
+                retrans_timer: u32::from_be_bytes(buffer[12..=15].try_into().unwrap()),
+                options: options(16),
+            },
+            Icmpv6Type::NEIGHBOR_SOLICITATION => Icmpv6::NeighborSolicitation {
+                target_address: buffer[8..=23].try_into().unwrap(),
+                options: options(24),
+            },
+            Icmpv6Type::NEIGHBOR_ADVERTISEMENT => Icmpv6::NeighborAdvertisement {
+                router: buffer[4] & 0x80 != 0,
+                solicited: buffer[4] & 0x40 != 0,
+                override_flag: buffer[4] & 0x20 != 0,
+                target_address: buffer[8..=23].try_into().unwrap(),
+                options: options(24),
+            },
+            _ => Icmpv6::Raw(&buffer[4..]),
+        })
+    }
+
+    pub fn message_type(&'a self) -> u8 {
+        self.buffer[0]
+    }
+
+    pub fn message_code(&'a self) -> u8 {
+        self.buffer[1]
+    }
+
+    pub fn checksum(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[2..=3].try_into().unwrap())
+    }
+
+    pub fn computed_checksum(&'a self, ip: &crate::Ipv6Parser) -> u16 {
+        util::checksum([
+            ip.source_address().as_ref(),
+            ip.destination_address().as_ref(),
+            (self.buffer.len() as u32).to_be_bytes().as_ref(),
+            [0x00, 0x00, 0x00, crate::IpProto::ICMPV6].as_ref(),
+            &self.buffer[0..=1],
+            &self.buffer[4..],
+        ])
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum NdpOption<'a> {
+    Raw {
+        option: u8,
+        data: &'a [u8],
+    },
+    SourceLinkLayerAddress {
+        address: [u8; 6],
+    },
+    TargetLinkLayerAddress {
+        address: [u8; 6],
+    },
+    PrefixInformation {
+        prefix_length: u8,
+        on_link: bool,
+        autonomous: bool,
+        valid_lifetime: u32,
+        preferred_lifetime: u32,
+        prefix: [u8; 16],
+    },
+    Mtu {
+        mtu: u32,
+    },
+}
+
+#[derive(Copy, Clone)]
+pub struct NdpOptionIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for NdpOptionIterator<'a> {
+    type Item = NdpOption<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pos = self.pos;
+        if self.buffer.len() < pos + 2 {
+            return None;
+        }
+        let option = self.buffer[pos];
+        let len = self.buffer[pos + 1] as usize * 8;
+        if len == 0 || self.buffer.len() < pos + len {
+            // A zero-length option would never advance, so the rest is discarded
+            return None;
+        }
+        self.pos += len;
+        match option {
+            NdpOptionType::SOURCE_LINK_LAYER_ADDRESS if len == 8 => {
+                Some(NdpOption::SourceLinkLayerAddress {
+                    address: self.buffer[pos + 2..=pos + 7].try_into().unwrap(),
+                })
+            }
+            NdpOptionType::TARGET_LINK_LAYER_ADDRESS if len == 8 => {
+                Some(NdpOption::TargetLinkLayerAddress {
+                    address: self.buffer[pos + 2..=pos + 7].try_into().unwrap(),
+                })
+            }
+            NdpOptionType::PREFIX_INFORMATION if len == 32 => Some(NdpOption::PrefixInformation {
+                prefix_length: self.buffer[pos + 2],
+                on_link: self.buffer[pos + 3] & 0x80 != 0,
+                autonomous: self.buffer[pos + 3] & 0x40 != 0,
+                valid_lifetime: u32::from_be_bytes(
+                    self.buffer[pos + 4..=pos + 7].try_into().unwrap(),
+                ),
+                preferred_lifetime: u32::from_be_bytes(
+                    self.buffer[pos + 8..=pos + 11].try_into().unwrap(),
+                ),
+                prefix: self.buffer[pos + 16..=pos + 31].try_into().unwrap(),
+            }),
+            NdpOptionType::MTU if len == 8 => Some(NdpOption::Mtu {
+                mtu: u32::from_be_bytes(self.buffer[pos + 4..=pos + 7].try_into().unwrap()),
+            }),
+            _ => Some(NdpOption::Raw {
+                option,
+                data: &self.buffer[pos..(pos + len)],
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: [u8; 16] = [0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01];
+    const DESTINATION: [u8; 16] = [
+        0xFF, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0xFF, 0, 0, 0x02,
+    ];
+
+    #[test]
+    fn echo_round_trip() {
+        let mut icmpv6_pdu = Icmpv6Pdu::new();
+        icmpv6_pdu.echo_request(0x1234, 7, b"ping").unwrap();
+        icmpv6_pdu.compute_checksum(&crate::Ipv6PseudoHeader {
+            source_address: SOURCE,
+            destination_address: DESTINATION,
+            next_header: crate::IpProto::ICMPV6,
+        });
+        let mut ipv6_pdu = crate::Ipv6Pdu::new();
+        ipv6_pdu.source_address(SOURCE);
+        ipv6_pdu.destination_address(DESTINATION);
+        ipv6_pdu.next_header(crate::IpProto::ICMPV6);
+        ipv6_pdu.inner(icmpv6_pdu.as_bytes()).unwrap();
+        let ipv6 = crate::Ipv6Parser::parse(ipv6_pdu.as_bytes()).unwrap();
+        let Ok(crate::Ipv6::Icmpv6(icmpv6)) = ipv6.inner() else {
+            panic!("expected ICMPv6");
+        };
+        assert_eq!(icmpv6.computed_checksum(&ipv6), icmpv6.checksum());
+        assert!(matches!(
+            icmpv6.inner(),
+            Ok(Icmpv6::EchoRequest {
+                identifier: 0x1234,
+                sequence_number: 7,
+                data: b"ping",
+            })
+        ));
+    }
+
+    #[test]
+    fn neighbor_solicitation_round_trip() {
+        let mut icmpv6_pdu = Icmpv6Pdu::new();
+        icmpv6_pdu.neighbor_solicitation(SOURCE);
+        icmpv6_pdu
+            .ndp_option(&NdpOption::SourceLinkLayerAddress {
+                address: [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+            })
+            .unwrap();
+        let icmpv6 = Icmpv6Parser::parse(icmpv6_pdu.as_bytes()).unwrap();
+        let Ok(Icmpv6::NeighborSolicitation {
+            target_address,
+            mut options,
+        }) = icmpv6.inner()
+        else {
+            panic!("expected Neighbor Solicitation");
+        };
+        assert_eq!(target_address, SOURCE);
+        assert!(matches!(
+            options.next(),
+            Some(NdpOption::SourceLinkLayerAddress {
+                address: [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+            })
+        ));
+        assert!(options.next().is_none());
+    }
+
+    #[test]
+    fn neighbor_advertisement_round_trip() {
+        let mut icmpv6_pdu = Icmpv6Pdu::new();
+        icmpv6_pdu.neighbor_advertisement(false, true, true, SOURCE);
+        let icmpv6 = Icmpv6Parser::parse(icmpv6_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            icmpv6.inner(),
+            Ok(Icmpv6::NeighborAdvertisement {
+                router: false,
+                solicited: true,
+                override_flag: true,
+                target_address: SOURCE,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn router_advertisement_round_trip() {
+        let mut icmpv6_pdu = Icmpv6Pdu::new();
+        icmpv6_pdu.router_advertisement(64, true, false, 1800, 30000, 1000);
+        icmpv6_pdu
+            .ndp_option(&NdpOption::PrefixInformation {
+                prefix_length: 64,
+                on_link: true,
+                autonomous: true,
+                valid_lifetime: 86400,
+                preferred_lifetime: 14400,
+                prefix: [0x20, 0x01, 0x0D, 0xB8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            })
+            .unwrap();
+        icmpv6_pdu
+            .ndp_option(&NdpOption::Mtu { mtu: 1500 })
+            .unwrap();
+        let icmpv6 = Icmpv6Parser::parse(icmpv6_pdu.as_bytes()).unwrap();
+        let Ok(Icmpv6::RouterAdvertisement {
+            cur_hop_limit: 64,
+            managed: true,
+            other: false,
+            router_lifetime: 1800,
+            reachable_time: 30000,
+            retrans_timer: 1000,
+            mut options,
+        }) = icmpv6.inner()
+        else {
+            panic!("expected Router Advertisement");
+        };
+        assert!(matches!(
+            options.next(),
+            Some(NdpOption::PrefixInformation {
+                prefix_length: 64,
+                on_link: true,
+                autonomous: true,
+                valid_lifetime: 86400,
+                preferred_lifetime: 14400,
+                prefix: [0x20, 0x01, 0x0D, 0xB8, ..],
+            })
+        ));
+        assert!(matches!(options.next(), Some(NdpOption::Mtu { mtu: 1500 })));
+        assert!(options.next().is_none());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut icmpv6_pdu = Icmpv6Pdu::new();
+        icmpv6_pdu.neighbor_solicitation(SOURCE);
+        let bytes = icmpv6_pdu.as_bytes();
+        assert!(matches!(
+            Icmpv6Parser::parse(&bytes[..7]),
+            Err(Error::Truncated)
+        ));
+        let icmpv6 = Icmpv6Parser::parse(&bytes[..23]).unwrap();
+        assert!(matches!(icmpv6.inner(), Err(Error::Truncated)));
+    }
+
+    #[test]
+    fn zero_length_option_ends_iteration() {
+        let mut icmpv6_pdu = Icmpv6Pdu::new();
+        icmpv6_pdu.router_solicitation();
+        icmpv6_pdu
+            .inner(&[0, 0, 0, 0, NdpOptionType::MTU, 0, 0, 0])
+            .unwrap();
+        let icmpv6 = Icmpv6Parser::parse(icmpv6_pdu.as_bytes()).unwrap();
+        let Ok(Icmpv6::RouterSolicitation { mut options }) = icmpv6.inner() else {
+            panic!("expected Router Solicitation");
+        };
+        assert!(options.next().is_none());
+    }
+
+    #[test]
+    fn malformed_and_oversized() {
+        let mut icmpv6_pdu = Icmpv6Pdu::new();
+        icmpv6_pdu.router_solicitation();
+        let raw = NdpOption::Raw {
+            option: 0x0E,
+            data: &[0u8; 6],
+        };
+        assert!(matches!(icmpv6_pdu.ndp_option(&raw), Err(Error::Malformed)));
+        assert!(matches!(
+            icmpv6_pdu.echo_request(0, 0, &[0u8; 1453]),
+            Err(Error::Oversized)
+        ));
+        assert!(icmpv6_pdu.echo_request(0, 0, &[0u8; 1452]).is_ok());
+        assert!(matches!(
+            icmpv6_pdu.ndp_option(&NdpOption::Mtu { mtu: 1500 }),
+            Err(Error::Oversized)
+        ));
+    }
+    #[test]
+    fn builders_reset_the_code() {
+        let mut icmpv6_pdu = Icmpv6Pdu::new();
+        icmpv6_pdu.message_code(3);
+        icmpv6_pdu.echo_reply(1, 1, b"pong").unwrap();
+        assert_eq!(icmpv6_pdu.as_bytes()[1], 0);
+        icmpv6_pdu.message_code(3);
+        icmpv6_pdu.router_solicitation();
+        assert_eq!(icmpv6_pdu.as_bytes()[1], 0);
+        icmpv6_pdu.message_code(3);
+        icmpv6_pdu.router_advertisement(64, false, false, 1800, 0, 0);
+        assert_eq!(icmpv6_pdu.as_bytes()[1], 0);
+        icmpv6_pdu.message_code(3);
+        icmpv6_pdu.neighbor_solicitation(SOURCE);
+        assert_eq!(icmpv6_pdu.as_bytes()[1], 0);
+        icmpv6_pdu.message_code(3);
+        icmpv6_pdu.neighbor_advertisement(false, true, true, SOURCE);
+        assert_eq!(icmpv6_pdu.as_bytes()[1], 0);
+    }
+}
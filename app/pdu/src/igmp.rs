@@ -0,0 +1,414 @@
+use core::convert::TryInto;
+
+use crate::{util, Error, Result};
+
+#[allow(non_snake_case)]
+pub mod IgmpType {
+    pub const MEMBERSHIP_QUERY: u8 = 0x11;
+    pub const V1_MEMBERSHIP_REPORT: u8 = 0x12;
+    pub const V2_MEMBERSHIP_REPORT: u8 = 0x16;
+    pub const LEAVE_GROUP: u8 = 0x17;
+    pub const V3_MEMBERSHIP_REPORT: u8 = 0x22;
+}
+
+#[allow(non_snake_case)]
+pub mod IgmpRecordType {
+    pub const MODE_IS_INCLUDE: u8 = 1;
+    pub const MODE_IS_EXCLUDE: u8 = 2;
+    pub const CHANGE_TO_INCLUDE_MODE: u8 = 3;
+    pub const CHANGE_TO_EXCLUDE_MODE: u8 = 4;
+    pub const ALLOW_NEW_SOURCES: u8 = 5;
+    pub const BLOCK_OLD_SOURCES: u8 = 6;
+}
+
+#[derive(Copy, Clone)]
+pub enum Igmp<'a> {
+    Raw(&'a [u8]),
+    MembershipQuery {
+        max_resp_time: u8,
+        group_address: [u8; 4],
+    },
+    MembershipQueryV3 {
+        max_resp_code: u8,
+        group_address: [u8; 4],
+        suppress_router_processing: bool,
+        robustness_variable: u8,
+        query_interval_code: u8,
+        sources: IgmpSourceIterator<'a>,
+    },
+    MembershipReport {
+        group_address: [u8; 4],
+    },
+    LeaveGroup {
+        group_address: [u8; 4],
+    },
+    MembershipReportV3 {
+        records: IgmpGroupRecordIterator<'a>,
+    },
+}
+
+pub struct IgmpPdu {
+    buffer: [u8; 1476],
+    inner_size: usize,
+}
+
+impl IgmpPdu {
+    pub fn new() -> Self {
+        IgmpPdu {
+            buffer: [0u8; 1476],
+            inner_size: 0,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..8 + self.inner_size]
+    }
+
+    pub fn message_type(&mut self, value: u8) {
+        self.buffer[0] = value;
+    }
+
+    pub fn max_resp_time(&mut self, value: u8) {
+        self.buffer[1] = value;
+    }
+
+    pub fn checksum(&mut self, value: u16) {
+        self.buffer[2..=3].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn compute_checksum(&mut self) {
+        self.checksum(util::checksum([
+            &self.buffer[0..=1],
+            &self.buffer[4..8 + self.inner_size],
+        ]));
+    }
+
+    pub fn group_address(&mut self, value: [u8; 4]) {
+        self.buffer[4..=7].copy_from_slice(&value);
+    }
+
+    pub fn number_of_group_records(&mut self, value: u16) {
+        self.buffer[6..=7].copy_from_slice(&value.to_be_bytes());
+    }
+
+    fn get_number_of_group_records(&self) -> u16 {
+        u16::from_be_bytes(self.buffer[6..=7].try_into().unwrap())
+    }
+
+    pub fn group_record(
+        &mut self,
+        record_type: u8,
+        multicast_address: [u8; 4],
+        sources: &[[u8; 4]],
+    ) -> Result<()> {
+        let pos = 8 + self.inner_size;
+        let len = 8 + sources.len() * 4;
+        if pos + len > 1476 {
+            return Err(Error::Oversized);
+        }
+        self.buffer[pos] = record_type;
+        self.buffer[pos + 1] = 0x00;
+        self.buffer[pos + 2..=pos + 3].copy_from_slice(&(sources.len() as u16).to_be_bytes());
+        self.buffer[pos + 4..=pos + 7].copy_from_slice(&multicast_address);
+        for (i, source) in sources.iter().enumerate() {
+            self.buffer[pos + 8 + i * 4..pos + 12 + i * 4].copy_from_slice(source);
+        }
+        self.inner_size += len;
+        self.number_of_group_records(self.get_number_of_group_records() + 1);
+        Ok(())
+    }
+}
+
+impl Default for IgmpPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct IgmpParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> IgmpParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 8 {
+            return Err(Error::Truncated);
+        }
+        Ok(IgmpParser { buffer })
+    }
+
+    pub fn inner(&'a self) -> Result<Igmp<'a>> {
+        (*self).into_inner()
+    }
+
+    pub fn into_inner(self) -> Result<Igmp<'a>> {
+        let buffer = self.buffer;
+        let group_address = buffer[4..=7].try_into().unwrap();
+        Ok(match self.message_type() {
+            // IGMPv3 queries are distinguished by length (RFC 3376, Section 7.1)
+            IgmpType::MEMBERSHIP_QUERY if buffer.len() >= 12 => {
+                let number_of_sources = u16::from_be_bytes(buffer[10..=11].try_into().unwrap());
+                let end = 12 + number_of_sources as usize * 4;
+                if buffer.len() < end {
+                    return Err(Error::Truncated);
+                }
+                Igmp::MembershipQueryV3 {
+                    max_resp_code: buffer[1],
+                    group_address,
+                    suppress_router_processing: buffer[8] & 0x8 != 0,
+                    robustness_variable: buffer[8] & 0x7,
+                    query_interval_code: buffer[9],
+                    sources: IgmpSourceIterator {
+                        buffer: &buffer[12..end],
+                        pos: 0,
+                    },
+                }
+            }
+            IgmpType::MEMBERSHIP_QUERY => Igmp::MembershipQuery {
+                max_resp_time: buffer[1],
+                group_address,
+            },
+            IgmpType::V1_MEMBERSHIP_REPORT | IgmpType::V2_MEMBERSHIP_REPORT => {
+                Igmp::MembershipReport { group_address }
+            }
+            IgmpType::LEAVE_GROUP => Igmp::LeaveGroup { group_address },
+            IgmpType::V3_MEMBERSHIP_REPORT => Igmp::MembershipReportV3 {
+                records: IgmpGroupRecordIterator {
+                    buffer,
+                    pos: 8,
+                    remaining: u16::from_be_bytes(buffer[6..=7].try_into().unwrap()),
+                },
+            },
+            _ => Igmp::Raw(&buffer[4..]),
+        })
+    }
+
+    pub fn message_type(&'a self) -> u8 {
+        self.buffer[0]
+    }
+
+    pub fn max_resp_time(&'a self) -> u8 {
+        self.buffer[1]
+    }
+
+    pub fn checksum(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[2..=3].try_into().unwrap())
+    }
+
+    pub fn computed_checksum(&'a self) -> u16 {
+        util::checksum([&self.buffer[0..=1], &self.buffer[4..]])
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct IgmpGroupRecord<'a> {
+    pub record_type: u8,
+    pub multicast_address: [u8; 4],
+    pub sources: IgmpSourceIterator<'a>,
+    pub auxiliary_data: &'a [u8],
+}
+
+#[derive(Copy, Clone)]
+pub struct IgmpGroupRecordIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+    remaining: u16,
+}
+
+impl<'a> Iterator for IgmpGroupRecordIterator<'a> {
+    type Item = IgmpGroupRecord<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pos = self.pos;
+        if self.remaining == 0 || self.buffer.len() < pos + 8 {
+            return None;
+        }
+        let aux_data_len = self.buffer[pos + 1] as usize * 4;
+        let number_of_sources =
+            u16::from_be_bytes(self.buffer[pos + 2..=pos + 3].try_into().unwrap()) as usize;
+        let sources_end = pos + 8 + number_of_sources * 4;
+        let len = 8 + number_of_sources * 4 + aux_data_len;
+        if self.buffer.len() < pos + len {
+            return None;
+        }
+        self.pos += len;
+        self.remaining -= 1;
+        Some(IgmpGroupRecord {
+            record_type: self.buffer[pos],
+            multicast_address: self.buffer[pos + 4..=pos + 7].try_into().unwrap(),
+            sources: IgmpSourceIterator {
+                buffer: &self.buffer[pos + 8..sources_end],
+                pos: 0,
+            },
+            auxiliary_data: &self.buffer[sources_end..pos + len],
+        })
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct IgmpSourceIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for IgmpSourceIterator<'a> {
+    type Item = [u8; 4];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.len() < self.pos + 4 {
+            return None;
+        }
+        let pos = self.pos;
+        self.pos += 4;
+        Some(self.buffer[pos..pos + 4].try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn membership_report_round_trip() {
+        let mut igmp_pdu = IgmpPdu::new();
+        igmp_pdu.message_type(IgmpType::V2_MEMBERSHIP_REPORT);
+        igmp_pdu.group_address([239, 1, 2, 3]);
+        igmp_pdu.compute_checksum();
+        let mut ipv4_pdu = crate::Ipv4Pdu::new();
+        ipv4_pdu.ttl(1);
+        ipv4_pdu.protocol(crate::IpProto::IGMP);
+        ipv4_pdu.router_alert(0).unwrap();
+        ipv4_pdu.inner(igmp_pdu.as_bytes()).unwrap();
+        let ipv4 = crate::Ipv4Parser::parse(ipv4_pdu.as_bytes()).unwrap();
+        assert_eq!(ipv4.computed_ihl(), 24);
+        assert!(matches!(
+            ipv4.options().next(),
+            Some(crate::Ipv4Option::RouterAlert { value: 0 })
+        ));
+        let Ok(crate::Ipv4::Igmp(igmp)) = ipv4.inner() else {
+            panic!("expected IGMP");
+        };
+        assert_eq!(igmp.computed_checksum(), igmp.checksum());
+        assert!(matches!(
+            igmp.inner(),
+            Ok(Igmp::MembershipReport {
+                group_address: [239, 1, 2, 3],
+            })
+        ));
+    }
+
+    #[test]
+    fn membership_query_versions() {
+        let mut igmp_pdu = IgmpPdu::new();
+        igmp_pdu.message_type(IgmpType::MEMBERSHIP_QUERY);
+        igmp_pdu.max_resp_time(100);
+        let igmp = IgmpParser::parse(igmp_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            igmp.inner(),
+            Ok(Igmp::MembershipQuery {
+                max_resp_time: 100,
+                group_address: [0, 0, 0, 0],
+            })
+        ));
+        let buffer = [
+            IgmpType::MEMBERSHIP_QUERY,
+            100,
+            0,
+            0,
+            239,
+            1,
+            2,
+            3,
+            0x0A,
+            125,
+            0,
+            1,
+            10,
+            0,
+            0,
+            1,
+        ];
+        let igmp = IgmpParser::parse(&buffer).unwrap();
+        let Ok(Igmp::MembershipQueryV3 {
+            max_resp_code: 100,
+            group_address: [239, 1, 2, 3],
+            suppress_router_processing: true,
+            robustness_variable: 2,
+            query_interval_code: 125,
+            mut sources,
+        }) = igmp.inner()
+        else {
+            panic!("expected an IGMPv3 Membership Query");
+        };
+        assert_eq!(sources.next(), Some([10, 0, 0, 1]));
+        assert_eq!(sources.next(), None);
+        let igmp = IgmpParser::parse(&buffer[..15]).unwrap();
+        assert!(matches!(igmp.inner(), Err(Error::Truncated)));
+    }
+
+    #[test]
+    fn membership_report_v3_round_trip() {
+        let mut igmp_pdu = IgmpPdu::new();
+        igmp_pdu.message_type(IgmpType::V3_MEMBERSHIP_REPORT);
+        igmp_pdu
+            .group_record(
+                IgmpRecordType::MODE_IS_INCLUDE,
+                [239, 1, 2, 3],
+                &[[10, 0, 0, 1], [10, 0, 0, 2]],
+            )
+            .unwrap();
+        igmp_pdu
+            .group_record(IgmpRecordType::CHANGE_TO_EXCLUDE_MODE, [239, 1, 2, 4], &[])
+            .unwrap();
+        igmp_pdu.compute_checksum();
+        let igmp = IgmpParser::parse(igmp_pdu.as_bytes()).unwrap();
+        assert_eq!(igmp.computed_checksum(), igmp.checksum());
+        let Ok(Igmp::MembershipReportV3 { mut records }) = igmp.inner() else {
+            panic!("expected an IGMPv3 Membership Report");
+        };
+        let record = records.next().unwrap();
+        assert_eq!(record.record_type, IgmpRecordType::MODE_IS_INCLUDE);
+        assert_eq!(record.multicast_address, [239, 1, 2, 3]);
+        let mut sources = record.sources;
+        assert_eq!(sources.next(), Some([10, 0, 0, 1]));
+        assert_eq!(sources.next(), Some([10, 0, 0, 2]));
+        assert_eq!(sources.next(), None);
+        assert!(record.auxiliary_data.is_empty());
+        let record = records.next().unwrap();
+        assert_eq!(record.record_type, IgmpRecordType::CHANGE_TO_EXCLUDE_MODE);
+        assert_eq!(record.sources.count(), 0);
+        assert!(records.next().is_none());
+        // A record cut short ends the iteration
+        let igmp = IgmpParser::parse(&igmp_pdu.as_bytes()[..20]).unwrap();
+        let Ok(Igmp::MembershipReportV3 { mut records }) = igmp.inner() else {
+            panic!("expected an IGMPv3 Membership Report");
+        };
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn truncated_and_oversized() {
+        assert!(matches!(
+            IgmpParser::parse(&[IgmpType::LEAVE_GROUP, 0, 0, 0, 239, 1, 2]),
+            Err(Error::Truncated)
+        ));
+        let mut igmp_pdu = IgmpPdu::new();
+        igmp_pdu.message_type(IgmpType::V3_MEMBERSHIP_REPORT);
+        assert!(matches!(
+            igmp_pdu.group_record(
+                IgmpRecordType::ALLOW_NEW_SOURCES,
+                [239, 1, 2, 3],
+                &[[0; 4]; 366]
+            ),
+            Err(Error::Oversized)
+        ));
+        assert!(igmp_pdu
+            .group_record(
+                IgmpRecordType::ALLOW_NEW_SOURCES,
+                [239, 1, 2, 3],
+                &[[0; 4]; 365]
+            )
+            .is_ok());
+    }
+}
@@ -4,14 +4,22 @@ use crate::{util, Error, Result};
 
 #[allow(non_snake_case)]
 pub mod IpProto {
+    pub const HOPOPT: u8 = 0;
     pub const ICMP: u8 = 1;
+    pub const IGMP: u8 = 2;
     pub const TCP: u8 = 6;
     pub const UDP: u8 = 17;
+    pub const IPV6_ROUTE: u8 = 43;
+    pub const IPV6_FRAG: u8 = 44;
+    pub const ICMPV6: u8 = 58;
+    pub const IPV6_NONXT: u8 = 59;
+    pub const IPV6_OPTS: u8 = 60;
 }
 
 #[derive(Copy, Clone)]
 pub enum Ip<'a> {
     Ipv4(Ipv4Parser<'a>),
+    Ipv6(Ipv6Parser<'a>),
 }
 
 impl<'a> Ip<'a> {
@@ -21,6 +29,7 @@ impl<'a> Ip<'a> {
         }
         match buffer[0] >> 4 {
             4 => Ok(Ip::Ipv4(Ipv4Parser::parse(buffer)?)),
+            6 => Ok(Ip::Ipv6(Ipv6Parser::parse(buffer)?)),
             _ => Err(Error::Malformed),
         }
     }
@@ -30,6 +39,7 @@ impl<'a> Ip<'a> {
 pub enum Ipv4<'a> {
     Raw(&'a [u8]),
     Icmp(super::IcmpParser<'a>),
+    Igmp(super::IgmpParser<'a>),
     Tcp(super::TcpParser<'a>),
     Udp(super::UdpParser<'a>),
 }
@@ -142,12 +152,28 @@ impl Ipv4Pdu {
         self.buffer[16..=19].copy_from_slice(&value);
     }
 
-    pub fn options(&mut self, value: &[u8]) {
-        let len = value.len();
-        let ihl = (20 + len + (20 + len) % 4) / 4;
-        self.ihl(ihl as u8);
+    pub fn options(&mut self, value: &[Ipv4Option]) -> Result<()> {
+        let mut options = [0u8; 40];
+        let mut len = 0;
+        for option in value {
+            len += option.write(&mut options[len..])?;
+        }
+        // Padded with End of Option List up to a 32-bit boundary
+        let ihl = 20 + len.div_ceil(4) * 4;
+        let old_ihl = self.computed_ihl();
+        if ihl + self.inner_size > 1500 {
+            return Err(Error::Oversized);
+        }
+        self.buffer
+            .copy_within(old_ihl..old_ihl + self.inner_size, ihl);
+        self.buffer[20..ihl].copy_from_slice(&options[..ihl - 20]);
+        self.ihl((ihl / 4) as u8);
         self.compute_total_length();
-        self.buffer[20..20 + len].copy_from_slice(value);
+        Ok(())
+    }
+
+    pub fn router_alert(&mut self, value: u16) -> Result<()> {
+        self.options(&[Ipv4Option::RouterAlert { value }])
     }
 
     pub fn inner(&mut self, value: &[u8]) -> Result<()> {
@@ -190,13 +216,16 @@ impl<'a> Ipv4Parser<'a> {
     }
 
     pub fn into_inner(self) -> Result<Ipv4<'a>> {
-        let rest = &self.buffer[self.computed_ihl()..];
+        // Ethernet padding must not be treated as upper-layer data
+        let end = (self.total_length() as usize).min(self.buffer.len());
+        let rest = &self.buffer[self.computed_ihl()..end];
 
         if self.fragment_offset() > 0 {
             Ok(Ipv4::Raw(rest))
         } else {
             Ok(match self.protocol() {
                 IpProto::ICMP => Ipv4::Icmp(super::IcmpParser::parse(rest)?),
+                IpProto::IGMP => Ipv4::Igmp(super::IgmpParser::parse(rest)?),
                 IpProto::TCP => Ipv4::Tcp(super::TcpParser::parse(rest)?),
                 IpProto::UDP => Ipv4::Udp(super::UdpParser::parse(rest)?),
                 _ => Ipv4::Raw(rest),
@@ -276,6 +305,13 @@ impl<'a> Ipv4Parser<'a> {
         destination_address
     }
 
+    pub(crate) fn quote(&self) -> &'a [u8] {
+        let len = (self.computed_ihl() + 8)
+            .min(self.total_length() as usize)
+            .min(self.buffer.len());
+        &self.buffer[..len]
+    }
+
     pub fn options(&'a self) -> Ipv4OptionIterator<'a> {
         Ipv4OptionIterator {
             buffer: &self.buffer,
@@ -285,9 +321,164 @@ impl<'a> Ipv4Parser<'a> {
     }
 }
 
+#[allow(non_snake_case)]
+pub mod Ipv4OptionType {
+    pub const END: u8 = 0;
+    pub const NOP: u8 = 1;
+    pub const RECORD_ROUTE: u8 = 7;
+    pub const TIMESTAMP: u8 = 68;
+    pub const SECURITY: u8 = 130;
+    pub const LOOSE_SOURCE_ROUTE: u8 = 131;
+    pub const STRICT_SOURCE_ROUTE: u8 = 137;
+    pub const ROUTER_ALERT: u8 = 148;
+}
+
+#[allow(non_snake_case)]
+pub mod Ipv4TimestampFlag {
+    pub const TIMESTAMP_ONLY: u8 = 0;
+    pub const WITH_ADDRESS: u8 = 1;
+    pub const PRESPECIFIED: u8 = 3;
+}
+
+#[allow(non_snake_case)]
+pub mod Ipv4SecurityClassification {
+    pub const TOP_SECRET: u8 = 0x3D;
+    pub const SECRET: u8 = 0x5A;
+    pub const CONFIDENTIAL: u8 = 0x96;
+    pub const UNCLASSIFIED: u8 = 0xAB;
+}
+
 #[derive(Copy, Clone)]
 pub enum Ipv4Option<'a> {
-    Raw { option: u8, data: &'a [u8] },
+    Raw {
+        option: u8,
+        data: &'a [u8],
+    },
+    End,
+    Nop,
+    RecordRoute {
+        pointer: u8,
+        route: &'a [u8],
+    },
+    Timestamp {
+        pointer: u8,
+        overflow: u8,
+        flag: u8,
+        data: &'a [u8],
+    },
+    LooseSourceRoute {
+        pointer: u8,
+        route: &'a [u8],
+    },
+    StrictSourceRoute {
+        pointer: u8,
+        route: &'a [u8],
+    },
+    RouterAlert {
+        value: u16,
+    },
+    Security {
+        classification: u8,
+        protection_authority: &'a [u8],
+    },
+}
+
+impl<'a> Ipv4Option<'a> {
+    fn write(&self, buffer: &mut [u8]) -> Result<usize> {
+        let len = match self {
+            Ipv4Option::Raw { data, .. } => data.len(),
+            Ipv4Option::End | Ipv4Option::Nop => 1,
+            Ipv4Option::RecordRoute { route, .. }
+            | Ipv4Option::LooseSourceRoute { route, .. }
+            | Ipv4Option::StrictSourceRoute { route, .. } => {
+                if route.len() % 4 != 0 {
+                    return Err(Error::Malformed);
+                }
+                3 + route.len()
+            }
+            Ipv4Option::Timestamp { flag, data, .. } => {
+                let entry = match *flag {
+                    Ipv4TimestampFlag::TIMESTAMP_ONLY => 4,
+                    Ipv4TimestampFlag::WITH_ADDRESS | Ipv4TimestampFlag::PRESPECIFIED => 8,
+                    _ => return Err(Error::Malformed),
+                };
+                if data.len() % entry != 0 {
+                    return Err(Error::Malformed);
+                }
+                4 + data.len()
+            }
+            Ipv4Option::RouterAlert { .. } => 4,
+            Ipv4Option::Security {
+                protection_authority,
+                ..
+            } => 3 + protection_authority.len(),
+        };
+        if len == 0 {
+            return Err(Error::Malformed);
+        }
+        if len > buffer.len() {
+            return Err(Error::Oversized);
+        }
+        let option = &mut buffer[..len];
+        match self {
+            Ipv4Option::Raw { option: kind, data } => {
+                option.copy_from_slice(data);
+                option[0] = *kind;
+            }
+            Ipv4Option::End => option[0] = Ipv4OptionType::END,
+            Ipv4Option::Nop => option[0] = Ipv4OptionType::NOP,
+            Ipv4Option::RecordRoute { pointer, route } => {
+                option[0..=2].copy_from_slice(&[Ipv4OptionType::RECORD_ROUTE, len as u8, *pointer]);
+                option[3..].copy_from_slice(route);
+            }
+            Ipv4Option::LooseSourceRoute { pointer, route } => {
+                option[0..=2].copy_from_slice(&[
+                    Ipv4OptionType::LOOSE_SOURCE_ROUTE,
+                    len as u8,
+                    *pointer,
+                ]);
+                option[3..].copy_from_slice(route);
+            }
+            Ipv4Option::StrictSourceRoute { pointer, route } => {
+                option[0..=2].copy_from_slice(&[
+                    Ipv4OptionType::STRICT_SOURCE_ROUTE,
+                    len as u8,
+                    *pointer,
+                ]);
+                option[3..].copy_from_slice(route);
+            }
+            Ipv4Option::Timestamp {
+                pointer,
+                overflow,
+                flag,
+                data,
+            } => {
+                option[0..=3].copy_from_slice(&[
+                    Ipv4OptionType::TIMESTAMP,
+                    len as u8,
+                    *pointer,
+                    overflow << 4 | flag & 0xF,
+                ]);
+                option[4..].copy_from_slice(data);
+            }
+            Ipv4Option::RouterAlert { value } => {
+                option[0..=1].copy_from_slice(&[Ipv4OptionType::ROUTER_ALERT, 4]);
+                option[2..=3].copy_from_slice(&value.to_be_bytes());
+            }
+            Ipv4Option::Security {
+                classification,
+                protection_authority,
+            } => {
+                option[0..=2].copy_from_slice(&[
+                    Ipv4OptionType::SECURITY,
+                    len as u8,
+                    *classification,
+                ]);
+                option[3..].copy_from_slice(protection_authority);
+            }
+        }
+        Ok(len)
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -321,9 +512,55 @@ impl<'a> Iterator for Ipv4OptionIterator<'a> {
                 return None;
             }
             self.pos += len;
-            Some(Ipv4Option::Raw {
-                option,
-                data: &self.buffer[pos..(pos + len)],
+            let data = &self.buffer[pos..(pos + len)];
+            let raw = Ipv4Option::Raw { option, data };
+            Some(match option {
+                Ipv4OptionType::END => {
+                    // The rest of the header is padding
+                    self.pos = self.ihl;
+                    Ipv4Option::End
+                }
+                Ipv4OptionType::NOP => Ipv4Option::Nop,
+                Ipv4OptionType::RECORD_ROUTE
+                | Ipv4OptionType::LOOSE_SOURCE_ROUTE
+                | Ipv4OptionType::STRICT_SOURCE_ROUTE => {
+                    if len < 3 || (len - 3) % 4 != 0 {
+                        return Some(raw);
+                    }
+                    let (pointer, route) = (data[2], &data[3..]);
+                    match option {
+                        Ipv4OptionType::RECORD_ROUTE => Ipv4Option::RecordRoute { pointer, route },
+                        Ipv4OptionType::LOOSE_SOURCE_ROUTE => {
+                            Ipv4Option::LooseSourceRoute { pointer, route }
+                        }
+                        _ => Ipv4Option::StrictSourceRoute { pointer, route },
+                    }
+                }
+                Ipv4OptionType::TIMESTAMP if len >= 4 => {
+                    let flag = data[3] & 0xF;
+                    let entry = match flag {
+                        Ipv4TimestampFlag::TIMESTAMP_ONLY => 4,
+                        Ipv4TimestampFlag::WITH_ADDRESS | Ipv4TimestampFlag::PRESPECIFIED => 8,
+                        _ => return Some(raw),
+                    };
+                    if (len - 4) % entry != 0 {
+                        return Some(raw);
+                    }
+                    Ipv4Option::Timestamp {
+                        pointer: data[2],
+                        overflow: data[3] >> 4,
+                        flag,
+                        data: &data[4..],
+                    }
+                }
+                Ipv4OptionType::ROUTER_ALERT if len == 4 => Ipv4Option::RouterAlert {
+                    value: u16::from_be_bytes(data[2..=3].try_into().unwrap()),
+                },
+                Ipv4OptionType::SECURITY if len >= 3 => Ipv4Option::Security {
+                    classification: data[2],
+                    protection_authority: &data[3..],
+                },
+                _ => raw,
             })
         } else {
             None
@@ -331,8 +568,359 @@ impl<'a> Iterator for Ipv4OptionIterator<'a> {
     }
 }
 
+#[derive(Copy, Clone)]
+pub enum Ipv6<'a> {
+    Raw(&'a [u8]),
+    Icmpv6(super::Icmpv6Parser<'a>),
+    Tcp(super::TcpParser<'a>),
+    Udp(super::UdpParser<'a>),
+}
+
+pub struct Ipv6Pdu {
+    buffer: [u8; 1500],
+    inner_size: usize,
+}
+
+impl Ipv6Pdu {
+    pub fn new() -> Self {
+        let mut ipv6_pdu = Ipv6Pdu {
+            buffer: [0u8; 1500],
+            inner_size: 0,
+        };
+        ipv6_pdu.version(0x06);
+        ipv6_pdu.hop_limit(0x40);
+        ipv6_pdu
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..40 + self.inner_size]
+    }
+
+    pub fn version(&mut self, value: u8) {
+        let version = self.buffer[0] & 0xF | value << 4;
+        self.buffer[0] = version;
+    }
+
+    pub fn traffic_class(&mut self, value: u8) {
+        self.buffer[0] = self.buffer[0] & 0xF0 | value >> 4;
+        self.buffer[1] = self.buffer[1] & 0xF | value << 4;
+    }
+
+    pub fn flow_label(&mut self, value: u32) {
+        self.buffer[1] = self.buffer[1] & 0xF0 | (value >> 16) as u8 & 0xF;
+        self.buffer[2..=3].copy_from_slice(&(value as u16).to_be_bytes());
+    }
+
+    pub fn payload_length(&mut self, value: u16) {
+        self.buffer[4..=5].copy_from_slice(&value.to_be_bytes());
+    }
+
+    fn compute_payload_length(&mut self) {
+        self.payload_length(self.inner_size as u16);
+    }
+
+    pub fn next_header(&mut self, value: u8) {
+        self.buffer[6] = value;
+    }
+
+    pub fn hop_limit(&mut self, value: u8) {
+        self.buffer[7] = value;
+    }
+
+    pub fn source_address(&mut self, value: [u8; 16]) {
+        self.buffer[8..=23].copy_from_slice(&value);
+    }
+
+    pub fn destination_address(&mut self, value: [u8; 16]) {
+        self.buffer[24..=39].copy_from_slice(&value);
+    }
+
+    pub fn inner(&mut self, value: &[u8]) -> Result<()> {
+        let len = value.len();
+        if len > 1460 {
+            return Err(Error::Oversized);
+        }
+        self.inner_size = len;
+        self.compute_payload_length();
+        self.buffer[40..40 + len].copy_from_slice(value);
+        Ok(())
+    }
+}
+
+impl Default for Ipv6Pdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Ipv6Parser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Ipv6Parser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 40 {
+            return Err(Error::Truncated);
+        }
+        let pdu = Ipv6Parser { buffer };
+        if pdu.version() != 6 {
+            return Err(Error::Malformed);
+        }
+        if buffer.len() < 40 + pdu.payload_length() as usize {
+            return Err(Error::Truncated);
+        }
+        Ok(pdu)
+    }
+
+    pub fn inner(&'a self) -> Result<Ipv6<'a>> {
+        (*self).into_inner()
+    }
+
+    pub fn into_inner(self) -> Result<Ipv6<'a>> {
+        let mut extension_headers = self.extension_headers();
+        let mut fragment_offset = 0;
+        for extension_header in &mut extension_headers {
+            if let Ipv6ExtensionHeader::Fragment { offset, .. } = extension_header {
+                fragment_offset = offset;
+            }
+        }
+        let rest = &extension_headers.buffer[extension_headers.payload_offset()..];
+
+        if fragment_offset > 0 {
+            Ok(Ipv6::Raw(rest))
+        } else {
+            Ok(match extension_headers.next_header() {
+                IpProto::ICMPV6 => Ipv6::Icmpv6(super::Icmpv6Parser::parse(rest)?),
+                IpProto::TCP => Ipv6::Tcp(super::TcpParser::parse(rest)?),
+                IpProto::UDP => Ipv6::Udp(super::UdpParser::parse(rest)?),
+                _ => Ipv6::Raw(rest),
+            })
+        }
+    }
+
+    pub fn version(&'a self) -> u8 {
+        self.buffer[0] >> 4
+    }
+
+    pub fn traffic_class(&'a self) -> u8 {
+        self.buffer[0] << 4 | self.buffer[1] >> 4
+    }
+
+    pub fn flow_label(&'a self) -> u32 {
+        u32::from_be_bytes([0x00, self.buffer[1] & 0xF, self.buffer[2], self.buffer[3]])
+    }
+
+    pub fn payload_length(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[4..=5].try_into().unwrap())
+    }
+
+    pub fn next_header(&'a self) -> u8 {
+        self.buffer[6]
+    }
+
+    pub fn hop_limit(&'a self) -> u8 {
+        self.buffer[7]
+    }
+
+    pub fn source_address(&'a self) -> [u8; 16] {
+        let mut source_address = [0u8; 16];
+        source_address.copy_from_slice(&self.buffer[8..=23]);
+        source_address
+    }
+
+    pub fn destination_address(&'a self) -> [u8; 16] {
+        let mut destination_address = [0u8; 16];
+        destination_address.copy_from_slice(&self.buffer[24..=39]);
+        destination_address
+    }
+
+    pub fn extension_headers(&self) -> Ipv6ExtensionHeaderIterator<'a> {
+        Ipv6ExtensionHeaderIterator {
+            // Ethernet padding must not be treated as extension headers or upper-layer data
+            buffer: &self.buffer[..40 + self.payload_length() as usize],
+            pos: 40,
+            next_header: self.next_header(),
+        }
+    }
+
+    pub fn upper_layer_protocol(&'a self) -> u8 {
+        let mut extension_headers = self.extension_headers();
+        for _ in &mut extension_headers {}
+        extension_headers.next_header()
+    }
+
+    pub fn computed_payload_offset(&'a self) -> usize {
+        let mut extension_headers = self.extension_headers();
+        for _ in &mut extension_headers {}
+        extension_headers.payload_offset()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum Ipv6ExtensionHeader<'a> {
+    HopByHop {
+        next_header: u8,
+        options: Ipv6OptionIterator<'a>,
+    },
+    Routing {
+        next_header: u8,
+        routing_type: u8,
+        segments_left: u8,
+        data: &'a [u8],
+    },
+    Fragment {
+        next_header: u8,
+        offset: u16,
+        more_fragments: bool,
+        identification: u32,
+    },
+    DestinationOptions {
+        next_header: u8,
+        options: Ipv6OptionIterator<'a>,
+    },
+}
+
+#[derive(Copy, Clone)]
+pub struct Ipv6ExtensionHeaderIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+    next_header: u8,
+}
+
+impl<'a> Ipv6ExtensionHeaderIterator<'a> {
+    pub fn next_header(&self) -> u8 {
+        self.next_header
+    }
+
+    pub fn payload_offset(&self) -> usize {
+        self.pos
+    }
+}
+
+impl<'a> Iterator for Ipv6ExtensionHeaderIterator<'a> {
+    type Item = Ipv6ExtensionHeader<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pos = self.pos;
+        let len = match self.next_header {
+            IpProto::HOPOPT | IpProto::IPV6_ROUTE | IpProto::IPV6_OPTS => {
+                if self.buffer.len() < pos + 2 {
+                    return None;
+                }
+                (self.buffer[pos + 1] as usize + 1) * 8
+            }
+            IpProto::IPV6_FRAG => 8,
+            _ => return None,
+        };
+        if self.buffer.len() < pos + len {
+            return None;
+        }
+        let next_header = self.buffer[pos];
+        let extension_header = match self.next_header {
+            IpProto::HOPOPT => Ipv6ExtensionHeader::HopByHop {
+                next_header,
+                options: Ipv6OptionIterator {
+                    buffer: &self.buffer[..pos + len],
+                    pos: pos + 2,
+                },
+            },
+            IpProto::IPV6_ROUTE => Ipv6ExtensionHeader::Routing {
+                next_header,
+                routing_type: self.buffer[pos + 2],
+                segments_left: self.buffer[pos + 3],
+                data: &self.buffer[pos + 4..pos + len],
+            },
+            IpProto::IPV6_FRAG => Ipv6ExtensionHeader::Fragment {
+                next_header,
+                offset: u16::from_be_bytes(self.buffer[pos + 2..=pos + 3].try_into().unwrap()) >> 3,
+                more_fragments: self.buffer[pos + 3] & 0x1 != 0,
+                identification: u32::from_be_bytes(
+                    self.buffer[pos + 4..=pos + 7].try_into().unwrap(),
+                ),
+            },
+            _ => Ipv6ExtensionHeader::DestinationOptions {
+                next_header,
+                options: Ipv6OptionIterator {
+                    buffer: &self.buffer[..pos + len],
+                    pos: pos + 2,
+                },
+            },
+        };
+        self.pos += len;
+        self.next_header = next_header;
+        Some(extension_header)
+    }
+}
+
+#[allow(non_snake_case)]
+pub mod Ipv6OptionType {
+    pub const PAD1: u8 = 0x00;
+    pub const PADN: u8 = 0x01;
+    pub const ROUTER_ALERT: u8 = 0x05;
+    pub const JUMBO_PAYLOAD: u8 = 0xC2;
+}
+
+#[derive(Copy, Clone)]
+pub enum Ipv6Option<'a> {
+    Raw { option: u8, data: &'a [u8] },
+    Pad1,
+    PadN { len: u8 },
+    RouterAlert { value: u16 },
+    Jumbo { payload_length: u32 },
+}
+
+#[derive(Copy, Clone)]
+pub struct Ipv6OptionIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for Ipv6OptionIterator<'a> {
+    type Item = Ipv6Option<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos < self.buffer.len() {
+            let pos = self.pos;
+            let option = self.buffer[pos];
+            if option == Ipv6OptionType::PAD1 {
+                self.pos += 1;
+                return Some(Ipv6Option::Pad1);
+            }
+            if self.buffer.len() <= (pos + 1) {
+                return None;
+            }
+            let len = self.buffer[pos + 1] as usize + 2;
+            if self.buffer.len() < (pos + len) {
+                return None;
+            }
+            self.pos += len;
+            match option {
+                Ipv6OptionType::PADN => Some(Ipv6Option::PadN {
+                    len: self.buffer[pos + 1],
+                }),
+                Ipv6OptionType::ROUTER_ALERT if len == 4 => Some(Ipv6Option::RouterAlert {
+                    value: u16::from_be_bytes(self.buffer[pos + 2..=pos + 3].try_into().unwrap()),
+                }),
+                Ipv6OptionType::JUMBO_PAYLOAD if len == 6 => Some(Ipv6Option::Jumbo {
+                    payload_length: u32::from_be_bytes(
+                        self.buffer[pos + 2..=pos + 5].try_into().unwrap(),
+                    ),
+                }),
+                _ => Some(Ipv6Option::Raw {
+                    option,
+                    data: &self.buffer[pos..(pos + len)],
+                }),
+            }
+        } else {
+            None
+        }
+    }
+}
+
 pub enum IpPseudoHeader {
     Ipv4(Ipv4PseudoHeader),
+    Ipv6(Ipv6PseudoHeader),
 }
 
 pub struct Ipv4PseudoHeader {
@@ -340,3 +928,407 @@ pub struct Ipv4PseudoHeader {
     pub destination_address: [u8; 4],
     pub protocol: u8,
 }
+
+pub struct Ipv6PseudoHeader {
+    pub source_address: [u8; 16],
+    pub destination_address: [u8; 16],
+    pub next_header: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: [u8; 16] = [0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01];
+    const DESTINATION: [u8; 16] = [0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x02];
+
+    fn ipv6_pdu(next_header: u8, inner: &[u8]) -> Ipv6Pdu {
+        let mut ipv6_pdu = Ipv6Pdu::new();
+        ipv6_pdu.source_address(SOURCE);
+        ipv6_pdu.destination_address(DESTINATION);
+        ipv6_pdu.next_header(next_header);
+        ipv6_pdu.inner(inner).unwrap();
+        ipv6_pdu
+    }
+
+    #[test]
+    fn ipv6_round_trip() {
+        let mut ipv6_pdu = ipv6_pdu(IpProto::IPV6_NONXT, &[0xAA; 8]);
+        ipv6_pdu.traffic_class(0xB8);
+        ipv6_pdu.flow_label(0x12345);
+        let ipv6 = Ipv6Parser::parse(ipv6_pdu.as_bytes()).unwrap();
+        assert_eq!(ipv6.version(), 6);
+        assert_eq!(ipv6.traffic_class(), 0xB8);
+        assert_eq!(ipv6.flow_label(), 0x12345);
+        assert_eq!(ipv6.payload_length(), 8);
+        assert_eq!(ipv6.next_header(), IpProto::IPV6_NONXT);
+        assert_eq!(ipv6.hop_limit(), 0x40);
+        assert_eq!(ipv6.source_address(), SOURCE);
+        assert_eq!(ipv6.destination_address(), DESTINATION);
+        assert!(matches!(
+            ipv6.inner(),
+            Ok(Ipv6::Raw(&[0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA]))
+        ));
+    }
+
+    #[test]
+    fn ipv6_ignores_ethernet_padding() {
+        let ipv6_pdu = ipv6_pdu(IpProto::IPV6_NONXT, &[0xAA; 2]);
+        let mut buffer = [0u8; 46];
+        buffer[..42].copy_from_slice(ipv6_pdu.as_bytes());
+        let ipv6 = Ipv6Parser::parse(&buffer).unwrap();
+        assert!(matches!(ipv6.inner(), Ok(Ipv6::Raw(&[0xAA, 0xAA]))));
+    }
+
+    #[test]
+    fn ipv6_udp_checksum() {
+        let mut udp_pdu = crate::UdpPdu::new();
+        udp_pdu.source_port(40000);
+        udp_pdu.destination_port(40001);
+        udp_pdu.inner(b"hello").unwrap();
+        udp_pdu.compute_checksum(&IpPseudoHeader::Ipv6(Ipv6PseudoHeader {
+            source_address: SOURCE,
+            destination_address: DESTINATION,
+            next_header: IpProto::UDP,
+        }));
+        let ipv6_pdu = ipv6_pdu(IpProto::UDP, udp_pdu.as_bytes());
+        let ipv6 = Ipv6Parser::parse(ipv6_pdu.as_bytes()).unwrap();
+        let Ok(Ipv6::Udp(udp)) = ipv6.inner() else {
+            panic!("expected UDP");
+        };
+        assert_eq!(udp.computed_checksum(&Ip::Ipv6(ipv6)), udp.checksum());
+    }
+
+    #[test]
+    fn ipv6_tcp_checksum() {
+        let mut tcp_pdu = crate::TcpPdu::new();
+        tcp_pdu.source_port(40000);
+        tcp_pdu.destination_port(80);
+        tcp_pdu.syn(true);
+        tcp_pdu.inner(b"hello").unwrap();
+        tcp_pdu.compute_checksum(&IpPseudoHeader::Ipv6(Ipv6PseudoHeader {
+            source_address: SOURCE,
+            destination_address: DESTINATION,
+            next_header: IpProto::TCP,
+        }));
+        let ipv6_pdu = ipv6_pdu(IpProto::TCP, tcp_pdu.as_bytes());
+        let ipv6 = Ipv6Parser::parse(ipv6_pdu.as_bytes()).unwrap();
+        let Ok(Ipv6::Tcp(tcp)) = ipv6.inner() else {
+            panic!("expected TCP");
+        };
+        assert_eq!(tcp.computed_checksum(&Ip::Ipv6(ipv6)), tcp.checksum());
+    }
+
+    #[test]
+    fn ipv6_truncated() {
+        let ipv6_pdu = ipv6_pdu(IpProto::IPV6_NONXT, &[0xAA; 8]);
+        let bytes = ipv6_pdu.as_bytes();
+        assert!(matches!(
+            Ipv6Parser::parse(&bytes[..39]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            Ipv6Parser::parse(&bytes[..47]),
+            Err(Error::Truncated)
+        ));
+    }
+
+    #[test]
+    fn ipv6_malformed_version() {
+        let mut ipv6_pdu = ipv6_pdu(IpProto::IPV6_NONXT, &[]);
+        ipv6_pdu.version(4);
+        assert!(matches!(
+            Ipv6Parser::parse(ipv6_pdu.as_bytes()),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn ipv6_oversized() {
+        let mut ipv6_pdu = Ipv6Pdu::new();
+        assert!(ipv6_pdu.inner(&[0u8; 1460]).is_ok());
+        assert!(matches!(
+            ipv6_pdu.inner(&[0u8; 1461]),
+            Err(Error::Oversized)
+        ));
+    }
+
+    #[test]
+    fn ipv6_extension_headers() {
+        let mut payload = [0u8; 32];
+        // Hop-by-Hop Options with Router Alert and PadN
+        payload[0..8].copy_from_slice(&[
+            IpProto::IPV6_ROUTE,
+            0,
+            0x05,
+            0x02,
+            0x00,
+            0x00,
+            0x01,
+            0x00,
+        ]);
+        // Routing header with no segments left
+        payload[8..16].copy_from_slice(&[IpProto::IPV6_FRAG, 0, 0x04, 0x00, 0, 0, 0, 0]);
+        // First fragment with more fragments to follow
+        payload[16..24].copy_from_slice(&[
+            IpProto::IPV6_NONXT,
+            0,
+            0x00,
+            0x01,
+            0x12,
+            0x34,
+            0x56,
+            0x78,
+        ]);
+        let ipv6_pdu = ipv6_pdu(IpProto::HOPOPT, &payload[..24]);
+        let ipv6 = Ipv6Parser::parse(ipv6_pdu.as_bytes()).unwrap();
+        let mut extension_headers = ipv6.extension_headers();
+        let Some(Ipv6ExtensionHeader::HopByHop {
+            next_header,
+            mut options,
+        }) = extension_headers.next()
+        else {
+            panic!("expected Hop-by-Hop Options");
+        };
+        assert_eq!(next_header, IpProto::IPV6_ROUTE);
+        assert!(matches!(
+            options.next(),
+            Some(Ipv6Option::RouterAlert { value: 0 })
+        ));
+        assert!(matches!(options.next(), Some(Ipv6Option::PadN { len: 0 })));
+        assert!(options.next().is_none());
+        assert!(matches!(
+            extension_headers.next(),
+            Some(Ipv6ExtensionHeader::Routing {
+                next_header: IpProto::IPV6_FRAG,
+                routing_type: 4,
+                segments_left: 0,
+                data: &[0, 0, 0, 0],
+            })
+        ));
+        assert!(matches!(
+            extension_headers.next(),
+            Some(Ipv6ExtensionHeader::Fragment {
+                next_header: IpProto::IPV6_NONXT,
+                offset: 0,
+                more_fragments: true,
+                identification: 0x12345678,
+            })
+        ));
+        assert!(extension_headers.next().is_none());
+        assert_eq!(ipv6.upper_layer_protocol(), IpProto::IPV6_NONXT);
+        assert_eq!(ipv6.computed_payload_offset(), 64);
+        assert!(matches!(ipv6.inner(), Ok(Ipv6::Raw(&[]))));
+    }
+
+    #[test]
+    fn ipv6_later_fragment_is_raw() {
+        // Offset 1 (8 octets) of a UDP datagram, which has no UDP header to parse
+        let payload = [IpProto::UDP, 0, 0x00, 0x08, 0, 0, 0, 0x01, 0xAA, 0xAA];
+        let ipv6_pdu = ipv6_pdu(IpProto::IPV6_FRAG, &payload);
+        let ipv6 = Ipv6Parser::parse(ipv6_pdu.as_bytes()).unwrap();
+        assert_eq!(ipv6.upper_layer_protocol(), IpProto::UDP);
+        assert!(matches!(ipv6.inner(), Ok(Ipv6::Raw(&[0xAA, 0xAA]))));
+    }
+
+    #[test]
+    fn ipv6_truncated_extension_header() {
+        // The Hop-by-Hop Options header claims 16 octets but only 8 are present
+        let payload = [IpProto::IPV6_NONXT, 1, 0x01, 0x04, 0, 0, 0, 0];
+        let ipv6_pdu = ipv6_pdu(IpProto::HOPOPT, &payload);
+        let ipv6 = Ipv6Parser::parse(ipv6_pdu.as_bytes()).unwrap();
+        assert!(ipv6.extension_headers().next().is_none());
+        assert_eq!(ipv6.upper_layer_protocol(), IpProto::HOPOPT);
+        assert_eq!(ipv6.computed_payload_offset(), 40);
+    }
+
+    #[test]
+    fn ipv6_options() {
+        let buffer = [
+            0x00, // Pad1
+            0xC2, 0x04, 0x00, 0x01, 0x00, 0x00, // Jumbo Payload
+            0x3E, 0x01, 0xFF, // Unknown option
+            0x01, 0x04, 0x00, // PadN cut short
+        ];
+        let mut options = Ipv6OptionIterator {
+            buffer: &buffer,
+            pos: 0,
+        };
+        assert!(matches!(options.next(), Some(Ipv6Option::Pad1)));
+        assert!(matches!(
+            options.next(),
+            Some(Ipv6Option::Jumbo {
+                payload_length: 0x10000
+            })
+        ));
+        assert!(matches!(
+            options.next(),
+            Some(Ipv6Option::Raw {
+                option: 0x3E,
+                data: &[0x3E, 0x01, 0xFF]
+            })
+        ));
+        assert!(options.next().is_none());
+    }
+
+    #[test]
+    fn ipv4_options_round_trip() {
+        let mut ipv4_pdu = Ipv4Pdu::new();
+        ipv4_pdu.protocol(253);
+        ipv4_pdu.inner(&[0xAB; 8]).unwrap();
+        ipv4_pdu
+            .options(&[
+                Ipv4Option::Nop,
+                Ipv4Option::RecordRoute {
+                    pointer: 4,
+                    route: &[0; 8],
+                },
+                Ipv4Option::RouterAlert { value: 0 },
+                Ipv4Option::Timestamp {
+                    pointer: 5,
+                    overflow: 2,
+                    flag: Ipv4TimestampFlag::TIMESTAMP_ONLY,
+                    data: &[0; 4],
+                },
+                Ipv4Option::Security {
+                    classification: Ipv4SecurityClassification::UNCLASSIFIED,
+                    protection_authority: &[0x80, 0x00],
+                },
+            ])
+            .unwrap();
+        ipv4_pdu.compute_checksum();
+        let ipv4 = Ipv4Parser::parse(ipv4_pdu.as_bytes()).unwrap();
+        // 29 octets of options are padded to 32
+        assert_eq!(ipv4.ihl(), 13);
+        assert_eq!(ipv4.total_length(), 60);
+        assert_eq!(ipv4.checksum(), ipv4.computed_checksum());
+        let mut options = ipv4.options();
+        assert!(matches!(options.next(), Some(Ipv4Option::Nop)));
+        assert!(matches!(
+            options.next(),
+            Some(Ipv4Option::RecordRoute {
+                pointer: 4,
+                route: &[0, 0, 0, 0, 0, 0, 0, 0],
+            })
+        ));
+        assert!(matches!(
+            options.next(),
+            Some(Ipv4Option::RouterAlert { value: 0 })
+        ));
+        assert!(matches!(
+            options.next(),
+            Some(Ipv4Option::Timestamp {
+                pointer: 5,
+                overflow: 2,
+                flag: Ipv4TimestampFlag::TIMESTAMP_ONLY,
+                data: &[0, 0, 0, 0],
+            })
+        ));
+        assert!(matches!(
+            options.next(),
+            Some(Ipv4Option::Security {
+                classification: Ipv4SecurityClassification::UNCLASSIFIED,
+                protection_authority: &[0x80, 0x00],
+            })
+        ));
+        assert!(matches!(options.next(), Some(Ipv4Option::End)));
+        assert!(options.next().is_none());
+        let Ok(Ipv4::Raw(rest)) = ipv4.inner() else {
+            panic!("expected raw payload");
+        };
+        assert_eq!(rest, [0xAB; 8]);
+
+        // Shorter options move the payload back
+        ipv4_pdu.router_alert(0).unwrap();
+        let ipv4 = Ipv4Parser::parse(ipv4_pdu.as_bytes()).unwrap();
+        assert_eq!(ipv4.ihl(), 6);
+        assert_eq!(ipv4_pdu.as_bytes()[20..24], [0x94, 0x04, 0x00, 0x00]);
+        let Ok(Ipv4::Raw(rest)) = ipv4.inner() else {
+            panic!("expected raw payload");
+        };
+        assert_eq!(rest, [0xAB; 8]);
+        ipv4_pdu.options(&[]).unwrap();
+        assert_eq!(ipv4_pdu.as_bytes().len(), 28);
+    }
+
+    #[test]
+    fn ipv4_options_builder_errors() {
+        let mut ipv4_pdu = Ipv4Pdu::new();
+        assert!(matches!(
+            ipv4_pdu.options(&[Ipv4Option::RecordRoute {
+                pointer: 4,
+                route: &[0; 3],
+            }]),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            ipv4_pdu.options(&[Ipv4Option::Timestamp {
+                pointer: 5,
+                overflow: 0,
+                flag: 2,
+                data: &[],
+            }]),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            ipv4_pdu.options(&[Ipv4Option::Timestamp {
+                pointer: 5,
+                overflow: 0,
+                flag: Ipv4TimestampFlag::WITH_ADDRESS,
+                data: &[0; 4],
+            }]),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            ipv4_pdu.options(&[Ipv4Option::Raw {
+                option: 25,
+                data: &[],
+            }]),
+            Err(Error::Malformed)
+        ));
+        // More than 40 octets of options
+        assert!(matches!(
+            ipv4_pdu.options(&[Ipv4Option::RecordRoute {
+                pointer: 4,
+                route: &[0; 40],
+            }]),
+            Err(Error::Oversized)
+        ));
+        ipv4_pdu.inner(&[0; 1480]).unwrap();
+        assert!(matches!(ipv4_pdu.router_alert(0), Err(Error::Oversized)));
+        assert_eq!(ipv4_pdu.as_bytes().len(), 1500);
+        assert_eq!(ipv4_pdu.as_bytes()[0], 0x45);
+    }
+
+    #[test]
+    fn ipv4_malformed_options() {
+        let mut ipv4_pdu = Ipv4Pdu::new();
+        // A route that is not a whole number of addresses
+        ipv4_pdu
+            .options(&[Ipv4Option::Raw {
+                option: Ipv4OptionType::RECORD_ROUTE,
+                data: &[0, 5, 4, 0, 0],
+            }])
+            .unwrap();
+        let ipv4 = Ipv4Parser::parse(ipv4_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            ipv4.options().next(),
+            Some(Ipv4Option::Raw {
+                option: Ipv4OptionType::RECORD_ROUTE,
+                ..
+            })
+        ));
+        // Option lengths below two or past the header end the iteration
+        ipv4_pdu.router_alert(0).unwrap();
+        let mut bytes = [0u8; 24];
+        bytes.copy_from_slice(ipv4_pdu.as_bytes());
+        bytes[20..24].copy_from_slice(&[Ipv4OptionType::NOP, Ipv4OptionType::ROUTER_ALERT, 1, 0]);
+        let ipv4 = Ipv4Parser::parse(&bytes).unwrap();
+        let mut options = ipv4.options();
+        assert!(matches!(options.next(), Some(Ipv4Option::Nop)));
+        assert!(options.next().is_none());
+        bytes[22] = 8;
+        let ipv4 = Ipv4Parser::parse(&bytes).unwrap();
+        assert_eq!(ipv4.options().count(), 1);
+    }
+}
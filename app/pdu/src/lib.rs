@@ -1,34 +1,122 @@
 #![no_std]
 
 mod ethernet;
-pub use ethernet::{Ethernet, EtherType, EthernetPdu, EthernetParser};
+pub use ethernet::{Ethernet, EtherType, VlanTag, VlanTagIterator, EthernetPdu, EthernetParser};
 
 mod arp;
 pub use arp::{ArpOpcode, ArpPdu, ArpParser};
 
+mod mac_control;
+pub use mac_control::{
+    MacControl, MacControlAddress, MacControlOpcode, MacControlPdu, MacControlParser,
+};
+
+mod llc;
+pub use llc::{Llc, LlcControl, LlcSap, LlcPdu, LlcParser};
+
+mod lldp;
+pub use lldp::{
+    LldpAddress, LldpCapability, LldpChassisIdSubtype, LldpPortIdSubtype, LldpTlv,
+    LldpTlvIterator, LldpTlvType, LldpPdu, LldpParser,
+};
+
 mod ip;
-pub use ip::{Ip, IpProto, IpPseudoHeader, Ipv4, Ipv4Option, Ipv4PseudoHeader, Ipv4Pdu, Ipv4Parser};
+pub use ip::{
+    Ip, IpProto, IpPseudoHeader, Ipv4, Ipv4Option, Ipv4OptionIterator, Ipv4OptionType,
+    Ipv4PseudoHeader, Ipv4SecurityClassification, Ipv4TimestampFlag, Ipv4Pdu, Ipv4Parser, Ipv6,
+    Ipv6ExtensionHeader, Ipv6ExtensionHeaderIterator, Ipv6Option, Ipv6OptionIterator,
+    Ipv6OptionType, Ipv6PseudoHeader, Ipv6Pdu, Ipv6Parser,
+};
 
 mod tcp;
-pub use tcp::{Tcp, TcpFlag, TcpOption, TcpState, TcpPdu, TcpParser};
+pub use tcp::{Tcp, TcpFlag, TcpOption, TcpOptionIterator, TcpPort, TcpState, TcpPdu, TcpParser};
 
 mod udp;
-pub use udp::{Udp, UdpPdu, UdpParser};
+pub use udp::{Udp, UdpPort, UdpPdu, UdpParser};
 
 mod icmp;
-pub use icmp::{Icmp, IcmpType, IcmpPdu, IcmpParser};
+pub use icmp::{
+    Icmp, IcmpRedirectCode, IcmpTimeExceededCode, IcmpType, IcmpUnreachableCode, IcmpPdu,
+    IcmpParser,
+};
+
+mod icmpv6;
+pub use icmpv6::{
+    Icmpv6, Icmpv6Type, Icmpv6Pdu, Icmpv6Parser, NdpOption, NdpOptionIterator, NdpOptionType,
+};
+
+mod igmp;
+pub use igmp::{
+    Igmp, IgmpGroupRecord, IgmpGroupRecordIterator, IgmpRecordType, IgmpSourceIterator, IgmpType,
+    IgmpPdu, IgmpParser,
+};
+
+mod dhcp;
+pub use dhcp::{
+    DhcpAddressIterator, DhcpMessageType, DhcpOp, DhcpOption, DhcpOptionCode, DhcpOptionIterator,
+    DhcpPdu, DhcpParser,
+};
+
+mod dns;
+pub use dns::{
+    DnsClass, DnsLabelIterator, DnsName, DnsOpcode, DnsQuestion, DnsQuestionIterator, DnsRData,
+    DnsRcode, DnsRecord, DnsRecordIterator, DnsTxt, DnsTxtIterator, DnsType, DnsPdu, DnsParser,
+};
+
+mod ntp;
+pub use ntp::{NtpLeapIndicator, NtpMode, NtpTimestamp, NtpPdu, NtpParser};
+
+mod tftp;
+pub use tftp::{
+    Tftp, TftpErrorCode, TftpMode, TftpOpcode, TftpOption, TftpOptionIterator, TftpPdu, TftpParser,
+};
+
+mod syslog;
+pub use syslog::{SyslogFacility, SyslogHeader, SyslogSeverity, SyslogTimestamp, SyslogPdu};
+
+mod modbus;
+pub use modbus::{
+    ModbusBitIterator, ModbusException, ModbusFunction, ModbusRegisterIterator, ModbusRequest,
+    ModbusResponse, ModbusPdu, ModbusParser,
+};
+
+mod mqtt;
+pub use mqtt::{
+    Mqtt, MqttConnectReturnCode, MqttPacketType, MqttQos, MqttStream, MqttSubscribeReturnCode,
+    MqttSubscriptionIterator, MqttTopicIterator, MqttWill, MqttPdu, MqttParser,
+};
+
+mod coap;
+pub use coap::{
+    CoapBlock, CoapCode, CoapContentFormat, CoapOption, CoapOptionIterator, CoapOptionNumber,
+    CoapType, CoapPdu, CoapParser,
+};
+
+mod wol;
+pub use wol::{WolPdu, WolParser};
 
 mod http;
-pub use http::{Http, HttpPdu, HttpParser};
+pub use http::{
+    Http, HttpChunkedDecoder, HttpChunkedIterator, HttpContentType, HttpFormField,
+    HttpFormIterator, HttpFormParser, HttpHeader, HttpHeaderIterator, HttpMethod, HttpMultipart,
+    HttpMultipartDecoder, HttpPart, HttpPdu, HttpParser, HttpPercentDecoder, HttpStatus,
+};
+
+mod websocket;
+pub use websocket::{
+    WebSocket, WebSocketCloseCode, WebSocketHandshake, WebSocketOpcode, WebSocketParser,
+    WebSocketPayload, WebSocketPdu, WebSocketStream,
+};
 
 mod util;
-pub use util::{checksum, crc32, Xorshift32};
+pub use util::{base64_decode, base64_encode, checksum, crc32, sha1, Xorshift32};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Error {
     Truncated,
     Oversized,
     Malformed,
+    Incomplete,
 }
 
 pub type Result<T> = core::result::Result<T, Error>;
@@ -0,0 +1,289 @@
+use core::convert::TryInto;
+
+use crate::{Error, Result};
+
+#[allow(non_snake_case)]
+pub mod LlcSap {
+    pub const NULL: u8 = 0x00;
+    pub const SPANNING_TREE: u8 = 0x42;
+    pub const SNAP: u8 = 0xAA;
+    pub const NETBIOS: u8 = 0xF0;
+    pub const GLOBAL: u8 = 0xFF;
+}
+
+#[allow(non_snake_case)]
+pub mod LlcControl {
+    pub const UI: u16 = 0x03;
+    pub const XID: u16 = 0xAF;
+    pub const TEST: u16 = 0xE3;
+}
+
+#[derive(Copy, Clone)]
+pub enum Llc<'a> {
+    Raw(&'a [u8]),
+    Snap {
+        oui: [u8; 3],
+        protocol_id: u16,
+        data: &'a [u8],
+    },
+}
+
+pub struct LlcPdu {
+    buffer: [u8; 1500],
+    inner_size: usize,
+}
+
+impl LlcPdu {
+    pub fn new() -> Self {
+        let mut llc_pdu = LlcPdu {
+            buffer: [0u8; 1500],
+            inner_size: 0,
+        };
+        llc_pdu.control(LlcControl::UI);
+        llc_pdu
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..self.computed_header_length() + self.inner_size]
+    }
+
+    fn computed_header_length(&self) -> usize {
+        header_length(&self.buffer)
+    }
+
+    pub fn dsap(&mut self, value: u8) {
+        self.buffer[0] = value;
+    }
+
+    pub fn ssap(&mut self, value: u8) {
+        self.buffer[1] = value;
+    }
+
+    pub fn control(&mut self, value: u16) {
+        if value & 0x03 == 0x03 {
+            self.buffer[2] = value as u8;
+        } else {
+            self.buffer[2..=3].copy_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    pub fn snap(&mut self, oui: [u8; 3], protocol_id: u16) {
+        self.dsap(LlcSap::SNAP);
+        self.ssap(LlcSap::SNAP);
+        self.control(LlcControl::UI);
+        self.buffer[3..=5].copy_from_slice(&oui);
+        self.buffer[6..=7].copy_from_slice(&protocol_id.to_be_bytes());
+    }
+
+    pub fn inner(&mut self, value: &[u8]) -> Result<()> {
+        let len = value.len();
+        let header_length = self.computed_header_length();
+        if header_length + len > 1500 {
+            return Err(Error::Oversized);
+        }
+        self.inner_size = len;
+        self.buffer[header_length..header_length + len].copy_from_slice(value);
+        Ok(())
+    }
+}
+
+impl Default for LlcPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct LlcParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> LlcParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 3 {
+            return Err(Error::Truncated);
+        }
+        if buffer.len() < header_length(buffer) {
+            return Err(Error::Truncated);
+        }
+        Ok(LlcParser { buffer })
+    }
+
+    pub fn inner(&'a self) -> Result<Llc<'a>> {
+        (*self).into_inner()
+    }
+
+    pub fn into_inner(self) -> Result<Llc<'a>> {
+        let rest = &self.buffer[self.computed_header_length()..];
+        Ok(match self.snap() {
+            Some((oui, protocol_id)) => Llc::Snap {
+                oui,
+                protocol_id,
+                data: rest,
+            },
+            None => Llc::Raw(rest),
+        })
+    }
+
+    pub fn computed_header_length(&'a self) -> usize {
+        header_length(self.buffer)
+    }
+
+    pub fn dsap(&'a self) -> u8 {
+        self.buffer[0]
+    }
+
+    pub fn ssap(&'a self) -> u8 {
+        self.buffer[1]
+    }
+
+    pub fn control(&'a self) -> u16 {
+        // The first control octet is kept in the low byte so that U-format values read as-is
+        if self.buffer[2] & 0x03 == 0x03 {
+            self.buffer[2] as u16
+        } else {
+            u16::from_le_bytes(self.buffer[2..=3].try_into().unwrap())
+        }
+    }
+
+    pub fn snap(&'a self) -> Option<([u8; 3], u16)> {
+        if !is_snap(self.buffer) {
+            return None;
+        }
+        Some((
+            self.buffer[3..=5].try_into().unwrap(),
+            u16::from_be_bytes(self.buffer[6..=7].try_into().unwrap()),
+        ))
+    }
+}
+
+fn is_snap(buffer: &[u8]) -> bool {
+    // The low bit of the SSAP is the command/response flag
+    buffer[0] == LlcSap::SNAP && buffer[1] & 0xFE == LlcSap::SNAP && buffer[2] == 0x03
+}
+
+fn header_length(buffer: &[u8]) -> usize {
+    // I-format and S-format PDUs carry a two-octet control field
+    let control_length = if buffer[2] & 0x03 == 0x03 { 1 } else { 2 };
+    if is_snap(buffer) {
+        8
+    } else {
+        2 + control_length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_round_trip_over_ethernet() {
+        let mut llc_pdu = LlcPdu::new();
+        llc_pdu.dsap(LlcSap::SPANNING_TREE);
+        llc_pdu.ssap(LlcSap::SPANNING_TREE);
+        llc_pdu.inner(&[0x00, 0x00, 0x00, 0x80]).unwrap();
+        assert_eq!(
+            llc_pdu.as_bytes(),
+            [0x42, 0x42, 0x03, 0x00, 0x00, 0x00, 0x80]
+        );
+
+        // A type field below 0x0600 makes this an IEEE 802.3 frame
+        let mut ethernet_pdu = crate::EthernetPdu::new(0);
+        ethernet_pdu.inner(llc_pdu.as_bytes()).unwrap();
+        let ethernet = crate::EthernetParser::parse(ethernet_pdu.as_bytes()).unwrap();
+        assert_eq!(ethernet.length(), Some(7));
+        let Ok(crate::Ethernet::Llc(llc)) = ethernet.inner() else {
+            panic!("expected LLC");
+        };
+        assert_eq!(llc.dsap(), LlcSap::SPANNING_TREE);
+        assert_eq!(llc.ssap(), LlcSap::SPANNING_TREE);
+        assert_eq!(llc.control(), LlcControl::UI);
+        assert_eq!(llc.computed_header_length(), 3);
+        assert!(llc.snap().is_none());
+        // The Ethernet padding is not part of the LLC payload
+        let Ok(Llc::Raw(data)) = llc.inner() else {
+            panic!("expected raw LLC data");
+        };
+        assert_eq!(data, [0x00, 0x00, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn snap_round_trip() {
+        let mut llc_pdu = LlcPdu::new();
+        llc_pdu.snap([0x00, 0x00, 0x0C], 0x2000);
+        llc_pdu.inner(b"cdp").unwrap();
+        assert_eq!(
+            llc_pdu.as_bytes()[..8],
+            [0xAA, 0xAA, 0x03, 0x00, 0x00, 0x0C, 0x20, 0x00]
+        );
+        let llc = LlcParser::parse(llc_pdu.as_bytes()).unwrap();
+        assert_eq!(llc.computed_header_length(), 8);
+        assert_eq!(llc.snap(), Some(([0x00, 0x00, 0x0C], 0x2000)));
+        assert!(matches!(
+            llc.inner(),
+            Ok(Llc::Snap {
+                oui: [0x00, 0x00, 0x0C],
+                protocol_id: 0x2000,
+                data: b"cdp",
+            })
+        ));
+        // A response still carries a SNAP header
+        llc_pdu.ssap(LlcSap::SNAP | 0x01);
+        let llc = LlcParser::parse(llc_pdu.as_bytes()).unwrap();
+        assert!(llc.snap().is_some());
+    }
+
+    #[test]
+    fn two_octet_control() {
+        let mut llc_pdu = LlcPdu::new();
+        llc_pdu.dsap(LlcSap::NETBIOS);
+        llc_pdu.ssap(LlcSap::NETBIOS);
+        // An I-format PDU with N(S) 5 and N(R) 3
+        llc_pdu.control(0x060A);
+        llc_pdu.inner(&[0x01]).unwrap();
+        assert_eq!(llc_pdu.as_bytes(), [0xF0, 0xF0, 0x0A, 0x06, 0x01]);
+        let llc = LlcParser::parse(llc_pdu.as_bytes()).unwrap();
+        assert_eq!(llc.control(), 0x060A);
+        assert_eq!(llc.computed_header_length(), 4);
+        assert!(matches!(llc.inner(), Ok(Llc::Raw(&[0x01]))));
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(matches!(
+            LlcParser::parse(&[0x42, 0x42]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            LlcParser::parse(&[0xF0, 0xF0, 0x0A]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            LlcParser::parse(&[0xAA, 0xAA, 0x03, 0x00, 0x00, 0x0C, 0x20]),
+            Err(Error::Truncated)
+        ));
+        // An 802.3 length beyond the end of the frame
+        let mut ethernet_pdu = crate::EthernetPdu::new(0);
+        ethernet_pdu.inner(&[0x42; 60]).unwrap();
+        let bytes = ethernet_pdu.as_bytes();
+        assert!(matches!(
+            crate::EthernetParser::parse(&bytes[..60]),
+            Err(Error::Truncated)
+        ));
+    }
+
+    #[test]
+    fn oversized() {
+        let mut llc_pdu = LlcPdu::new();
+        assert!(matches!(llc_pdu.inner(&[0; 1498]), Err(Error::Oversized)));
+        llc_pdu.inner(&[0; 1497]).unwrap();
+        assert_eq!(llc_pdu.as_bytes().len(), 1500);
+        // 1501 is neither a length nor an EtherType
+        let mut bytes = [0u8; 64];
+        bytes[12..=13].copy_from_slice(&1501u16.to_be_bytes());
+        assert!(matches!(
+            crate::EthernetParser::parse(&bytes),
+            Err(Error::Malformed)
+        ));
+    }
+}
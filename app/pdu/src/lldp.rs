@@ -0,0 +1,598 @@
+use core::convert::TryInto;
+
+use crate::{Error, Result};
+
+#[allow(non_snake_case)]
+pub mod LldpAddress {
+    pub const NEAREST_BRIDGE: [u8; 6] = [0x01, 0x80, 0xC2, 0x00, 0x00, 0x0E];
+    pub const NEAREST_NON_TPMR_BRIDGE: [u8; 6] = [0x01, 0x80, 0xC2, 0x00, 0x00, 0x03];
+    pub const NEAREST_CUSTOMER_BRIDGE: [u8; 6] = [0x01, 0x80, 0xC2, 0x00, 0x00, 0x00];
+}
+
+#[allow(non_snake_case)]
+pub mod LldpTlvType {
+    pub const END: u8 = 0;
+    pub const CHASSIS_ID: u8 = 1;
+    pub const PORT_ID: u8 = 2;
+    pub const TTL: u8 = 3;
+    pub const PORT_DESCRIPTION: u8 = 4;
+    pub const SYSTEM_NAME: u8 = 5;
+    pub const SYSTEM_DESCRIPTION: u8 = 6;
+    pub const SYSTEM_CAPABILITIES: u8 = 7;
+    pub const MANAGEMENT_ADDRESS: u8 = 8;
+    pub const ORGANIZATIONALLY_SPECIFIC: u8 = 127;
+}
+
+#[allow(non_snake_case)]
+pub mod LldpChassisIdSubtype {
+    pub const CHASSIS_COMPONENT: u8 = 1;
+    pub const INTERFACE_ALIAS: u8 = 2;
+    pub const PORT_COMPONENT: u8 = 3;
+    pub const MAC_ADDRESS: u8 = 4;
+    pub const NETWORK_ADDRESS: u8 = 5;
+    pub const INTERFACE_NAME: u8 = 6;
+    pub const LOCALLY_ASSIGNED: u8 = 7;
+}
+
+#[allow(non_snake_case)]
+pub mod LldpPortIdSubtype {
+    pub const INTERFACE_ALIAS: u8 = 1;
+    pub const PORT_COMPONENT: u8 = 2;
+    pub const MAC_ADDRESS: u8 = 3;
+    pub const NETWORK_ADDRESS: u8 = 4;
+    pub const INTERFACE_NAME: u8 = 5;
+    pub const AGENT_CIRCUIT_ID: u8 = 6;
+    pub const LOCALLY_ASSIGNED: u8 = 7;
+}
+
+#[allow(non_snake_case)]
+pub mod LldpCapability {
+    pub const OTHER: u16 = 0x0001;
+    pub const REPEATER: u16 = 0x0002;
+    pub const BRIDGE: u16 = 0x0004;
+    pub const WLAN_ACCESS_POINT: u16 = 0x0008;
+    pub const ROUTER: u16 = 0x0010;
+    pub const TELEPHONE: u16 = 0x0020;
+    pub const DOCSIS_CABLE_DEVICE: u16 = 0x0040;
+    pub const STATION_ONLY: u16 = 0x0080;
+}
+
+#[derive(Copy, Clone)]
+pub enum LldpTlv<'a> {
+    Raw {
+        tlv_type: u8,
+        data: &'a [u8],
+    },
+    End,
+    ChassisId {
+        subtype: u8,
+        id: &'a [u8],
+    },
+    PortId {
+        subtype: u8,
+        id: &'a [u8],
+    },
+    Ttl {
+        seconds: u16,
+    },
+    PortDescription(&'a str),
+    SystemName(&'a str),
+    SystemDescription(&'a str),
+    SystemCapabilities {
+        capabilities: u16,
+        enabled: u16,
+    },
+    ManagementAddress {
+        address_subtype: u8,
+        address: &'a [u8],
+        interface_subtype: u8,
+        interface_number: u32,
+        oid: &'a [u8],
+    },
+    OrganizationallySpecific {
+        oui: [u8; 3],
+        subtype: u8,
+        data: &'a [u8],
+    },
+}
+
+pub struct LldpPdu {
+    buffer: [u8; 1500],
+    inner_size: usize,
+}
+
+impl LldpPdu {
+    pub fn new() -> Self {
+        LldpPdu {
+            buffer: [0u8; 1500],
+            inner_size: 0,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..self.inner_size]
+    }
+
+    pub fn tlv(&mut self, value: &LldpTlv) -> Result<()> {
+        let (tlv_type, len) = match value {
+            LldpTlv::Raw { tlv_type, data } => (*tlv_type, data.len()),
+            LldpTlv::End => (LldpTlvType::END, 0),
+            LldpTlv::ChassisId { id, .. } => (LldpTlvType::CHASSIS_ID, 1 + id.len()),
+            LldpTlv::PortId { id, .. } => (LldpTlvType::PORT_ID, 1 + id.len()),
+            LldpTlv::Ttl { .. } => (LldpTlvType::TTL, 2),
+            LldpTlv::PortDescription(value) => (LldpTlvType::PORT_DESCRIPTION, value.len()),
+            LldpTlv::SystemName(value) => (LldpTlvType::SYSTEM_NAME, value.len()),
+            LldpTlv::SystemDescription(value) => (LldpTlvType::SYSTEM_DESCRIPTION, value.len()),
+            LldpTlv::SystemCapabilities { .. } => (LldpTlvType::SYSTEM_CAPABILITIES, 4),
+            LldpTlv::ManagementAddress { address, oid, .. } => {
+                if address.is_empty() || address.len() > 31 || oid.len() > 128 {
+                    return Err(Error::Malformed);
+                }
+                (
+                    LldpTlvType::MANAGEMENT_ADDRESS,
+                    8 + address.len() + oid.len(),
+                )
+            }
+            LldpTlv::OrganizationallySpecific { data, .. } => {
+                (LldpTlvType::ORGANIZATIONALLY_SPECIFIC, 4 + data.len())
+            }
+        };
+        if tlv_type > 127 {
+            return Err(Error::Malformed);
+        }
+        if len > 511 {
+            return Err(Error::Oversized);
+        }
+        let pos = self.inner_size;
+        if pos + 2 + len > 1500 {
+            return Err(Error::Oversized);
+        }
+        let header = (tlv_type as u16) << 9 | len as u16;
+        self.buffer[pos..pos + 2].copy_from_slice(&header.to_be_bytes());
+        let tlv = &mut self.buffer[pos + 2..pos + 2 + len];
+        match value {
+            LldpTlv::Raw { data, .. } => tlv.copy_from_slice(data),
+            LldpTlv::End => {}
+            LldpTlv::ChassisId { subtype, id } | LldpTlv::PortId { subtype, id } => {
+                tlv[0] = *subtype;
+                tlv[1..].copy_from_slice(id);
+            }
+            LldpTlv::Ttl { seconds } => tlv.copy_from_slice(&seconds.to_be_bytes()),
+            LldpTlv::PortDescription(value)
+            | LldpTlv::SystemName(value)
+            | LldpTlv::SystemDescription(value) => tlv.copy_from_slice(value.as_bytes()),
+            LldpTlv::SystemCapabilities {
+                capabilities,
+                enabled,
+            } => {
+                tlv[0..=1].copy_from_slice(&capabilities.to_be_bytes());
+                tlv[2..=3].copy_from_slice(&enabled.to_be_bytes());
+            }
+            LldpTlv::ManagementAddress {
+                address_subtype,
+                address,
+                interface_subtype,
+                interface_number,
+                oid,
+            } => {
+                let address_len = address.len();
+                tlv[0] = 1 + address_len as u8;
+                tlv[1] = *address_subtype;
+                tlv[2..2 + address_len].copy_from_slice(address);
+                let tlv = &mut tlv[2 + address_len..];
+                tlv[0] = *interface_subtype;
+                tlv[1..=4].copy_from_slice(&interface_number.to_be_bytes());
+                tlv[5] = oid.len() as u8;
+                tlv[6..].copy_from_slice(oid);
+            }
+            LldpTlv::OrganizationallySpecific { oui, subtype, data } => {
+                tlv[0..=2].copy_from_slice(oui);
+                tlv[3] = *subtype;
+                tlv[4..].copy_from_slice(data);
+            }
+        }
+        self.inner_size += 2 + len;
+        Ok(())
+    }
+}
+
+impl Default for LldpPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct LldpParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> LldpParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        let pdu = LldpParser { buffer };
+        let mut tlvs = pdu.tlvs();
+        // Chassis ID, Port ID and TTL must come first, in this order (IEEE 802.1AB, 8.2)
+        for expected in &[
+            LldpTlvType::CHASSIS_ID,
+            LldpTlvType::PORT_ID,
+            LldpTlvType::TTL,
+        ] {
+            let (tlv_type, _) = tlvs.read()?.ok_or(Error::Truncated)?;
+            if tlv_type != *expected {
+                return Err(Error::Malformed);
+            }
+        }
+        while tlvs.read()?.is_some() {}
+        Ok(pdu)
+    }
+
+    pub fn tlvs(&self) -> LldpTlvIterator<'a> {
+        LldpTlvIterator {
+            buffer: self.buffer,
+            pos: 0,
+        }
+    }
+
+    pub fn chassis_id(&'a self) -> (u8, &'a [u8]) {
+        let tlv = &self.buffer[2..2 + tlv_length(self.buffer)];
+        (tlv[0], &tlv[1..])
+    }
+
+    pub fn port_id(&'a self) -> (u8, &'a [u8]) {
+        let pos = 2 + tlv_length(self.buffer);
+        let tlv = &self.buffer[pos + 2..pos + 2 + tlv_length(&self.buffer[pos..])];
+        (tlv[0], &tlv[1..])
+    }
+
+    pub fn ttl(&'a self) -> u16 {
+        let pos = 2 + tlv_length(self.buffer);
+        let pos = pos + 2 + tlv_length(&self.buffer[pos..]);
+        u16::from_be_bytes(self.buffer[pos + 2..pos + 4].try_into().unwrap())
+    }
+}
+
+fn tlv_length(buffer: &[u8]) -> usize {
+    (u16::from_be_bytes(buffer[0..=1].try_into().unwrap()) & 0x01FF) as usize
+}
+
+#[derive(Copy, Clone)]
+pub struct LldpTlvIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> LldpTlvIterator<'a> {
+    fn read(&mut self) -> Result<Option<(u8, &'a [u8])>> {
+        let buffer = self.buffer;
+        let pos = self.pos;
+        if pos == buffer.len() {
+            // Tolerate a missing End of LLDPDU TLV
+            return Ok(None);
+        }
+        if buffer.len() < pos + 2 {
+            return Err(Error::Truncated);
+        }
+        let tlv_type = buffer[pos] >> 1;
+        let len = tlv_length(&buffer[pos..]);
+        if buffer.len() < pos + 2 + len {
+            return Err(Error::Truncated);
+        }
+        let min_len = match tlv_type {
+            LldpTlvType::END => {
+                if len != 0 {
+                    return Err(Error::Malformed);
+                }
+                // Anything after End of LLDPDU is Ethernet padding
+                self.pos = buffer.len();
+                return Ok(None);
+            }
+            LldpTlvType::CHASSIS_ID | LldpTlvType::PORT_ID => 2,
+            LldpTlvType::TTL => 2,
+            LldpTlvType::SYSTEM_CAPABILITIES => 4,
+            LldpTlvType::MANAGEMENT_ADDRESS => 9,
+            LldpTlvType::ORGANIZATIONALLY_SPECIFIC => 4,
+            _ => 0,
+        };
+        if len < min_len {
+            return Err(Error::Malformed);
+        }
+        self.pos = pos + 2 + len;
+        Ok(Some((tlv_type, &buffer[pos + 2..pos + 2 + len])))
+    }
+}
+
+impl<'a> Iterator for LldpTlvIterator<'a> {
+    type Item = LldpTlv<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (tlv_type, data) = self.read().ok()??;
+        let raw = LldpTlv::Raw { tlv_type, data };
+        Some(match tlv_type {
+            LldpTlvType::CHASSIS_ID => LldpTlv::ChassisId {
+                subtype: data[0],
+                id: &data[1..],
+            },
+            LldpTlvType::PORT_ID => LldpTlv::PortId {
+                subtype: data[0],
+                id: &data[1..],
+            },
+            LldpTlvType::TTL => LldpTlv::Ttl {
+                seconds: u16::from_be_bytes(data[0..=1].try_into().unwrap()),
+            },
+            LldpTlvType::PORT_DESCRIPTION
+            | LldpTlvType::SYSTEM_NAME
+            | LldpTlvType::SYSTEM_DESCRIPTION => match core::str::from_utf8(data) {
+                Ok(value) if tlv_type == LldpTlvType::PORT_DESCRIPTION => {
+                    LldpTlv::PortDescription(value)
+                }
+                Ok(value) if tlv_type == LldpTlvType::SYSTEM_NAME => LldpTlv::SystemName(value),
+                Ok(value) => LldpTlv::SystemDescription(value),
+                Err(_) => raw,
+            },
+            LldpTlvType::SYSTEM_CAPABILITIES => LldpTlv::SystemCapabilities {
+                capabilities: u16::from_be_bytes(data[0..=1].try_into().unwrap()),
+                enabled: u16::from_be_bytes(data[2..=3].try_into().unwrap()),
+            },
+            LldpTlvType::MANAGEMENT_ADDRESS => {
+                let address_len = data[0] as usize;
+                if address_len < 2 || data.len() < 7 + address_len {
+                    return Some(raw);
+                }
+                let rest = &data[1 + address_len..];
+                let oid_len = rest[5] as usize;
+                if rest.len() != 6 + oid_len {
+                    return Some(raw);
+                }
+                LldpTlv::ManagementAddress {
+                    address_subtype: data[1],
+                    address: &data[2..1 + address_len],
+                    interface_subtype: rest[0],
+                    interface_number: u32::from_be_bytes(rest[1..=4].try_into().unwrap()),
+                    oid: &rest[6..],
+                }
+            }
+            LldpTlvType::ORGANIZATIONALLY_SPECIFIC => LldpTlv::OrganizationallySpecific {
+                oui: data[0..=2].try_into().unwrap(),
+                subtype: data[3],
+                data: &data[4..],
+            },
+            _ => raw,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mandatory_tlvs(lldp_pdu: &mut LldpPdu) {
+        lldp_pdu
+            .tlv(&LldpTlv::ChassisId {
+                subtype: LldpChassisIdSubtype::MAC_ADDRESS,
+                id: &[0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+            })
+            .unwrap();
+        lldp_pdu
+            .tlv(&LldpTlv::PortId {
+                subtype: LldpPortIdSubtype::INTERFACE_NAME,
+                id: b"eth0",
+            })
+            .unwrap();
+        lldp_pdu.tlv(&LldpTlv::Ttl { seconds: 120 }).unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut lldp_pdu = LldpPdu::new();
+        mandatory_tlvs(&mut lldp_pdu);
+        lldp_pdu.tlv(&LldpTlv::SystemName("nucleo")).unwrap();
+        lldp_pdu
+            .tlv(&LldpTlv::SystemCapabilities {
+                capabilities: LldpCapability::STATION_ONLY | LldpCapability::ROUTER,
+                enabled: LldpCapability::STATION_ONLY,
+            })
+            .unwrap();
+        lldp_pdu
+            .tlv(&LldpTlv::ManagementAddress {
+                address_subtype: 1,
+                address: &[192, 168, 0, 10],
+                interface_subtype: 2,
+                interface_number: 1,
+                oid: &[],
+            })
+            .unwrap();
+        lldp_pdu
+            .tlv(&LldpTlv::OrganizationallySpecific {
+                oui: [0x00, 0x12, 0x0F],
+                subtype: 1,
+                data: &[0x03, 0x6C, 0x03, 0x00, 0x10],
+            })
+            .unwrap();
+        lldp_pdu.tlv(&LldpTlv::End).unwrap();
+        assert_eq!(lldp_pdu.as_bytes()[..2], [0x02, 0x07]);
+
+        let mut ethernet_pdu = crate::EthernetPdu::new(crate::EtherType::LLDP);
+        ethernet_pdu.destination_address(LldpAddress::NEAREST_BRIDGE);
+        ethernet_pdu.inner(lldp_pdu.as_bytes()).unwrap();
+        let ethernet = crate::EthernetParser::parse(ethernet_pdu.as_bytes()).unwrap();
+        // The Ethernet padding and FCS after End of LLDPDU are ignored
+        let Ok(crate::Ethernet::Lldp(lldp)) = ethernet.inner() else {
+            panic!("expected LLDP");
+        };
+        assert_eq!(
+            lldp.chassis_id(),
+            (
+                LldpChassisIdSubtype::MAC_ADDRESS,
+                &[0x02, 0x00, 0x00, 0x00, 0x00, 0x01][..]
+            )
+        );
+        assert_eq!(
+            lldp.port_id(),
+            (LldpPortIdSubtype::INTERFACE_NAME, &b"eth0"[..])
+        );
+        assert_eq!(lldp.ttl(), 120);
+        let mut tlvs = lldp.tlvs();
+        assert!(matches!(tlvs.next(), Some(LldpTlv::ChassisId { .. })));
+        assert!(matches!(
+            tlvs.next(),
+            Some(LldpTlv::PortId {
+                subtype: LldpPortIdSubtype::INTERFACE_NAME,
+                id: b"eth0",
+            })
+        ));
+        assert!(matches!(tlvs.next(), Some(LldpTlv::Ttl { seconds: 120 })));
+        assert!(matches!(tlvs.next(), Some(LldpTlv::SystemName("nucleo"))));
+        assert!(matches!(
+            tlvs.next(),
+            Some(LldpTlv::SystemCapabilities {
+                capabilities: 0x0090,
+                enabled: LldpCapability::STATION_ONLY,
+            })
+        ));
+        assert!(matches!(
+            tlvs.next(),
+            Some(LldpTlv::ManagementAddress {
+                address_subtype: 1,
+                address: &[192, 168, 0, 10],
+                interface_subtype: 2,
+                interface_number: 1,
+                oid: &[],
+            })
+        ));
+        assert!(matches!(
+            tlvs.next(),
+            Some(LldpTlv::OrganizationallySpecific {
+                oui: [0x00, 0x12, 0x0F],
+                subtype: 1,
+                data: &[0x03, 0x6C, 0x03, 0x00, 0x10],
+            })
+        ));
+        assert!(tlvs.next().is_none());
+    }
+
+    #[test]
+    fn missing_end_is_tolerated() {
+        let mut lldp_pdu = LldpPdu::new();
+        mandatory_tlvs(&mut lldp_pdu);
+        let lldp = LldpParser::parse(lldp_pdu.as_bytes()).unwrap();
+        assert_eq!(lldp.tlvs().count(), 3);
+    }
+
+    #[test]
+    fn truncated_and_malformed() {
+        let mut lldp_pdu = LldpPdu::new();
+        mandatory_tlvs(&mut lldp_pdu);
+        let bytes = lldp_pdu.as_bytes();
+        assert!(matches!(LldpParser::parse(&[]), Err(Error::Truncated)));
+        assert!(matches!(
+            LldpParser::parse(&bytes[..9]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            LldpParser::parse(&bytes[..16]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            LldpParser::parse(&bytes[..bytes.len() - 1]),
+            Err(Error::Truncated)
+        ));
+        // Port ID before Chassis ID
+        assert!(matches!(
+            LldpParser::parse(&bytes[9..]),
+            Err(Error::Malformed)
+        ));
+        // A TTL of a single octet
+        let mut lldp_pdu = LldpPdu::new();
+        mandatory_tlvs(&mut lldp_pdu);
+        lldp_pdu
+            .tlv(&LldpTlv::Raw {
+                tlv_type: LldpTlvType::TTL,
+                data: &[0x00],
+            })
+            .unwrap();
+        assert!(matches!(
+            LldpParser::parse(lldp_pdu.as_bytes()),
+            Err(Error::Malformed)
+        ));
+        // End of LLDPDU with a non-zero length
+        let mut lldp_pdu = LldpPdu::new();
+        mandatory_tlvs(&mut lldp_pdu);
+        lldp_pdu
+            .tlv(&LldpTlv::Raw {
+                tlv_type: LldpTlvType::END,
+                data: &[0x00],
+            })
+            .unwrap();
+        assert!(matches!(
+            LldpParser::parse(lldp_pdu.as_bytes()),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn inconsistent_management_address_is_raw() {
+        let mut lldp_pdu = LldpPdu::new();
+        mandatory_tlvs(&mut lldp_pdu);
+        // The OID string length points past the end of the TLV
+        lldp_pdu
+            .tlv(&LldpTlv::Raw {
+                tlv_type: LldpTlvType::MANAGEMENT_ADDRESS,
+                data: &[5, 1, 192, 168, 0, 10, 2, 0, 0, 0, 1, 4],
+            })
+            .unwrap();
+        let lldp = LldpParser::parse(lldp_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            lldp.tlvs().nth(3),
+            Some(LldpTlv::Raw {
+                tlv_type: LldpTlvType::MANAGEMENT_ADDRESS,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn builder_rejects_bad_tlvs() {
+        let mut lldp_pdu = LldpPdu::new();
+        assert!(matches!(
+            lldp_pdu.tlv(&LldpTlv::Raw {
+                tlv_type: 128,
+                data: &[],
+            }),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            lldp_pdu.tlv(&LldpTlv::ManagementAddress {
+                address_subtype: 1,
+                address: &[],
+                interface_subtype: 2,
+                interface_number: 1,
+                oid: &[],
+            }),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            lldp_pdu.tlv(&LldpTlv::Raw {
+                tlv_type: 9,
+                data: &[0; 512],
+            }),
+            Err(Error::Oversized)
+        ));
+        // Two 511-octet TLVs fit, a third does not
+        for _ in 0..2 {
+            lldp_pdu
+                .tlv(&LldpTlv::Raw {
+                    tlv_type: 9,
+                    data: &[0; 511],
+                })
+                .unwrap();
+        }
+        assert!(matches!(
+            lldp_pdu.tlv(&LldpTlv::Raw {
+                tlv_type: 9,
+                data: &[0; 511],
+            }),
+            Err(Error::Oversized)
+        ));
+        assert_eq!(lldp_pdu.as_bytes().len(), 1026);
+    }
+}
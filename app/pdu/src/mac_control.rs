@@ -0,0 +1,206 @@
+use core::convert::TryInto;
+
+use crate::{Error, Result};
+
+#[allow(non_snake_case)]
+pub mod MacControlAddress {
+    pub const PAUSE: [u8; 6] = [0x01, 0x80, 0xC2, 0x00, 0x00, 0x01];
+}
+
+#[allow(non_snake_case)]
+pub mod MacControlOpcode {
+    pub const PAUSE: u16 = 0x0001;
+    pub const GATE: u16 = 0x0002;
+    pub const REPORT: u16 = 0x0003;
+    pub const REGISTER_REQ: u16 = 0x0004;
+    pub const REGISTER: u16 = 0x0005;
+    pub const REGISTER_ACK: u16 = 0x0006;
+    pub const PFC: u16 = 0x0101;
+}
+
+#[derive(Copy, Clone)]
+pub enum MacControl<'a> {
+    Raw { opcode: u16, data: &'a [u8] },
+    Pause { quanta: u16 },
+    Pfc { quanta: [Option<u16>; 8] },
+}
+
+pub struct MacControlPdu {
+    buffer: [u8; 46],
+}
+
+impl MacControlPdu {
+    pub fn new() -> Self {
+        MacControlPdu { buffer: [0u8; 46] }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        // MAC Control frames are always minimum-size, padded with reserved zeros
+        &self.buffer
+    }
+
+    pub fn opcode(&mut self, value: u16) {
+        self.buffer[0..=1].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn pause(&mut self, quanta: u16) {
+        self.buffer = [0u8; 46];
+        self.opcode(MacControlOpcode::PAUSE);
+        self.buffer[2..=3].copy_from_slice(&quanta.to_be_bytes());
+    }
+
+    pub fn pfc(&mut self, quanta: &[Option<u16>; 8]) {
+        self.buffer = [0u8; 46];
+        self.opcode(MacControlOpcode::PFC);
+        let mut class_enable_vector = 0u16;
+        for (priority, quanta) in quanta.iter().enumerate() {
+            if let Some(quanta) = quanta {
+                class_enable_vector |= 1 << priority;
+                let pos = 4 + priority * 2;
+                self.buffer[pos..pos + 2].copy_from_slice(&quanta.to_be_bytes());
+            }
+        }
+        self.buffer[2..=3].copy_from_slice(&class_enable_vector.to_be_bytes());
+    }
+}
+
+impl Default for MacControlPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct MacControlParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> MacControlParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 2 {
+            return Err(Error::Truncated);
+        }
+        Ok(MacControlParser { buffer })
+    }
+
+    pub fn inner(&'a self) -> Result<MacControl<'a>> {
+        (*self).into_inner()
+    }
+
+    pub fn into_inner(self) -> Result<MacControl<'a>> {
+        let buffer = self.buffer;
+        Ok(match self.opcode() {
+            MacControlOpcode::PAUSE => {
+                if buffer.len() < 4 {
+                    return Err(Error::Truncated);
+                }
+                MacControl::Pause {
+                    quanta: u16::from_be_bytes(buffer[2..=3].try_into().unwrap()),
+                }
+            }
+            MacControlOpcode::PFC => {
+                if buffer.len() < 20 {
+                    return Err(Error::Truncated);
+                }
+                let class_enable_vector = u16::from_be_bytes(buffer[2..=3].try_into().unwrap());
+                let mut quanta = [None; 8];
+                for (priority, quanta) in quanta.iter_mut().enumerate() {
+                    if class_enable_vector & (1 << priority) != 0 {
+                        let pos = 4 + priority * 2;
+                        *quanta =
+                            Some(u16::from_be_bytes(buffer[pos..pos + 2].try_into().unwrap()));
+                    }
+                }
+                MacControl::Pfc { quanta }
+            }
+            opcode => MacControl::Raw {
+                opcode,
+                data: &buffer[2..],
+            },
+        })
+    }
+
+    pub fn opcode(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[0..=1].try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_round_trip() {
+        let mut mac_control_pdu = MacControlPdu::new();
+        mac_control_pdu.pause(0xFFFF);
+        assert_eq!(mac_control_pdu.as_bytes().len(), 46);
+        assert_eq!(mac_control_pdu.as_bytes()[..4], [0x00, 0x01, 0xFF, 0xFF]);
+        let mut ethernet_pdu = crate::EthernetPdu::new(crate::EtherType::MAC_CONTROL);
+        ethernet_pdu.destination_address(MacControlAddress::PAUSE);
+        ethernet_pdu.inner(mac_control_pdu.as_bytes()).unwrap();
+        let bytes = ethernet_pdu.as_bytes();
+        assert_eq!(bytes.len(), 64);
+        let ethernet = crate::EthernetParser::parse(bytes).unwrap();
+        let Ok(crate::Ethernet::MacControl(mac_control)) = ethernet.inner() else {
+            panic!("expected MAC Control");
+        };
+        assert_eq!(mac_control.opcode(), MacControlOpcode::PAUSE);
+        assert!(matches!(
+            mac_control.inner(),
+            Ok(MacControl::Pause { quanta: 0xFFFF })
+        ));
+    }
+
+    #[test]
+    fn pfc_round_trip() {
+        let quanta = [Some(1), None, None, Some(0x1234), None, None, None, Some(0)];
+        let mut mac_control_pdu = MacControlPdu::new();
+        // The previous frame's fields are cleared
+        mac_control_pdu.pause(0xFFFF);
+        mac_control_pdu.pfc(&quanta);
+        let bytes = mac_control_pdu.as_bytes();
+        assert_eq!(bytes[..6], [0x01, 0x01, 0x00, 0x89, 0x00, 0x01]);
+        assert_eq!(bytes[6..8], [0x00, 0x00]);
+        let mac_control = MacControlParser::parse(bytes).unwrap();
+        let Ok(MacControl::Pfc { quanta: parsed }) = mac_control.inner() else {
+            panic!("expected PFC");
+        };
+        assert_eq!(parsed, quanta);
+    }
+
+    #[test]
+    fn unknown_opcode_is_raw() {
+        let mut mac_control_pdu = MacControlPdu::new();
+        mac_control_pdu.opcode(MacControlOpcode::GATE);
+        let mac_control = MacControlParser::parse(mac_control_pdu.as_bytes()).unwrap();
+        let Ok(MacControl::Raw {
+            opcode: MacControlOpcode::GATE,
+            data,
+        }) = mac_control.inner()
+        else {
+            panic!("expected raw MAC Control");
+        };
+        assert_eq!(data.len(), 44);
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(matches!(
+            MacControlParser::parse(&[0x00]),
+            Err(Error::Truncated)
+        ));
+        let mac_control = MacControlParser::parse(&[0x00, 0x01, 0xFF]).unwrap();
+        assert!(matches!(mac_control.inner(), Err(Error::Truncated)));
+        let mut mac_control_pdu = MacControlPdu::new();
+        mac_control_pdu.pfc(&[Some(1); 8]);
+        let mac_control = MacControlParser::parse(&mac_control_pdu.as_bytes()[..19]).unwrap();
+        assert!(matches!(mac_control.inner(), Err(Error::Truncated)));
+        let mac_control = MacControlParser::parse(&mac_control_pdu.as_bytes()[..20]).unwrap();
+        assert!(matches!(
+            mac_control.inner(),
+            Ok(MacControl::Pfc {
+                quanta: [Some(1), ..]
+            })
+        ));
+    }
+}
@@ -0,0 +1,705 @@
+use core::convert::TryInto;
+
+use crate::{Error, Result};
+
+#[allow(non_snake_case)]
+pub mod ModbusFunction {
+    pub const READ_COILS: u8 = 0x01;
+    pub const READ_DISCRETE_INPUTS: u8 = 0x02;
+    pub const READ_HOLDING_REGISTERS: u8 = 0x03;
+    pub const READ_INPUT_REGISTERS: u8 = 0x04;
+    pub const WRITE_SINGLE_COIL: u8 = 0x05;
+    pub const WRITE_SINGLE_REGISTER: u8 = 0x06;
+    pub const WRITE_MULTIPLE_COILS: u8 = 0x0F;
+    pub const WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+}
+
+#[allow(non_snake_case)]
+pub mod ModbusException {
+    pub const ILLEGAL_FUNCTION: u8 = 0x01;
+    pub const ILLEGAL_DATA_ADDRESS: u8 = 0x02;
+    pub const ILLEGAL_DATA_VALUE: u8 = 0x03;
+    pub const SERVER_DEVICE_FAILURE: u8 = 0x04;
+    pub const ACKNOWLEDGE: u8 = 0x05;
+    pub const SERVER_DEVICE_BUSY: u8 = 0x06;
+    pub const MEMORY_PARITY_ERROR: u8 = 0x08;
+    pub const GATEWAY_PATH_UNAVAILABLE: u8 = 0x0A;
+    pub const GATEWAY_TARGET_FAILED_TO_RESPOND: u8 = 0x0B;
+}
+
+#[derive(Copy, Clone)]
+pub enum ModbusRequest<'a> {
+    Raw {
+        function: u8,
+        data: &'a [u8],
+    },
+    ReadCoils {
+        address: u16,
+        quantity: u16,
+    },
+    ReadDiscreteInputs {
+        address: u16,
+        quantity: u16,
+    },
+    ReadHoldingRegisters {
+        address: u16,
+        quantity: u16,
+    },
+    ReadInputRegisters {
+        address: u16,
+        quantity: u16,
+    },
+    WriteSingleCoil {
+        address: u16,
+        value: bool,
+    },
+    WriteSingleRegister {
+        address: u16,
+        value: u16,
+    },
+    WriteMultipleCoils {
+        address: u16,
+        values: ModbusBitIterator<'a>,
+    },
+    WriteMultipleRegisters {
+        address: u16,
+        values: ModbusRegisterIterator<'a>,
+    },
+}
+
+#[derive(Copy, Clone)]
+pub enum ModbusResponse<'a> {
+    Raw { function: u8, data: &'a [u8] },
+    ReadCoils { values: ModbusBitIterator<'a> },
+    ReadDiscreteInputs { values: ModbusBitIterator<'a> },
+    ReadHoldingRegisters { values: ModbusRegisterIterator<'a> },
+    ReadInputRegisters { values: ModbusRegisterIterator<'a> },
+    WriteSingleCoil { address: u16, value: bool },
+    WriteSingleRegister { address: u16, value: u16 },
+    WriteMultipleCoils { address: u16, quantity: u16 },
+    WriteMultipleRegisters { address: u16, quantity: u16 },
+    Exception { function: u8, code: u8 },
+}
+
+pub struct ModbusPdu {
+    buffer: [u8; 260],
+    inner_size: usize,
+}
+
+impl ModbusPdu {
+    pub fn new() -> Self {
+        ModbusPdu {
+            buffer: [0u8; 260],
+            inner_size: 0,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..7 + self.inner_size]
+    }
+
+    pub fn transaction_id(&mut self, value: u16) {
+        self.buffer[0..=1].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn protocol_id(&mut self, value: u16) {
+        self.buffer[2..=3].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn length(&mut self, value: u16) {
+        self.buffer[4..=5].copy_from_slice(&value.to_be_bytes());
+    }
+
+    fn compute_length(&mut self) {
+        self.length((1 + self.inner_size) as u16);
+    }
+
+    pub fn unit_id(&mut self, value: u8) {
+        self.buffer[6] = value;
+    }
+
+    pub fn read_coils(&mut self, address: u16, quantity: u16) -> Result<()> {
+        check_quantity(quantity as usize, 2000)?;
+        self.address_pair(ModbusFunction::READ_COILS, address, quantity);
+        Ok(())
+    }
+
+    pub fn read_discrete_inputs(&mut self, address: u16, quantity: u16) -> Result<()> {
+        check_quantity(quantity as usize, 2000)?;
+        self.address_pair(ModbusFunction::READ_DISCRETE_INPUTS, address, quantity);
+        Ok(())
+    }
+
+    pub fn read_holding_registers(&mut self, address: u16, quantity: u16) -> Result<()> {
+        check_quantity(quantity as usize, 125)?;
+        self.address_pair(ModbusFunction::READ_HOLDING_REGISTERS, address, quantity);
+        Ok(())
+    }
+
+    pub fn read_input_registers(&mut self, address: u16, quantity: u16) -> Result<()> {
+        check_quantity(quantity as usize, 125)?;
+        self.address_pair(ModbusFunction::READ_INPUT_REGISTERS, address, quantity);
+        Ok(())
+    }
+
+    pub fn write_single_coil(&mut self, address: u16, value: bool) {
+        let value = if value { 0xFF00 } else { 0x0000 };
+        self.address_pair(ModbusFunction::WRITE_SINGLE_COIL, address, value);
+    }
+
+    pub fn write_single_register(&mut self, address: u16, value: u16) {
+        self.address_pair(ModbusFunction::WRITE_SINGLE_REGISTER, address, value);
+    }
+
+    pub fn write_multiple_coils(&mut self, address: u16, values: &[bool]) -> Result<()> {
+        check_quantity(values.len(), 1968)?;
+        self.address_pair(
+            ModbusFunction::WRITE_MULTIPLE_COILS,
+            address,
+            values.len() as u16,
+        );
+        self.bits(values);
+        Ok(())
+    }
+
+    pub fn write_multiple_registers(&mut self, address: u16, values: &[u16]) -> Result<()> {
+        check_quantity(values.len(), 123)?;
+        self.address_pair(
+            ModbusFunction::WRITE_MULTIPLE_REGISTERS,
+            address,
+            values.len() as u16,
+        );
+        self.registers(values);
+        Ok(())
+    }
+
+    pub fn read_coils_response(&mut self, values: &[bool]) -> Result<()> {
+        check_quantity(values.len(), 2000)?;
+        self.function(ModbusFunction::READ_COILS);
+        self.bits(values);
+        Ok(())
+    }
+
+    pub fn read_discrete_inputs_response(&mut self, values: &[bool]) -> Result<()> {
+        check_quantity(values.len(), 2000)?;
+        self.function(ModbusFunction::READ_DISCRETE_INPUTS);
+        self.bits(values);
+        Ok(())
+    }
+
+    pub fn read_holding_registers_response(&mut self, values: &[u16]) -> Result<()> {
+        check_quantity(values.len(), 125)?;
+        self.function(ModbusFunction::READ_HOLDING_REGISTERS);
+        self.registers(values);
+        Ok(())
+    }
+
+    pub fn read_input_registers_response(&mut self, values: &[u16]) -> Result<()> {
+        check_quantity(values.len(), 125)?;
+        self.function(ModbusFunction::READ_INPUT_REGISTERS);
+        self.registers(values);
+        Ok(())
+    }
+
+    pub fn write_multiple_coils_response(&mut self, address: u16, quantity: u16) {
+        self.address_pair(ModbusFunction::WRITE_MULTIPLE_COILS, address, quantity);
+    }
+
+    pub fn write_multiple_registers_response(&mut self, address: u16, quantity: u16) {
+        self.address_pair(ModbusFunction::WRITE_MULTIPLE_REGISTERS, address, quantity);
+    }
+
+    pub fn exception(&mut self, function: u8, code: u8) {
+        self.function(function | 0x80);
+        self.buffer[8] = code;
+        self.inner_size = 2;
+        self.compute_length();
+    }
+
+    fn function(&mut self, value: u8) {
+        self.buffer[7] = value;
+        self.inner_size = 1;
+        self.compute_length();
+    }
+
+    fn address_pair(&mut self, function: u8, address: u16, value: u16) {
+        self.function(function);
+        self.buffer[8..=9].copy_from_slice(&address.to_be_bytes());
+        self.buffer[10..=11].copy_from_slice(&value.to_be_bytes());
+        self.inner_size = 5;
+        self.compute_length();
+    }
+
+    fn bits(&mut self, values: &[bool]) {
+        let pos = 7 + self.inner_size;
+        let byte_count = values.len().div_ceil(8);
+        self.buffer[pos] = byte_count as u8;
+        self.buffer[pos + 1..pos + 1 + byte_count].fill(0x00);
+        for (i, _) in values.iter().enumerate().filter(|(_, &value)| value) {
+            self.buffer[pos + 1 + i / 8] |= 1 << (i % 8);
+        }
+        self.inner_size += 1 + byte_count;
+        self.compute_length();
+    }
+
+    fn registers(&mut self, values: &[u16]) {
+        let pos = 7 + self.inner_size;
+        self.buffer[pos] = (values.len() * 2) as u8;
+        for (i, value) in values.iter().enumerate() {
+            self.buffer[pos + 1 + i * 2..pos + 3 + i * 2].copy_from_slice(&value.to_be_bytes());
+        }
+        self.inner_size += 1 + values.len() * 2;
+        self.compute_length();
+    }
+}
+
+impl Default for ModbusPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn check_quantity(quantity: usize, max: usize) -> Result<()> {
+    if quantity == 0 || quantity > max {
+        return Err(Error::Oversized);
+    }
+    Ok(())
+}
+
+#[derive(Copy, Clone)]
+pub struct ModbusParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> ModbusParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 8 {
+            return Err(Error::Truncated);
+        }
+        let pdu = ModbusParser { buffer };
+        if pdu.protocol_id() != 0 {
+            return Err(Error::Malformed);
+        }
+        if pdu.length() < 2 || pdu.length() > 254 {
+            return Err(Error::Malformed);
+        }
+        if buffer.len() < pdu.computed_length() {
+            return Err(Error::Truncated);
+        }
+        Ok(pdu)
+    }
+
+    pub fn request(&'a self) -> Result<ModbusRequest<'a>> {
+        (*self).into_request()
+    }
+
+    pub fn into_request(self) -> Result<ModbusRequest<'a>> {
+        let data = self.data();
+        let function = self.function();
+        Ok(match function {
+            ModbusFunction::READ_COILS
+            | ModbusFunction::READ_DISCRETE_INPUTS
+            | ModbusFunction::READ_HOLDING_REGISTERS
+            | ModbusFunction::READ_INPUT_REGISTERS
+            | ModbusFunction::WRITE_SINGLE_COIL
+            | ModbusFunction::WRITE_SINGLE_REGISTER => {
+                if data.len() != 4 {
+                    return Err(Error::Malformed);
+                }
+                let address = u16::from_be_bytes(data[0..=1].try_into().unwrap());
+                let value = u16::from_be_bytes(data[2..=3].try_into().unwrap());
+                match function {
+                    ModbusFunction::READ_COILS => {
+                        check_quantity(value as usize, 2000).map_err(|_| Error::Malformed)?;
+                        ModbusRequest::ReadCoils {
+                            address,
+                            quantity: value,
+                        }
+                    }
+                    ModbusFunction::READ_DISCRETE_INPUTS => {
+                        check_quantity(value as usize, 2000).map_err(|_| Error::Malformed)?;
+                        ModbusRequest::ReadDiscreteInputs {
+                            address,
+                            quantity: value,
+                        }
+                    }
+                    ModbusFunction::READ_HOLDING_REGISTERS => {
+                        check_quantity(value as usize, 125).map_err(|_| Error::Malformed)?;
+                        ModbusRequest::ReadHoldingRegisters {
+                            address,
+                            quantity: value,
+                        }
+                    }
+                    ModbusFunction::READ_INPUT_REGISTERS => {
+                        check_quantity(value as usize, 125).map_err(|_| Error::Malformed)?;
+                        ModbusRequest::ReadInputRegisters {
+                            address,
+                            quantity: value,
+                        }
+                    }
+                    ModbusFunction::WRITE_SINGLE_COIL => ModbusRequest::WriteSingleCoil {
+                        address,
+                        value: coil_value(value)?,
+                    },
+                    _ => ModbusRequest::WriteSingleRegister { address, value },
+                }
+            }
+            ModbusFunction::WRITE_MULTIPLE_COILS => {
+                if data.len() < 5 {
+                    return Err(Error::Malformed);
+                }
+                let quantity = u16::from_be_bytes(data[2..=3].try_into().unwrap());
+                check_quantity(quantity as usize, 1968).map_err(|_| Error::Malformed)?;
+                let byte_count = data[4] as usize;
+                if byte_count != (quantity as usize).div_ceil(8) || data.len() != 5 + byte_count {
+                    return Err(Error::Malformed);
+                }
+                ModbusRequest::WriteMultipleCoils {
+                    address: u16::from_be_bytes(data[0..=1].try_into().unwrap()),
+                    values: ModbusBitIterator {
+                        buffer: &data[5..],
+                        pos: 0,
+                        quantity: quantity as usize,
+                    },
+                }
+            }
+            ModbusFunction::WRITE_MULTIPLE_REGISTERS => {
+                if data.len() < 5 {
+                    return Err(Error::Malformed);
+                }
+                let quantity = u16::from_be_bytes(data[2..=3].try_into().unwrap());
+                check_quantity(quantity as usize, 123).map_err(|_| Error::Malformed)?;
+                let byte_count = data[4] as usize;
+                if byte_count != quantity as usize * 2 || data.len() != 5 + byte_count {
+                    return Err(Error::Malformed);
+                }
+                ModbusRequest::WriteMultipleRegisters {
+                    address: u16::from_be_bytes(data[0..=1].try_into().unwrap()),
+                    values: ModbusRegisterIterator { buffer: &data[5..] },
+                }
+            }
+            _ => ModbusRequest::Raw { function, data },
+        })
+    }
+
+    pub fn response(&'a self) -> Result<ModbusResponse<'a>> {
+        (*self).into_response()
+    }
+
+    pub fn into_response(self) -> Result<ModbusResponse<'a>> {
+        let data = self.data();
+        let function = self.function();
+        if function & 0x80 != 0 {
+            if data.len() != 1 {
+                return Err(Error::Malformed);
+            }
+            return Ok(ModbusResponse::Exception {
+                function: function & 0x7F,
+                code: data[0],
+            });
+        }
+        Ok(match function {
+            ModbusFunction::READ_COILS | ModbusFunction::READ_DISCRETE_INPUTS => {
+                if data.is_empty() || data.len() != 1 + data[0] as usize {
+                    return Err(Error::Malformed);
+                }
+                let values = ModbusBitIterator {
+                    buffer: &data[1..],
+                    pos: 0,
+                    quantity: (data.len() - 1) * 8,
+                };
+                if function == ModbusFunction::READ_COILS {
+                    ModbusResponse::ReadCoils { values }
+                } else {
+                    ModbusResponse::ReadDiscreteInputs { values }
+                }
+            }
+            ModbusFunction::READ_HOLDING_REGISTERS | ModbusFunction::READ_INPUT_REGISTERS => {
+                if data.is_empty() || data.len() != 1 + data[0] as usize || data[0] % 2 != 0 {
+                    return Err(Error::Malformed);
+                }
+                let values = ModbusRegisterIterator { buffer: &data[1..] };
+                if function == ModbusFunction::READ_HOLDING_REGISTERS {
+                    ModbusResponse::ReadHoldingRegisters { values }
+                } else {
+                    ModbusResponse::ReadInputRegisters { values }
+                }
+            }
+            ModbusFunction::WRITE_SINGLE_COIL
+            | ModbusFunction::WRITE_SINGLE_REGISTER
+            | ModbusFunction::WRITE_MULTIPLE_COILS
+            | ModbusFunction::WRITE_MULTIPLE_REGISTERS => {
+                if data.len() != 4 {
+                    return Err(Error::Malformed);
+                }
+                let address = u16::from_be_bytes(data[0..=1].try_into().unwrap());
+                let value = u16::from_be_bytes(data[2..=3].try_into().unwrap());
+                match function {
+                    ModbusFunction::WRITE_SINGLE_COIL => ModbusResponse::WriteSingleCoil {
+                        address,
+                        value: coil_value(value)?,
+                    },
+                    ModbusFunction::WRITE_SINGLE_REGISTER => {
+                        ModbusResponse::WriteSingleRegister { address, value }
+                    }
+                    ModbusFunction::WRITE_MULTIPLE_COILS => ModbusResponse::WriteMultipleCoils {
+                        address,
+                        quantity: value,
+                    },
+                    _ => ModbusResponse::WriteMultipleRegisters {
+                        address,
+                        quantity: value,
+                    },
+                }
+            }
+            _ => ModbusResponse::Raw { function, data },
+        })
+    }
+
+    pub fn transaction_id(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[0..=1].try_into().unwrap())
+    }
+
+    pub fn protocol_id(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[2..=3].try_into().unwrap())
+    }
+
+    pub fn length(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[4..=5].try_into().unwrap())
+    }
+
+    pub fn computed_length(&'a self) -> usize {
+        // A TCP segment may carry several ADUs; this is where the next one starts
+        6 + self.length() as usize
+    }
+
+    pub fn unit_id(&'a self) -> u8 {
+        self.buffer[6]
+    }
+
+    pub fn function(&'a self) -> u8 {
+        self.buffer[7]
+    }
+
+    fn data(&self) -> &'a [u8] {
+        &self.buffer[8..6 + self.length() as usize]
+    }
+}
+
+fn coil_value(value: u16) -> Result<bool> {
+    match value {
+        0xFF00 => Ok(true),
+        0x0000 => Ok(false),
+        _ => Err(Error::Malformed),
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct ModbusBitIterator<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+    quantity: usize,
+}
+
+impl<'a> Iterator for ModbusBitIterator<'a> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.quantity {
+            return None;
+        }
+        let pos = self.pos;
+        self.pos += 1;
+        Some(self.buffer[pos / 8] & (1 << (pos % 8)) != 0)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct ModbusRegisterIterator<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Iterator for ModbusRegisterIterator<'a> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.len() < 2 {
+            return None;
+        }
+        let value = u16::from_be_bytes(self.buffer[0..=1].try_into().unwrap());
+        self.buffer = &self.buffer[2..];
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_holding_registers_round_trip() {
+        let mut modbus_pdu = ModbusPdu::new();
+        modbus_pdu.transaction_id(0x0102);
+        modbus_pdu.unit_id(0x11);
+        modbus_pdu.read_holding_registers(0x006B, 3).unwrap();
+        assert_eq!(
+            modbus_pdu.as_bytes(),
+            [0x01, 0x02, 0x00, 0x00, 0x00, 0x06, 0x11, 0x03, 0x00, 0x6B, 0x00, 0x03]
+        );
+        let modbus = ModbusParser::parse(modbus_pdu.as_bytes()).unwrap();
+        assert_eq!(modbus.transaction_id(), 0x0102);
+        assert_eq!(modbus.unit_id(), 0x11);
+        assert_eq!(modbus.computed_length(), 12);
+        assert!(matches!(
+            modbus.request(),
+            Ok(ModbusRequest::ReadHoldingRegisters {
+                address: 0x006B,
+                quantity: 3,
+            })
+        ));
+
+        modbus_pdu
+            .read_holding_registers_response(&[0x022B, 0x0000, 0x0064])
+            .unwrap();
+        let modbus = ModbusParser::parse(modbus_pdu.as_bytes()).unwrap();
+        let Ok(ModbusResponse::ReadHoldingRegisters { mut values }) = modbus.response() else {
+            panic!("expected Read Holding Registers");
+        };
+        assert_eq!(values.next(), Some(0x022B));
+        assert_eq!(values.next(), Some(0x0000));
+        assert_eq!(values.next(), Some(0x0064));
+        assert_eq!(values.next(), None);
+    }
+
+    #[test]
+    fn write_multiple_coils_round_trip() {
+        let coils = [
+            true, false, true, true, false, false, true, true, true, false,
+        ];
+        let mut modbus_pdu = ModbusPdu::new();
+        modbus_pdu.write_multiple_coils(0x0013, &coils).unwrap();
+        assert_eq!(modbus_pdu.as_bytes()[12..], [0x02, 0xCD, 0x01]);
+        let modbus = ModbusParser::parse(modbus_pdu.as_bytes()).unwrap();
+        let Ok(ModbusRequest::WriteMultipleCoils {
+            address: 0x0013,
+            values,
+        }) = modbus.request()
+        else {
+            panic!("expected Write Multiple Coils");
+        };
+        assert!(values.eq(coils.iter().copied()));
+
+        modbus_pdu.write_multiple_coils_response(0x0013, 10);
+        let modbus = ModbusParser::parse(modbus_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            modbus.response(),
+            Ok(ModbusResponse::WriteMultipleCoils {
+                address: 0x0013,
+                quantity: 10,
+            })
+        ));
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let mut modbus_pdu = ModbusPdu::new();
+        modbus_pdu.write_single_coil(0x00AC, true);
+        let modbus = ModbusParser::parse(modbus_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            modbus.request(),
+            Ok(ModbusRequest::WriteSingleCoil {
+                address: 0x00AC,
+                value: true,
+            })
+        ));
+        modbus_pdu
+            .write_multiple_registers(0x0001, &[0x000A, 0x0102])
+            .unwrap();
+        let modbus = ModbusParser::parse(modbus_pdu.as_bytes()).unwrap();
+        let Ok(ModbusRequest::WriteMultipleRegisters {
+            address: 0x0001,
+            values,
+        }) = modbus.request()
+        else {
+            panic!("expected Write Multiple Registers");
+        };
+        assert!(values.eq([0x000A, 0x0102]));
+        modbus_pdu.exception(
+            ModbusFunction::READ_COILS,
+            ModbusException::ILLEGAL_DATA_ADDRESS,
+        );
+        let modbus = ModbusParser::parse(modbus_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            modbus.response(),
+            Ok(ModbusResponse::Exception {
+                function: ModbusFunction::READ_COILS,
+                code: ModbusException::ILLEGAL_DATA_ADDRESS,
+            })
+        ));
+    }
+
+    #[test]
+    fn oversized_quantities() {
+        let mut modbus_pdu = ModbusPdu::new();
+        assert!(matches!(modbus_pdu.read_coils(0, 0), Err(Error::Oversized)));
+        assert!(matches!(
+            modbus_pdu.read_coils(0, 2001),
+            Err(Error::Oversized)
+        ));
+        assert!(matches!(
+            modbus_pdu.write_multiple_registers(0, &[0u16; 124]),
+            Err(Error::Oversized)
+        ));
+        // Counts that wrap to a small value as u16 must still be rejected
+        assert!(matches!(
+            modbus_pdu.write_multiple_registers(0, &[0u16; 65537]),
+            Err(Error::Oversized)
+        ));
+        assert!(matches!(
+            modbus_pdu.write_multiple_coils(0, &[false; 65537]),
+            Err(Error::Oversized)
+        ));
+        assert!(matches!(
+            modbus_pdu.read_coils_response(&[false; 65536 + 8]),
+            Err(Error::Oversized)
+        ));
+        assert!(modbus_pdu.read_coils_response(&[true; 2000]).is_ok());
+        assert_eq!(modbus_pdu.as_bytes().len(), 7 + 2 + 250);
+    }
+
+    #[test]
+    fn truncated() {
+        let mut modbus_pdu = ModbusPdu::new();
+        modbus_pdu.read_coils(0, 8).unwrap();
+        let bytes = modbus_pdu.as_bytes();
+        assert!(matches!(
+            ModbusParser::parse(&bytes[..7]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            ModbusParser::parse(&bytes[..11]),
+            Err(Error::Truncated)
+        ));
+    }
+
+    #[test]
+    fn malformed() {
+        let mut modbus_pdu = ModbusPdu::new();
+        modbus_pdu.read_coils(0, 8).unwrap();
+        modbus_pdu.protocol_id(1);
+        assert!(matches!(
+            ModbusParser::parse(modbus_pdu.as_bytes()),
+            Err(Error::Malformed)
+        ));
+        // A coil value other than 0xFF00 or 0x0000
+        let modbus = ModbusParser::parse(&[0, 0, 0, 0, 0, 6, 1, 0x05, 0, 1, 0x12, 0x34]).unwrap();
+        assert!(matches!(modbus.request(), Err(Error::Malformed)));
+        // A byte count that disagrees with the quantity
+        let modbus =
+            ModbusParser::parse(&[0, 0, 0, 0, 0, 8, 1, 0x0F, 0, 0, 0, 9, 1, 0xFF]).unwrap();
+        assert!(matches!(modbus.request(), Err(Error::Malformed)));
+        // An odd register byte count
+        let modbus = ModbusParser::parse(&[0, 0, 0, 0, 0, 5, 1, 0x03, 3, 0, 0, 0]).unwrap();
+        assert!(matches!(modbus.response(), Err(Error::Malformed)));
+    }
+}
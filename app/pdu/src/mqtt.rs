@@ -0,0 +1,1066 @@
+use core::convert::TryInto;
+
+use crate::{Error, Result};
+
+#[allow(non_snake_case)]
+pub mod MqttPacketType {
+    pub const CONNECT: u8 = 1;
+    pub const CONNACK: u8 = 2;
+    pub const PUBLISH: u8 = 3;
+    pub const PUBACK: u8 = 4;
+    pub const PUBREC: u8 = 5;
+    pub const PUBREL: u8 = 6;
+    pub const PUBCOMP: u8 = 7;
+    pub const SUBSCRIBE: u8 = 8;
+    pub const SUBACK: u8 = 9;
+    pub const UNSUBSCRIBE: u8 = 10;
+    pub const UNSUBACK: u8 = 11;
+    pub const PINGREQ: u8 = 12;
+    pub const PINGRESP: u8 = 13;
+    pub const DISCONNECT: u8 = 14;
+}
+
+#[allow(non_snake_case)]
+pub mod MqttQos {
+    pub const AT_MOST_ONCE: u8 = 0;
+    pub const AT_LEAST_ONCE: u8 = 1;
+    pub const EXACTLY_ONCE: u8 = 2;
+}
+
+#[allow(non_snake_case)]
+pub mod MqttConnectReturnCode {
+    pub const ACCEPTED: u8 = 0;
+    pub const UNACCEPTABLE_PROTOCOL_VERSION: u8 = 1;
+    pub const IDENTIFIER_REJECTED: u8 = 2;
+    pub const SERVER_UNAVAILABLE: u8 = 3;
+    pub const BAD_USERNAME_OR_PASSWORD: u8 = 4;
+    pub const NOT_AUTHORIZED: u8 = 5;
+}
+
+#[allow(non_snake_case)]
+pub mod MqttSubscribeReturnCode {
+    pub const MAXIMUM_QOS_0: u8 = 0x00;
+    pub const MAXIMUM_QOS_1: u8 = 0x01;
+    pub const MAXIMUM_QOS_2: u8 = 0x02;
+    pub const FAILURE: u8 = 0x80;
+}
+
+#[derive(Copy, Clone)]
+pub enum Mqtt<'a> {
+    Connect {
+        client_id: &'a str,
+        keep_alive: u16,
+        clean_session: bool,
+        will: Option<MqttWill<'a>>,
+        username: Option<&'a str>,
+        password: Option<&'a [u8]>,
+    },
+    ConnAck {
+        session_present: bool,
+        return_code: u8,
+    },
+    Publish {
+        dup: bool,
+        qos: u8,
+        retain: bool,
+        topic: &'a str,
+        packet_id: Option<u16>,
+        payload: &'a [u8],
+    },
+    PubAck {
+        packet_id: u16,
+    },
+    PubRec {
+        packet_id: u16,
+    },
+    PubRel {
+        packet_id: u16,
+    },
+    PubComp {
+        packet_id: u16,
+    },
+    Subscribe {
+        packet_id: u16,
+        topics: MqttSubscriptionIterator<'a>,
+    },
+    SubAck {
+        packet_id: u16,
+        return_codes: &'a [u8],
+    },
+    Unsubscribe {
+        packet_id: u16,
+        topics: MqttTopicIterator<'a>,
+    },
+    UnsubAck {
+        packet_id: u16,
+    },
+    PingReq,
+    PingResp,
+    Disconnect,
+}
+
+#[derive(Copy, Clone)]
+pub struct MqttWill<'a> {
+    pub topic: &'a str,
+    pub message: &'a [u8],
+    pub qos: u8,
+    pub retain: bool,
+}
+
+// Room for the longest fixed header: one type byte and a four-byte remaining length
+const HEADER_SPACE: usize = 5;
+
+pub struct MqttPdu {
+    buffer: [u8; 1460],
+    inner_size: usize,
+    header_size: usize,
+}
+
+impl MqttPdu {
+    pub fn new() -> Self {
+        MqttPdu {
+            buffer: [0u8; 1460],
+            inner_size: 0,
+            header_size: 0,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[HEADER_SPACE - self.header_size..HEADER_SPACE + self.inner_size]
+    }
+
+    pub fn connect(
+        &mut self,
+        client_id: &str,
+        keep_alive: u16,
+        clean_session: bool,
+        will: Option<&MqttWill>,
+        username: Option<&str>,
+        password: Option<&[u8]>,
+    ) -> Result<()> {
+        let mut flags = 0;
+        if clean_session {
+            flags |= 0x02;
+        }
+        if let Some(will) = will {
+            if will.qos > MqttQos::EXACTLY_ONCE {
+                return Err(Error::Malformed);
+            }
+            flags |= 0x04 | will.qos << 3;
+            if will.retain {
+                flags |= 0x20;
+            }
+        }
+        if password.is_some() {
+            if username.is_none() {
+                // A password without a user name is not allowed (MQTT-3.1.2-22)
+                return Err(Error::Malformed);
+            }
+            flags |= 0x40;
+        }
+        if username.is_some() {
+            flags |= 0x80;
+        }
+        // Checked before anything is written so that a failure leaves the previous packet intact
+        let len = 12
+            + client_id.len()
+            + will.map_or(0, |will| 4 + will.topic.len() + will.message.len())
+            + username.map_or(0, |username| 2 + username.len())
+            + password.map_or(0, |password| 2 + password.len());
+        if HEADER_SPACE + len > 1460 {
+            return Err(Error::Oversized);
+        }
+        self.packet(MqttPacketType::CONNECT << 4);
+        self.write(&[0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04, flags])?;
+        self.write(&keep_alive.to_be_bytes())?;
+        self.write_binary(client_id.as_bytes())?;
+        if let Some(will) = will {
+            self.write_binary(will.topic.as_bytes())?;
+            self.write_binary(will.message)?;
+        }
+        if let Some(username) = username {
+            self.write_binary(username.as_bytes())?;
+        }
+        if let Some(password) = password {
+            self.write_binary(password)?;
+        }
+        Ok(())
+    }
+
+    pub fn connack(&mut self, session_present: bool, return_code: u8) {
+        self.packet(MqttPacketType::CONNACK << 4);
+        self.write(&[session_present as u8, return_code]).unwrap();
+    }
+
+    pub fn publish(&mut self, topic: &str, qos: u8, packet_id: u16, payload: &[u8]) -> Result<()> {
+        if qos > MqttQos::EXACTLY_ONCE {
+            return Err(Error::Malformed);
+        }
+        self.packet(MqttPacketType::PUBLISH << 4 | qos << 1);
+        self.write_binary(topic.as_bytes())?;
+        if qos > MqttQos::AT_MOST_ONCE {
+            self.write(&packet_id.to_be_bytes())?;
+        }
+        self.write(payload)
+    }
+
+    pub fn dup(&mut self, value: bool) {
+        self.flag(0x08, value);
+    }
+
+    pub fn retain(&mut self, value: bool) {
+        self.flag(0x01, value);
+    }
+
+    pub fn puback(&mut self, packet_id: u16) {
+        self.acknowledge(MqttPacketType::PUBACK << 4, packet_id);
+    }
+
+    pub fn pubrec(&mut self, packet_id: u16) {
+        self.acknowledge(MqttPacketType::PUBREC << 4, packet_id);
+    }
+
+    pub fn pubrel(&mut self, packet_id: u16) {
+        self.acknowledge(MqttPacketType::PUBREL << 4 | 0x02, packet_id);
+    }
+
+    pub fn pubcomp(&mut self, packet_id: u16) {
+        self.acknowledge(MqttPacketType::PUBCOMP << 4, packet_id);
+    }
+
+    pub fn subscribe(&mut self, packet_id: u16) {
+        self.acknowledge(MqttPacketType::SUBSCRIBE << 4 | 0x02, packet_id);
+    }
+
+    pub fn subscribe_topic(&mut self, topic: &str, qos: u8) -> Result<()> {
+        if qos > MqttQos::EXACTLY_ONCE {
+            return Err(Error::Malformed);
+        }
+        let inner_size = self.inner_size;
+        let result = self
+            .write_binary(topic.as_bytes())
+            .and_then(|_| self.write(&[qos]));
+        if result.is_err() {
+            self.inner_size = inner_size;
+            self.fixed_header();
+        }
+        result
+    }
+
+    pub fn suback(&mut self, packet_id: u16, return_codes: &[u8]) -> Result<()> {
+        if HEADER_SPACE + 2 + return_codes.len() > 1460 {
+            return Err(Error::Oversized);
+        }
+        self.acknowledge(MqttPacketType::SUBACK << 4, packet_id);
+        self.write(return_codes)
+    }
+
+    pub fn unsubscribe(&mut self, packet_id: u16) {
+        self.acknowledge(MqttPacketType::UNSUBSCRIBE << 4 | 0x02, packet_id);
+    }
+
+    pub fn unsubscribe_topic(&mut self, topic: &str) -> Result<()> {
+        let inner_size = self.inner_size;
+        let result = self.write_binary(topic.as_bytes());
+        if result.is_err() {
+            self.inner_size = inner_size;
+            self.fixed_header();
+        }
+        result
+    }
+
+    pub fn unsuback(&mut self, packet_id: u16) {
+        self.acknowledge(MqttPacketType::UNSUBACK << 4, packet_id);
+    }
+
+    pub fn pingreq(&mut self) {
+        self.packet(MqttPacketType::PINGREQ << 4);
+    }
+
+    pub fn pingresp(&mut self) {
+        self.packet(MqttPacketType::PINGRESP << 4);
+    }
+
+    pub fn disconnect(&mut self) {
+        self.packet(MqttPacketType::DISCONNECT << 4);
+    }
+
+    fn packet(&mut self, value: u8) {
+        self.buffer[0] = value;
+        self.inner_size = 0;
+        self.fixed_header();
+    }
+
+    fn flag(&mut self, mask: u8, value: bool) {
+        if value {
+            self.buffer[0] |= mask;
+        } else {
+            self.buffer[0] &= !mask;
+        }
+        self.fixed_header();
+    }
+
+    fn acknowledge(&mut self, value: u8, packet_id: u16) {
+        self.packet(value);
+        self.write(&packet_id.to_be_bytes()).unwrap();
+    }
+
+    fn fixed_header(&mut self) {
+        // buffer[0] keeps the type byte; the header is laid out right before the body
+        let mut header = [self.buffer[0], 0, 0, 0, 0];
+        let mut len = self.inner_size;
+        let mut header_size = 1;
+        loop {
+            let mut byte = (len % 128) as u8;
+            len /= 128;
+            if len > 0 {
+                byte |= 0x80;
+            }
+            header[header_size] = byte;
+            header_size += 1;
+            if len == 0 {
+                break;
+            }
+        }
+        self.header_size = header_size;
+        self.buffer[HEADER_SPACE - header_size..HEADER_SPACE]
+            .copy_from_slice(&header[..header_size]);
+        self.buffer[0] = header[0];
+    }
+
+    fn write(&mut self, value: &[u8]) -> Result<()> {
+        let pos = HEADER_SPACE + self.inner_size;
+        let len = value.len();
+        if pos + len > 1460 {
+            return Err(Error::Oversized);
+        }
+        self.buffer[pos..pos + len].copy_from_slice(value);
+        self.inner_size += len;
+        self.fixed_header();
+        Ok(())
+    }
+
+    fn write_binary(&mut self, value: &[u8]) -> Result<()> {
+        if HEADER_SPACE + self.inner_size + 2 + value.len() > 1460 {
+            return Err(Error::Oversized);
+        }
+        self.write(&(value.len() as u16).to_be_bytes())?;
+        self.write(value)
+    }
+}
+
+impl Default for MqttPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct MqttParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> MqttParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 2 {
+            return Err(Error::Truncated);
+        }
+        let (remaining_length, header_size) = decode_remaining_length(&buffer[1..])?;
+        let len = 1 + header_size + remaining_length;
+        if buffer.len() < len {
+            return Err(Error::Truncated);
+        }
+        // Only the first control packet is kept when several share a segment
+        Ok(MqttParser {
+            buffer: &buffer[..len],
+        })
+    }
+
+    pub fn inner(&'a self) -> Result<Mqtt<'a>> {
+        (*self).into_inner()
+    }
+
+    pub fn into_inner(self) -> Result<Mqtt<'a>> {
+        let body = self.body();
+        let flags = self.flags();
+        let expected_flags = match self.packet_type() {
+            MqttPacketType::PUBLISH => flags,
+            MqttPacketType::PUBREL | MqttPacketType::SUBSCRIBE | MqttPacketType::UNSUBSCRIBE => {
+                0x02
+            }
+            _ => 0x00,
+        };
+        if flags != expected_flags {
+            return Err(Error::Malformed);
+        }
+        Ok(match self.packet_type() {
+            MqttPacketType::CONNECT => {
+                if body.len() < 10 || body[0..=6] != [0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04] {
+                    // Supports only MQTT 3.1.1
+                    return Err(Error::Malformed);
+                }
+                let connect_flags = body[7];
+                let will_qos = (connect_flags >> 3) & 0x03;
+                if connect_flags & 0x01 != 0 || will_qos > MqttQos::EXACTLY_ONCE {
+                    return Err(Error::Malformed);
+                }
+                let keep_alive = u16::from_be_bytes(body[8..=9].try_into().unwrap());
+                let (client_id, mut rest) = split_string(&body[10..])?;
+                let will = if connect_flags & 0x04 != 0 {
+                    let (topic, next) = split_string(rest)?;
+                    let (message, next) = split_binary(next)?;
+                    rest = next;
+                    Some(MqttWill {
+                        topic,
+                        message,
+                        qos: will_qos,
+                        retain: connect_flags & 0x20 != 0,
+                    })
+                } else {
+                    if connect_flags & 0x38 != 0 {
+                        return Err(Error::Malformed);
+                    }
+                    None
+                };
+                let username = if connect_flags & 0x80 != 0 {
+                    let (username, next) = split_string(rest)?;
+                    rest = next;
+                    Some(username)
+                } else {
+                    None
+                };
+                let password = if connect_flags & 0x40 != 0 {
+                    let (password, next) = split_binary(rest)?;
+                    rest = next;
+                    Some(password)
+                } else {
+                    None
+                };
+                if !rest.is_empty() {
+                    return Err(Error::Malformed);
+                }
+                Mqtt::Connect {
+                    client_id,
+                    keep_alive,
+                    clean_session: connect_flags & 0x02 != 0,
+                    will,
+                    username,
+                    password,
+                }
+            }
+            MqttPacketType::CONNACK => {
+                if body.len() != 2 || body[0] & 0xFE != 0 {
+                    return Err(Error::Malformed);
+                }
+                Mqtt::ConnAck {
+                    session_present: body[0] & 0x01 != 0,
+                    return_code: body[1],
+                }
+            }
+            MqttPacketType::PUBLISH => {
+                let qos = (flags >> 1) & 0x03;
+                // DUP must be clear on QoS 0 messages (MQTT-3.3.1-2)
+                if qos > MqttQos::EXACTLY_ONCE || qos == MqttQos::AT_MOST_ONCE && flags & 0x08 != 0
+                {
+                    return Err(Error::Malformed);
+                }
+                let (topic, mut payload) = split_string(body)?;
+                let packet_id = if qos > MqttQos::AT_MOST_ONCE {
+                    if payload.len() < 2 {
+                        return Err(Error::Malformed);
+                    }
+                    let packet_id = u16::from_be_bytes(payload[0..=1].try_into().unwrap());
+                    payload = &payload[2..];
+                    Some(packet_id)
+                } else {
+                    None
+                };
+                Mqtt::Publish {
+                    dup: flags & 0x08 != 0,
+                    qos,
+                    retain: flags & 0x01 != 0,
+                    topic,
+                    packet_id,
+                    payload,
+                }
+            }
+            MqttPacketType::PUBACK
+            | MqttPacketType::PUBREC
+            | MqttPacketType::PUBREL
+            | MqttPacketType::PUBCOMP
+            | MqttPacketType::UNSUBACK => {
+                if body.len() != 2 {
+                    return Err(Error::Malformed);
+                }
+                let packet_id = u16::from_be_bytes(body[0..=1].try_into().unwrap());
+                match self.packet_type() {
+                    MqttPacketType::PUBACK => Mqtt::PubAck { packet_id },
+                    MqttPacketType::PUBREC => Mqtt::PubRec { packet_id },
+                    MqttPacketType::PUBREL => Mqtt::PubRel { packet_id },
+                    MqttPacketType::PUBCOMP => Mqtt::PubComp { packet_id },
+                    _ => Mqtt::UnsubAck { packet_id },
+                }
+            }
+            MqttPacketType::SUBSCRIBE => {
+                if body.len() < 2 {
+                    return Err(Error::Malformed);
+                }
+                Mqtt::Subscribe {
+                    packet_id: u16::from_be_bytes(body[0..=1].try_into().unwrap()),
+                    topics: MqttSubscriptionIterator::parse(&body[2..])?,
+                }
+            }
+            MqttPacketType::SUBACK => {
+                if body.len() < 3 {
+                    return Err(Error::Malformed);
+                }
+                Mqtt::SubAck {
+                    packet_id: u16::from_be_bytes(body[0..=1].try_into().unwrap()),
+                    return_codes: &body[2..],
+                }
+            }
+            MqttPacketType::UNSUBSCRIBE => {
+                if body.len() < 2 {
+                    return Err(Error::Malformed);
+                }
+                Mqtt::Unsubscribe {
+                    packet_id: u16::from_be_bytes(body[0..=1].try_into().unwrap()),
+                    topics: MqttTopicIterator::parse(&body[2..])?,
+                }
+            }
+            MqttPacketType::PINGREQ | MqttPacketType::PINGRESP | MqttPacketType::DISCONNECT => {
+                if !body.is_empty() {
+                    return Err(Error::Malformed);
+                }
+                match self.packet_type() {
+                    MqttPacketType::PINGREQ => Mqtt::PingReq,
+                    MqttPacketType::PINGRESP => Mqtt::PingResp,
+                    _ => Mqtt::Disconnect,
+                }
+            }
+            _ => return Err(Error::Malformed),
+        })
+    }
+
+    pub fn packet_type(&'a self) -> u8 {
+        self.buffer[0] >> 4
+    }
+
+    pub fn flags(&'a self) -> u8 {
+        self.buffer[0] & 0x0F
+    }
+
+    pub fn remaining_length(&'a self) -> usize {
+        decode_remaining_length(&self.buffer[1..]).unwrap().0
+    }
+
+    pub fn computed_length(&'a self) -> usize {
+        self.buffer.len()
+    }
+
+    fn body(&self) -> &'a [u8] {
+        let (remaining_length, header_size) = decode_remaining_length(&self.buffer[1..]).unwrap();
+        &self.buffer[1 + header_size..1 + header_size + remaining_length]
+    }
+}
+
+fn decode_remaining_length(buffer: &[u8]) -> Result<(usize, usize)> {
+    let mut value = 0;
+    for (i, &byte) in buffer.iter().enumerate().take(4) {
+        value |= ((byte & 0x7F) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    if buffer.len() < 4 {
+        return Err(Error::Truncated);
+    }
+    Err(Error::Malformed)
+}
+
+fn split_binary(buffer: &[u8]) -> Result<(&[u8], &[u8])> {
+    if buffer.len() < 2 {
+        return Err(Error::Malformed);
+    }
+    let len = u16::from_be_bytes(buffer[0..=1].try_into().unwrap()) as usize;
+    if buffer.len() < 2 + len {
+        return Err(Error::Malformed);
+    }
+    Ok((&buffer[2..2 + len], &buffer[2 + len..]))
+}
+
+fn split_string(buffer: &[u8]) -> Result<(&str, &[u8])> {
+    let (string, rest) = split_binary(buffer)?;
+    let string = core::str::from_utf8(string).map_err(|_| Error::Malformed)?;
+    Ok((string, rest))
+}
+
+#[derive(Copy, Clone)]
+pub struct MqttSubscriptionIterator<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> MqttSubscriptionIterator<'a> {
+    fn parse(buffer: &'a [u8]) -> Result<Self> {
+        // At least one topic filter is required (MQTT-3.8.3-3)
+        if buffer.is_empty() {
+            return Err(Error::Malformed);
+        }
+        let mut rest = buffer;
+        while !rest.is_empty() {
+            let (_, next) = split_string(rest)?;
+            if next.is_empty() || next[0] > MqttQos::EXACTLY_ONCE {
+                return Err(Error::Malformed);
+            }
+            rest = &next[1..];
+        }
+        Ok(MqttSubscriptionIterator { buffer })
+    }
+}
+
+impl<'a> Iterator for MqttSubscriptionIterator<'a> {
+    type Item = (&'a str, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (topic, rest) = split_string(self.buffer).ok()?;
+        let qos = *rest.first()?;
+        self.buffer = &rest[1..];
+        Some((topic, qos))
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct MqttTopicIterator<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> MqttTopicIterator<'a> {
+    fn parse(buffer: &'a [u8]) -> Result<Self> {
+        // At least one topic filter is required (MQTT-3.10.3-2)
+        if buffer.is_empty() {
+            return Err(Error::Malformed);
+        }
+        let mut rest = buffer;
+        while !rest.is_empty() {
+            rest = split_string(rest)?.1;
+        }
+        Ok(MqttTopicIterator { buffer })
+    }
+}
+
+impl<'a> Iterator for MqttTopicIterator<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (topic, rest) = split_string(self.buffer).ok()?;
+        self.buffer = rest;
+        Some(topic)
+    }
+}
+
+pub struct MqttStream {
+    buffer: [u8; 2048],
+    len: usize,
+}
+
+impl MqttStream {
+    pub fn new() -> Self {
+        MqttStream {
+            buffer: [0u8; 2048],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) -> Result<()> {
+        let len = data.len();
+        if self.len + len > 2048 {
+            return Err(Error::Oversized);
+        }
+        self.buffer[self.len..self.len + len].copy_from_slice(data);
+        self.len += len;
+        Ok(())
+    }
+
+    pub fn packet(&self) -> Result<Option<MqttParser<'_>>> {
+        match MqttParser::parse(&self.buffer[..self.len]) {
+            Ok(pdu) => Ok(Some(pdu)),
+            Err(Error::Truncated) => {
+                // A packet larger than the buffer would never complete
+                let rest = self.buffer[..self.len].get(1..).unwrap_or(&[]);
+                match decode_remaining_length(rest) {
+                    Ok((remaining_length, header_size))
+                        if 1 + header_size + remaining_length > self.buffer.len() =>
+                    {
+                        Err(Error::Oversized)
+                    }
+                    _ => Ok(None),
+                }
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn consume(&mut self) {
+        let len = match self.packet() {
+            Ok(Some(pdu)) => pdu.computed_length(),
+            Ok(None) => return,
+            // The stream cannot be resynchronized after a malformed or oversized fixed header
+            Err(_) => self.len,
+        };
+        self.buffer.copy_within(len..self.len, 0);
+        self.len -= len;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Default for MqttStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connect_round_trip() {
+        let mut mqtt_pdu = MqttPdu::new();
+        mqtt_pdu
+            .connect(
+                "nucleo",
+                60,
+                true,
+                Some(&MqttWill {
+                    topic: "status",
+                    message: b"offline",
+                    qos: MqttQos::AT_LEAST_ONCE,
+                    retain: true,
+                }),
+                Some("user"),
+                Some(b"secret"),
+            )
+            .unwrap();
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        assert_eq!(mqtt.packet_type(), MqttPacketType::CONNECT);
+        assert_eq!(mqtt.computed_length(), mqtt_pdu.as_bytes().len());
+        let Ok(Mqtt::Connect {
+            client_id: "nucleo",
+            keep_alive: 60,
+            clean_session: true,
+            will: Some(will),
+            username: Some("user"),
+            password: Some(b"secret"),
+        }) = mqtt.inner()
+        else {
+            panic!("expected CONNECT");
+        };
+        assert_eq!(will.topic, "status");
+        assert_eq!(will.message, b"offline");
+        assert_eq!(will.qos, MqttQos::AT_LEAST_ONCE);
+        assert!(will.retain);
+
+        // A password without a user name is not allowed
+        assert!(matches!(
+            mqtt_pdu.connect("nucleo", 60, true, None, None, Some(b"secret")),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn connack_round_trip() {
+        let mut mqtt_pdu = MqttPdu::new();
+        mqtt_pdu.connack(true, MqttConnectReturnCode::ACCEPTED);
+        assert_eq!(mqtt_pdu.as_bytes(), [0x20, 0x02, 0x01, 0x00]);
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            mqtt.inner(),
+            Ok(Mqtt::ConnAck {
+                session_present: true,
+                return_code: MqttConnectReturnCode::ACCEPTED,
+            })
+        ));
+    }
+
+    #[test]
+    fn publish_round_trip() {
+        let mut mqtt_pdu = MqttPdu::new();
+        // A 200-octet payload needs a two-byte remaining length
+        mqtt_pdu
+            .publish("sensors/temp", MqttQos::EXACTLY_ONCE, 42, &[0xAA; 200])
+            .unwrap();
+        mqtt_pdu.dup(true);
+        mqtt_pdu.retain(true);
+        let bytes = mqtt_pdu.as_bytes();
+        assert_eq!(bytes[..3], [0x3D, 0xD8, 0x01]);
+        let mqtt = MqttParser::parse(bytes).unwrap();
+        assert_eq!(mqtt.remaining_length(), 216);
+        let Ok(Mqtt::Publish {
+            dup: true,
+            qos: MqttQos::EXACTLY_ONCE,
+            retain: true,
+            topic: "sensors/temp",
+            packet_id: Some(42),
+            payload,
+        }) = mqtt.inner()
+        else {
+            panic!("expected PUBLISH");
+        };
+        assert_eq!(payload, [0xAA; 200]);
+
+        mqtt_pdu
+            .publish("sensors/temp", MqttQos::AT_MOST_ONCE, 0, b"21.5")
+            .unwrap();
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            mqtt.inner(),
+            Ok(Mqtt::Publish {
+                dup: false,
+                qos: MqttQos::AT_MOST_ONCE,
+                retain: false,
+                packet_id: None,
+                payload: b"21.5",
+                ..
+            })
+        ));
+        // DUP must be clear on QoS 0 messages
+        mqtt_pdu.dup(true);
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        assert!(matches!(mqtt.inner(), Err(Error::Malformed)));
+    }
+
+    #[test]
+    fn subscribe_round_trip() {
+        let mut mqtt_pdu = MqttPdu::new();
+        mqtt_pdu.subscribe(7);
+        mqtt_pdu
+            .subscribe_topic("sensors/#", MqttQos::AT_LEAST_ONCE)
+            .unwrap();
+        mqtt_pdu
+            .subscribe_topic("commands/+", MqttQos::AT_MOST_ONCE)
+            .unwrap();
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        let Ok(Mqtt::Subscribe {
+            packet_id: 7,
+            topics,
+        }) = mqtt.inner()
+        else {
+            panic!("expected SUBSCRIBE");
+        };
+        assert!(topics.eq([
+            ("sensors/#", MqttQos::AT_LEAST_ONCE),
+            ("commands/+", MqttQos::AT_MOST_ONCE),
+        ]));
+
+        mqtt_pdu
+            .suback(
+                7,
+                &[
+                    MqttSubscribeReturnCode::MAXIMUM_QOS_1,
+                    MqttSubscribeReturnCode::FAILURE,
+                ],
+            )
+            .unwrap();
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            mqtt.inner(),
+            Ok(Mqtt::SubAck {
+                packet_id: 7,
+                return_codes: &[
+                    MqttSubscribeReturnCode::MAXIMUM_QOS_1,
+                    MqttSubscribeReturnCode::FAILURE
+                ],
+            })
+        ));
+
+        // At least one topic filter is required
+        mqtt_pdu.subscribe(8);
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        assert!(matches!(mqtt.inner(), Err(Error::Malformed)));
+    }
+
+    #[test]
+    fn unsubscribe_round_trip() {
+        let mut mqtt_pdu = MqttPdu::new();
+        mqtt_pdu.unsubscribe(9);
+        mqtt_pdu.unsubscribe_topic("sensors/#").unwrap();
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        let Ok(Mqtt::Unsubscribe {
+            packet_id: 9,
+            topics,
+        }) = mqtt.inner()
+        else {
+            panic!("expected UNSUBSCRIBE");
+        };
+        assert!(topics.eq(["sensors/#"]));
+        mqtt_pdu.unsuback(9);
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        assert!(matches!(mqtt.inner(), Ok(Mqtt::UnsubAck { packet_id: 9 })));
+    }
+
+    #[test]
+    fn oversized_topics_are_rolled_back() {
+        let topic = core::str::from_utf8(&[b'a'; 1450]).unwrap();
+        let mut mqtt_pdu = MqttPdu::new();
+        mqtt_pdu.subscribe(1);
+        mqtt_pdu
+            .subscribe_topic("a", MqttQos::AT_MOST_ONCE)
+            .unwrap();
+        assert!(matches!(
+            mqtt_pdu.subscribe_topic(topic, MqttQos::AT_MOST_ONCE),
+            Err(Error::Oversized)
+        ));
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        let Ok(Mqtt::Subscribe { topics, .. }) = mqtt.inner() else {
+            panic!("expected SUBSCRIBE");
+        };
+        assert!(topics.eq([("a", MqttQos::AT_MOST_ONCE)]));
+
+        mqtt_pdu.unsubscribe(2);
+        mqtt_pdu.unsubscribe_topic("a").unwrap();
+        assert!(matches!(
+            mqtt_pdu.unsubscribe_topic(topic),
+            Err(Error::Oversized)
+        ));
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        let Ok(Mqtt::Unsubscribe { topics, .. }) = mqtt.inner() else {
+            panic!("expected UNSUBSCRIBE");
+        };
+        assert!(topics.eq(["a"]));
+    }
+
+    #[test]
+    fn control_packets_round_trip() {
+        let mut mqtt_pdu = MqttPdu::new();
+        mqtt_pdu.pubrel(3);
+        assert_eq!(mqtt_pdu.as_bytes(), [0x62, 0x02, 0x00, 0x03]);
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        assert!(matches!(mqtt.inner(), Ok(Mqtt::PubRel { packet_id: 3 })));
+        mqtt_pdu.pingreq();
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        assert!(matches!(mqtt.inner(), Ok(Mqtt::PingReq)));
+        mqtt_pdu.disconnect();
+        let mqtt = MqttParser::parse(mqtt_pdu.as_bytes()).unwrap();
+        assert!(matches!(mqtt.inner(), Ok(Mqtt::Disconnect)));
+        // PUBREL must carry the reserved flags 0b0010
+        let mqtt = MqttParser::parse(&[0x60, 0x02, 0x00, 0x03]).unwrap();
+        assert!(matches!(mqtt.inner(), Err(Error::Malformed)));
+    }
+
+    #[test]
+    fn truncated_and_malformed() {
+        let mut mqtt_pdu = MqttPdu::new();
+        mqtt_pdu
+            .publish("a", MqttQos::AT_MOST_ONCE, 0, &[0; 200])
+            .unwrap();
+        let bytes = mqtt_pdu.as_bytes();
+        assert!(matches!(
+            MqttParser::parse(&bytes[..1]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            MqttParser::parse(&bytes[..2]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            MqttParser::parse(&bytes[..bytes.len() - 1]),
+            Err(Error::Truncated)
+        ));
+        // A remaining length of more than four bytes
+        assert!(matches!(
+            MqttParser::parse(&[0x30, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn stream_reassembles_packets() {
+        let mut mqtt_pdu = MqttPdu::new();
+        mqtt_pdu
+            .publish("a", MqttQos::AT_MOST_ONCE, 0, b"hello")
+            .unwrap();
+        let mut segment = [0u8; 64];
+        let len = mqtt_pdu.as_bytes().len();
+        segment[..len].copy_from_slice(mqtt_pdu.as_bytes());
+        mqtt_pdu.pingreq();
+        segment[len..len + 2].copy_from_slice(mqtt_pdu.as_bytes());
+
+        let mut stream = MqttStream::new();
+        stream.push(&segment[..3]).unwrap();
+        assert!(matches!(stream.packet(), Ok(None)));
+        stream.push(&segment[3..len + 1]).unwrap();
+        let Ok(Some(mqtt)) = stream.packet() else {
+            panic!("expected a complete packet");
+        };
+        assert!(matches!(
+            mqtt.inner(),
+            Ok(Mqtt::Publish {
+                payload: b"hello",
+                ..
+            })
+        ));
+        stream.consume();
+        assert!(matches!(stream.packet(), Ok(None)));
+        stream.push(&segment[len + 1..len + 2]).unwrap();
+        let Ok(Some(mqtt)) = stream.packet() else {
+            panic!("expected a complete packet");
+        };
+        assert!(matches!(mqtt.inner(), Ok(Mqtt::PingReq)));
+        stream.consume();
+        assert!(matches!(stream.packet(), Ok(None)));
+    }
+
+    #[test]
+    fn stream_reports_oversized_packets() {
+        let mut stream = MqttStream::new();
+        // A PUBLISH with a remaining length of 4000 can never fit the buffer
+        stream.push(&[0x30, 0xA0, 0x1F, 0x00, 0x01]).unwrap();
+        assert!(matches!(stream.packet(), Err(Error::Oversized)));
+        stream.consume();
+        assert!(matches!(stream.packet(), Ok(None)));
+        assert!(matches!(stream.push(&[0u8; 2049]), Err(Error::Oversized)));
+    }
+    #[test]
+    fn oversized_packets_leave_the_previous_one() {
+        let client_id = core::str::from_utf8(&[b'a'; 1444]).unwrap();
+        let mut mqtt_pdu = MqttPdu::new();
+        mqtt_pdu.pingreq();
+        assert!(matches!(
+            mqtt_pdu.connect(client_id, 60, true, None, None, None),
+            Err(Error::Oversized)
+        ));
+        assert_eq!(mqtt_pdu.as_bytes(), [0xC0, 0x00]);
+        let will = MqttWill {
+            topic: "status",
+            message: &[0u8; 1440],
+            qos: MqttQos::AT_MOST_ONCE,
+            retain: false,
+        };
+        assert!(matches!(
+            mqtt_pdu.connect("nucleo", 60, true, Some(&will), None, None),
+            Err(Error::Oversized)
+        ));
+        assert_eq!(mqtt_pdu.as_bytes(), [0xC0, 0x00]);
+        // The largest CONNECT that fits
+        let client_id = core::str::from_utf8(&[b'a'; 1443]).unwrap();
+        mqtt_pdu
+            .connect(client_id, 60, true, None, None, None)
+            .unwrap();
+        assert!(matches!(
+            MqttParser::parse(mqtt_pdu.as_bytes()).unwrap().inner(),
+            Ok(Mqtt::Connect { .. })
+        ));
+
+        mqtt_pdu.pingresp();
+        assert!(matches!(
+            mqtt_pdu.suback(1, &[0u8; 1454]),
+            Err(Error::Oversized)
+        ));
+        assert_eq!(mqtt_pdu.as_bytes(), [0xD0, 0x00]);
+        mqtt_pdu.suback(1, &[0u8; 1453]).unwrap();
+        assert_eq!(mqtt_pdu.as_bytes().len(), 3 + 1455);
+    }
+}
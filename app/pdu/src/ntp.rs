@@ -0,0 +1,339 @@
+use core::convert::TryInto;
+
+use crate::{Error, Result};
+
+#[allow(non_snake_case)]
+pub mod NtpLeapIndicator {
+    pub const NO_WARNING: u8 = 0;
+    pub const LAST_MINUTE_61: u8 = 1;
+    pub const LAST_MINUTE_59: u8 = 2;
+    pub const ALARM: u8 = 3;
+}
+
+#[allow(non_snake_case)]
+pub mod NtpMode {
+    pub const SYMMETRIC_ACTIVE: u8 = 1;
+    pub const SYMMETRIC_PASSIVE: u8 = 2;
+    pub const CLIENT: u8 = 3;
+    pub const SERVER: u8 = 4;
+    pub const BROADCAST: u8 = 5;
+}
+
+// Seconds from the NTP prime epoch (1900-01-01) to the Unix epoch (1970-01-01)
+const UNIX_OFFSET: u64 = 2_208_988_800;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct NtpTimestamp {
+    pub seconds: u32,
+    pub fraction: u32,
+}
+
+impl NtpTimestamp {
+    pub fn from_unix(seconds: u64, nanos: u32) -> Self {
+        // Whole seconds in nanos are carried over instead of wrapping the fraction
+        let seconds = seconds.wrapping_add((nanos / 1_000_000_000) as u64);
+        let nanos = nanos % 1_000_000_000;
+        NtpTimestamp {
+            // Wraps into the next era after 2036-02-07
+            seconds: seconds.wrapping_add(UNIX_OFFSET) as u32,
+            // Rounded up so that unix_nanos() gives back the same value
+            fraction: ((nanos as u64) << 32).div_ceil(1_000_000_000) as u32,
+        }
+    }
+
+    pub fn unix_seconds(&self) -> Option<u64> {
+        // Timestamps with the MSB clear are taken to be in era 1 (RFC 4330, Section 3)
+        if self.seconds & 0x8000_0000 != 0 {
+            // Anything before 1970 has no Unix representation
+            (self.seconds as u64).checked_sub(UNIX_OFFSET)
+        } else {
+            Some(self.seconds as u64 + (1 << 32) - UNIX_OFFSET)
+        }
+    }
+
+    pub fn unix_nanos(&self) -> u32 {
+        ((self.fraction as u64 * 1_000_000_000) >> 32) as u32
+    }
+
+    fn from_bytes(value: &[u8]) -> Self {
+        NtpTimestamp {
+            seconds: u32::from_be_bytes(value[0..=3].try_into().unwrap()),
+            fraction: u32::from_be_bytes(value[4..=7].try_into().unwrap()),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 8] {
+        let mut bytes = [0u8; 8];
+        bytes[0..=3].copy_from_slice(&self.seconds.to_be_bytes());
+        bytes[4..=7].copy_from_slice(&self.fraction.to_be_bytes());
+        bytes
+    }
+}
+
+pub struct NtpPdu {
+    buffer: [u8; 48],
+}
+
+impl NtpPdu {
+    pub fn new() -> Self {
+        let mut ntp_pdu = NtpPdu { buffer: [0u8; 48] };
+        ntp_pdu.version(0x04);
+        ntp_pdu
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    pub fn leap_indicator(&mut self, value: u8) {
+        let leap_indicator = self.buffer[0] & 0x3F | value << 6;
+        self.buffer[0] = leap_indicator;
+    }
+
+    pub fn version(&mut self, value: u8) {
+        let version = self.buffer[0] & 0xC7 | (value & 0x7) << 3;
+        self.buffer[0] = version;
+    }
+
+    pub fn mode(&mut self, value: u8) {
+        let mode = self.buffer[0] & 0xF8 | value & 0x7;
+        self.buffer[0] = mode;
+    }
+
+    pub fn stratum(&mut self, value: u8) {
+        self.buffer[1] = value;
+    }
+
+    pub fn poll(&mut self, value: i8) {
+        self.buffer[2] = value as u8;
+    }
+
+    pub fn precision(&mut self, value: i8) {
+        self.buffer[3] = value as u8;
+    }
+
+    pub fn root_delay(&mut self, value: u32) {
+        self.buffer[4..=7].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn root_dispersion(&mut self, value: u32) {
+        self.buffer[8..=11].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn reference_id(&mut self, value: [u8; 4]) {
+        self.buffer[12..=15].copy_from_slice(&value);
+    }
+
+    pub fn reference_timestamp(&mut self, value: NtpTimestamp) {
+        self.buffer[16..=23].copy_from_slice(&value.to_bytes());
+    }
+
+    pub fn origin_timestamp(&mut self, value: NtpTimestamp) {
+        self.buffer[24..=31].copy_from_slice(&value.to_bytes());
+    }
+
+    pub fn receive_timestamp(&mut self, value: NtpTimestamp) {
+        self.buffer[32..=39].copy_from_slice(&value.to_bytes());
+    }
+
+    pub fn transmit_timestamp(&mut self, value: NtpTimestamp) {
+        self.buffer[40..=47].copy_from_slice(&value.to_bytes());
+    }
+}
+
+impl Default for NtpPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct NtpParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> NtpParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 48 {
+            return Err(Error::Truncated);
+        }
+        let pdu = NtpParser { buffer };
+        if pdu.version() != 3 && pdu.version() != 4 {
+            // Supports only NTPv3 and NTPv4
+            return Err(Error::Malformed);
+        }
+        Ok(pdu)
+    }
+
+    pub fn leap_indicator(&'a self) -> u8 {
+        self.buffer[0] >> 6
+    }
+
+    pub fn version(&'a self) -> u8 {
+        (self.buffer[0] >> 3) & 0x7
+    }
+
+    pub fn mode(&'a self) -> u8 {
+        self.buffer[0] & 0x7
+    }
+
+    pub fn stratum(&'a self) -> u8 {
+        self.buffer[1]
+    }
+
+    pub fn poll(&'a self) -> i8 {
+        self.buffer[2] as i8
+    }
+
+    pub fn precision(&'a self) -> i8 {
+        self.buffer[3] as i8
+    }
+
+    pub fn root_delay(&'a self) -> u32 {
+        u32::from_be_bytes(self.buffer[4..=7].try_into().unwrap())
+    }
+
+    pub fn root_dispersion(&'a self) -> u32 {
+        u32::from_be_bytes(self.buffer[8..=11].try_into().unwrap())
+    }
+
+    pub fn reference_id(&'a self) -> [u8; 4] {
+        let mut reference_id = [0u8; 4];
+        reference_id.copy_from_slice(&self.buffer[12..=15]);
+        reference_id
+    }
+
+    pub fn reference_timestamp(&'a self) -> NtpTimestamp {
+        NtpTimestamp::from_bytes(&self.buffer[16..=23])
+    }
+
+    pub fn origin_timestamp(&'a self) -> NtpTimestamp {
+        NtpTimestamp::from_bytes(&self.buffer[24..=31])
+    }
+
+    pub fn receive_timestamp(&'a self) -> NtpTimestamp {
+        NtpTimestamp::from_bytes(&self.buffer[32..=39])
+    }
+
+    pub fn transmit_timestamp(&'a self) -> NtpTimestamp {
+        NtpTimestamp::from_bytes(&self.buffer[40..=47])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_response_round_trip() {
+        let mut ntp_pdu = NtpPdu::new();
+        ntp_pdu.leap_indicator(NtpLeapIndicator::NO_WARNING);
+        ntp_pdu.mode(NtpMode::SERVER);
+        ntp_pdu.stratum(1);
+        ntp_pdu.poll(6);
+        ntp_pdu.precision(-20);
+        ntp_pdu.root_delay(0x0000_0100);
+        ntp_pdu.root_dispersion(0x0000_0200);
+        ntp_pdu.reference_id(*b"GPS\0");
+        let timestamp = NtpTimestamp::from_unix(1_700_000_000, 123_456_789);
+        ntp_pdu.reference_timestamp(timestamp);
+        ntp_pdu.origin_timestamp(NtpTimestamp {
+            seconds: 1,
+            fraction: 2,
+        });
+        ntp_pdu.receive_timestamp(timestamp);
+        ntp_pdu.transmit_timestamp(timestamp);
+        let mut udp_pdu = crate::UdpPdu::new();
+        udp_pdu.source_port(crate::UdpPort::NTP);
+        // Dispatch follows the destination port, as for a client bound to port 123
+        udp_pdu.destination_port(crate::UdpPort::NTP);
+        udp_pdu.inner(ntp_pdu.as_bytes()).unwrap();
+        let udp = crate::UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(crate::Udp::Ntp(ntp)) = udp.inner() else {
+            panic!("expected NTP");
+        };
+        assert_eq!(ntp.leap_indicator(), NtpLeapIndicator::NO_WARNING);
+        assert_eq!(ntp.version(), 4);
+        assert_eq!(ntp.mode(), NtpMode::SERVER);
+        assert_eq!(ntp.stratum(), 1);
+        assert_eq!(ntp.poll(), 6);
+        assert_eq!(ntp.precision(), -20);
+        assert_eq!(ntp.root_delay(), 0x0000_0100);
+        assert_eq!(ntp.root_dispersion(), 0x0000_0200);
+        assert_eq!(ntp.reference_id(), *b"GPS\0");
+        assert_eq!(ntp.reference_timestamp(), timestamp);
+        assert_eq!(
+            ntp.origin_timestamp(),
+            NtpTimestamp {
+                seconds: 1,
+                fraction: 2,
+            }
+        );
+        assert_eq!(ntp.receive_timestamp(), timestamp);
+        assert_eq!(ntp.transmit_timestamp(), timestamp);
+    }
+
+    #[test]
+    fn unix_round_trip() {
+        for (seconds, nanos) in [
+            (0, 0),
+            (1_700_000_000, 123_456_789),
+            (1_700_000_000, 999_999_999),
+            // 2036-02-07T06:28:16Z, the start of era 1
+            (2_085_978_496, 1),
+        ] {
+            let timestamp = NtpTimestamp::from_unix(seconds, nanos);
+            assert_eq!(timestamp.unix_seconds(), Some(seconds));
+            assert_eq!(timestamp.unix_nanos(), nanos);
+        }
+        assert_eq!(NtpTimestamp::from_unix(2_085_978_496, 0).seconds, 0);
+    }
+
+    #[test]
+    fn from_unix_carries_whole_seconds() {
+        assert_eq!(
+            NtpTimestamp::from_unix(10, 1_500_000_000),
+            NtpTimestamp::from_unix(11, 500_000_000)
+        );
+        assert_eq!(
+            NtpTimestamp::from_unix(10, u32::MAX),
+            NtpTimestamp::from_unix(14, 294_967_295)
+        );
+        // Saturating the inputs must not panic
+        NtpTimestamp::from_unix(u64::MAX, u32::MAX);
+    }
+
+    #[test]
+    fn unix_seconds_before_1970() {
+        let timestamp = NtpTimestamp {
+            seconds: 0x8000_0000,
+            fraction: 0,
+        };
+        assert_eq!(timestamp.unix_seconds(), None);
+        let timestamp = NtpTimestamp {
+            seconds: UNIX_OFFSET as u32 - 1,
+            fraction: 0,
+        };
+        assert_eq!(timestamp.unix_seconds(), None);
+        let timestamp = NtpTimestamp {
+            seconds: UNIX_OFFSET as u32,
+            fraction: 0,
+        };
+        assert_eq!(timestamp.unix_seconds(), Some(0));
+    }
+
+    #[test]
+    fn truncated_and_malformed() {
+        let ntp_pdu = NtpPdu::new();
+        assert!(matches!(
+            NtpParser::parse(&ntp_pdu.as_bytes()[..47]),
+            Err(Error::Truncated)
+        ));
+        let mut ntp_pdu = NtpPdu::new();
+        ntp_pdu.version(2);
+        assert!(matches!(
+            NtpParser::parse(ntp_pdu.as_bytes()),
+            Err(Error::Malformed)
+        ));
+    }
+}
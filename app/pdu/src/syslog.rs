@@ -0,0 +1,594 @@
+use crate::{util, Error, Result};
+
+#[allow(non_snake_case)]
+pub mod SyslogFacility {
+    pub const KERN: u8 = 0;
+    pub const USER: u8 = 1;
+    pub const MAIL: u8 = 2;
+    pub const DAEMON: u8 = 3;
+    pub const AUTH: u8 = 4;
+    pub const SYSLOG: u8 = 5;
+    pub const LPR: u8 = 6;
+    pub const NEWS: u8 = 7;
+    pub const UUCP: u8 = 8;
+    pub const CRON: u8 = 9;
+    pub const AUTHPRIV: u8 = 10;
+    pub const FTP: u8 = 11;
+    pub const NTP: u8 = 12;
+    pub const AUDIT: u8 = 13;
+    pub const ALERT: u8 = 14;
+    pub const CLOCK: u8 = 15;
+    pub const LOCAL0: u8 = 16;
+    pub const LOCAL1: u8 = 17;
+    pub const LOCAL2: u8 = 18;
+    pub const LOCAL3: u8 = 19;
+    pub const LOCAL4: u8 = 20;
+    pub const LOCAL5: u8 = 21;
+    pub const LOCAL6: u8 = 22;
+    pub const LOCAL7: u8 = 23;
+}
+
+#[allow(non_snake_case)]
+pub mod SyslogSeverity {
+    pub const EMERGENCY: u8 = 0;
+    pub const ALERT: u8 = 1;
+    pub const CRITICAL: u8 = 2;
+    pub const ERROR: u8 = 3;
+    pub const WARNING: u8 = 4;
+    pub const NOTICE: u8 = 5;
+    pub const INFORMATIONAL: u8 = 6;
+    pub const DEBUG: u8 = 7;
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SyslogTimestamp {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub microsecond: Option<u32>,
+}
+
+impl SyslogTimestamp {
+    pub fn from_unix(seconds: u64, nanos: u32) -> Option<Self> {
+        // Whole seconds in nanos are carried over, as for NtpTimestamp
+        let seconds = seconds.checked_add((nanos / 1_000_000_000) as u64)?;
+        let nanos = nanos % 1_000_000_000;
+        if seconds > 253_402_300_799 {
+            // Past 9999-12-31T23:59:59Z, which four year digits cannot show
+            return None;
+        }
+        // Civil date from a day count (Howard Hinnant's days_from_civil, inverted)
+        let days = seconds / 86400;
+        let time = seconds % 86400;
+        let z = days + 719468;
+        let era = z / 146097;
+        let doe = z % 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as u64;
+        Some(SyslogTimestamp {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time / 60 % 60) as u8,
+            second: (time % 60) as u8,
+            microsecond: Some(nanos / 1000),
+        })
+    }
+
+    fn is_valid(&self) -> bool {
+        self.year <= 9999
+            && (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            // Leap seconds are not representable (RFC 5424, Section 6.2.3)
+            && self.second < 60
+            && self.microsecond.is_none_or(|microsecond| microsecond < 1_000_000)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct SyslogHeader<'a> {
+    pub facility: u8,
+    pub severity: u8,
+    pub timestamp: Option<SyslogTimestamp>,
+    pub hostname: Option<&'a str>,
+    pub app_name: Option<&'a str>,
+    pub proc_id: Option<&'a str>,
+    pub msg_id: Option<&'a str>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum SyslogFormat {
+    Rfc5424,
+    Rfc3164,
+}
+
+pub struct SyslogPdu {
+    buffer: [u8; 1472],
+    inner_size: usize,
+    format: SyslogFormat,
+    has_structured_data: bool,
+    has_message: bool,
+}
+
+impl SyslogPdu {
+    pub fn new() -> Self {
+        SyslogPdu {
+            buffer: [0u8; 1472],
+            inner_size: 0,
+            format: SyslogFormat::Rfc5424,
+            has_structured_data: false,
+            has_message: false,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..self.inner_size]
+    }
+
+    pub fn rfc5424(&mut self, header: &SyslogHeader) -> Result<()> {
+        self.start(SyslogFormat::Rfc5424, header)?;
+        self.push("1 ".as_bytes())?;
+        match header.timestamp {
+            Some(timestamp) => {
+                self.push_number(timestamp.year as u32, 4)?;
+                self.push("-".as_bytes())?;
+                self.push_number(timestamp.month as u32, 2)?;
+                self.push("-".as_bytes())?;
+                self.push_number(timestamp.day as u32, 2)?;
+                self.push("T".as_bytes())?;
+                self.push_time(&timestamp)?;
+                if let Some(microsecond) = timestamp.microsecond {
+                    self.push(".".as_bytes())?;
+                    self.push_number(microsecond, 6)?;
+                }
+                self.push("Z".as_bytes())?;
+            }
+            None => self.push("-".as_bytes())?,
+        }
+        // Field lengths from the ABNF in RFC 5424, Section 6
+        for (value, max_len) in [
+            (header.hostname, 255),
+            (header.app_name, 48),
+            (header.proc_id, 128),
+            (header.msg_id, 32),
+        ] {
+            self.push(" ".as_bytes())?;
+            match value {
+                Some(value) if is_header_field(value, max_len) => self.push(value.as_bytes())?,
+                Some(_) => return Err(Error::Malformed),
+                None => self.push("-".as_bytes())?,
+            }
+        }
+        // The structured data is NILVALUE until an element is added
+        self.push(" -".as_bytes())
+    }
+
+    pub fn rfc3164(&mut self, header: &SyslogHeader) -> Result<()> {
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        self.start(SyslogFormat::Rfc3164, header)?;
+        if let Some(timestamp) = header.timestamp {
+            self.push(MONTHS[timestamp.month as usize - 1].as_bytes())?;
+            // The day of the month is padded with a space rather than a zero
+            if timestamp.day < 10 {
+                self.push(" ".as_bytes())?;
+            }
+            self.push(" ".as_bytes())?;
+            self.push_number(timestamp.day as u32, 1)?;
+            self.push(" ".as_bytes())?;
+            self.push_time(&timestamp)?;
+            self.push(" ".as_bytes())?;
+        }
+        if let Some(hostname) = header.hostname {
+            if !is_header_field(hostname, 255) {
+                return Err(Error::Malformed);
+            }
+            self.push(hostname.as_bytes())?;
+            self.push(" ".as_bytes())?;
+        }
+        if let Some(app_name) = header.app_name {
+            // The TAG is at most 32 alphanumeric characters (RFC 3164, Section 4.1.3)
+            if app_name.is_empty()
+                || app_name.len() > 32
+                || !app_name.bytes().all(|b| b.is_ascii_alphanumeric())
+            {
+                return Err(Error::Malformed);
+            }
+            self.push(app_name.as_bytes())?;
+            if let Some(proc_id) = header.proc_id {
+                if !is_header_field(proc_id, 128) {
+                    return Err(Error::Malformed);
+                }
+                self.push("[".as_bytes())?;
+                self.push(proc_id.as_bytes())?;
+                self.push("]".as_bytes())?;
+            }
+            self.push(":".as_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn structured_data(&mut self, id: &str, params: &[(&str, &str)]) -> Result<()> {
+        if self.format != SyslogFormat::Rfc5424 || self.has_message || self.inner_size == 0 {
+            return Err(Error::Malformed);
+        }
+        if !is_sd_name(id) || !params.iter().all(|(name, _)| is_sd_name(name)) {
+            return Err(Error::Malformed);
+        }
+        let inner_size = self.inner_size;
+        if !self.has_structured_data {
+            // Drops the NILVALUE written by rfc5424()
+            self.inner_size -= 1;
+        }
+        let result = self.push_sd_element(id, params);
+        if result.is_err() {
+            self.inner_size = inner_size;
+            if !self.has_structured_data {
+                // The element was written over the NILVALUE
+                self.buffer[inner_size - 1] = b'-';
+            }
+            return result;
+        }
+        self.has_structured_data = true;
+        Ok(())
+    }
+
+    pub fn message(&mut self, value: &str) -> Result<()> {
+        if self.has_message || self.inner_size == 0 {
+            return Err(Error::Malformed);
+        }
+        let inner_size = self.inner_size;
+        // An RFC 3164 header without a TAG already ends with a space, or is just the PRI
+        let separator = match self.buffer[inner_size - 1] {
+            b' ' | b'>' => "",
+            _ => " ",
+        };
+        let result = self
+            .push(separator.as_bytes())
+            .and_then(|_| self.push(value.as_bytes()));
+        if result.is_err() {
+            self.inner_size = inner_size;
+            return result;
+        }
+        self.has_message = true;
+        Ok(())
+    }
+
+    fn start(&mut self, format: SyslogFormat, header: &SyslogHeader) -> Result<()> {
+        if header.facility > SyslogFacility::LOCAL7
+            || header.severity > SyslogSeverity::DEBUG
+            || !header
+                .timestamp
+                .is_none_or(|timestamp| timestamp.is_valid())
+        {
+            return Err(Error::Malformed);
+        }
+        self.inner_size = 0;
+        self.format = format;
+        self.has_structured_data = false;
+        self.has_message = false;
+        self.push("<".as_bytes())?;
+        self.push_number((header.facility * 8 + header.severity) as u32, 1)?;
+        self.push(">".as_bytes())
+    }
+
+    fn push_time(&mut self, timestamp: &SyslogTimestamp) -> Result<()> {
+        self.push_number(timestamp.hour as u32, 2)?;
+        self.push(":".as_bytes())?;
+        self.push_number(timestamp.minute as u32, 2)?;
+        self.push(":".as_bytes())?;
+        self.push_number(timestamp.second as u32, 2)
+    }
+
+    fn push_sd_element(&mut self, id: &str, params: &[(&str, &str)]) -> Result<()> {
+        self.push("[".as_bytes())?;
+        self.push(id.as_bytes())?;
+        for (name, value) in params {
+            self.push(" ".as_bytes())?;
+            self.push(name.as_bytes())?;
+            self.push("=\"".as_bytes())?;
+            let mut rest = *value;
+            // '"', '\' and ']' are escaped in PARAM-VALUE (RFC 5424, Section 6.3.3)
+            while let Some(pos) = rest.find(['"', '\\', ']']) {
+                self.push(&rest.as_bytes()[..pos])?;
+                self.push("\\".as_bytes())?;
+                self.push(&rest.as_bytes()[pos..pos + 1])?;
+                rest = &rest[pos + 1..];
+            }
+            self.push(rest.as_bytes())?;
+            self.push("\"".as_bytes())?;
+        }
+        self.push("]".as_bytes())
+    }
+
+    fn push_number(&mut self, value: u32, width: usize) -> Result<()> {
+        let mut digits = [0u8; 20];
+        let digits = util::u64_to_ascii(value as u64, &mut digits);
+        for _ in digits.len()..width {
+            self.push("0".as_bytes())?;
+        }
+        self.push(digits)
+    }
+
+    fn push(&mut self, value: &[u8]) -> Result<()> {
+        // Legacy messages must not exceed 1024 bytes (RFC 3164, Section 4.1)
+        let max_size = match self.format {
+            SyslogFormat::Rfc5424 => self.buffer.len(),
+            SyslogFormat::Rfc3164 => 1024,
+        };
+        let len = value.len();
+        if self.inner_size + len > max_size {
+            return Err(Error::Oversized);
+        }
+        self.buffer[self.inner_size..self.inner_size + len].copy_from_slice(value);
+        self.inner_size += len;
+        Ok(())
+    }
+}
+
+impl Default for SyslogPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_header_field(value: &str, max_len: usize) -> bool {
+    // PRINTUSASCII, so that the space-separated header stays parseable
+    !value.is_empty() && value.len() <= max_len && value.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn is_sd_name(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 32
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && b != b'=' && b != b']' && b != b'"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(timestamp: Option<SyslogTimestamp>) -> SyslogHeader<'static> {
+        SyslogHeader {
+            facility: SyslogFacility::LOCAL0,
+            severity: SyslogSeverity::NOTICE,
+            timestamp,
+            hostname: Some("nucleo"),
+            app_name: Some("pdu"),
+            proc_id: None,
+            msg_id: Some("ID47"),
+        }
+    }
+
+    #[test]
+    fn timestamp_from_unix() {
+        assert_eq!(
+            SyslogTimestamp::from_unix(1_700_000_000, 123_456_789).unwrap(),
+            SyslogTimestamp {
+                year: 2023,
+                month: 11,
+                day: 14,
+                hour: 22,
+                minute: 13,
+                second: 20,
+                microsecond: Some(123_456),
+            }
+        );
+        let timestamp = SyslogTimestamp::from_unix(0, 0).unwrap();
+        assert_eq!(
+            (timestamp.year, timestamp.month, timestamp.day),
+            (1970, 1, 1)
+        );
+        // A leap day and the day after a century leap year
+        let timestamp = SyslogTimestamp::from_unix(1_709_164_800, 0).unwrap();
+        assert_eq!(
+            (timestamp.year, timestamp.month, timestamp.day),
+            (2024, 2, 29)
+        );
+        let timestamp = SyslogTimestamp::from_unix(951_868_800, 0).unwrap();
+        assert_eq!(
+            (timestamp.year, timestamp.month, timestamp.day),
+            (2000, 3, 1)
+        );
+    }
+
+    #[test]
+    fn rfc5424_message() {
+        let mut syslog_pdu = SyslogPdu::new();
+        syslog_pdu
+            .rfc5424(&header(SyslogTimestamp::from_unix(
+                1_700_000_000,
+                123_456_789,
+            )))
+            .unwrap();
+        assert_eq!(
+            syslog_pdu.as_bytes(),
+            b"<133>1 2023-11-14T22:13:20.123456Z nucleo pdu - ID47 -"
+        );
+        syslog_pdu
+            .structured_data(
+                "exampleSDID@32473",
+                &[("iut", "3"), ("eventSource", "A\"p]p\\")],
+            )
+            .unwrap();
+        syslog_pdu.structured_data("origin", &[]).unwrap();
+        syslog_pdu.message("hello").unwrap();
+        assert_eq!(
+            syslog_pdu.as_bytes(),
+            &b"<133>1 2023-11-14T22:13:20.123456Z nucleo pdu - ID47 [exampleSDID@32473 iut=\"3\" eventSource=\"A\\\"p\\]p\\\\\"][origin] hello"[..]
+        );
+        assert!(matches!(syslog_pdu.message("again"), Err(Error::Malformed)));
+        assert!(matches!(
+            syslog_pdu.structured_data("late", &[]),
+            Err(Error::Malformed)
+        ));
+
+        let mut syslog_pdu = SyslogPdu::new();
+        syslog_pdu
+            .rfc5424(&SyslogHeader {
+                facility: SyslogFacility::KERN,
+                severity: SyslogSeverity::EMERGENCY,
+                timestamp: None,
+                hostname: None,
+                app_name: None,
+                proc_id: None,
+                msg_id: None,
+            })
+            .unwrap();
+        syslog_pdu.message("").unwrap();
+        assert_eq!(syslog_pdu.as_bytes(), b"<0>1 - - - - - - ");
+    }
+
+    #[test]
+    fn rfc3164_message() {
+        let mut timestamp = SyslogTimestamp::from_unix(1_700_000_000, 0).unwrap();
+        timestamp.day = 5;
+        let mut header = header(Some(timestamp));
+        header.proc_id = Some("42");
+        let mut syslog_pdu = SyslogPdu::new();
+        syslog_pdu.rfc3164(&header).unwrap();
+        syslog_pdu.message("hello").unwrap();
+        assert_eq!(
+            syslog_pdu.as_bytes(),
+            b"<133>Nov  5 22:13:20 nucleo pdu[42]: hello"
+        );
+        // Structured data only exists in RFC 5424
+        assert!(matches!(
+            syslog_pdu.structured_data("origin", &[]),
+            Err(Error::Malformed)
+        ));
+        header.app_name = Some("pdu-server");
+        assert!(matches!(syslog_pdu.rfc3164(&header), Err(Error::Malformed)));
+    }
+
+    #[test]
+    fn malformed_headers() {
+        let mut syslog_pdu = SyslogPdu::new();
+        assert!(matches!(syslog_pdu.message("x"), Err(Error::Malformed)));
+        assert!(matches!(
+            syslog_pdu.structured_data("origin", &[]),
+            Err(Error::Malformed)
+        ));
+        let mut timestamp = SyslogTimestamp::from_unix(0, 0).unwrap();
+        timestamp.second = 60;
+        let long_name = core::str::from_utf8(&[b'a'; 49]).unwrap();
+        for header in [
+            SyslogHeader {
+                facility: 24,
+                ..header(None)
+            },
+            SyslogHeader {
+                severity: 8,
+                ..header(None)
+            },
+            header(Some(timestamp)),
+            SyslogHeader {
+                hostname: Some("nu cleo"),
+                ..header(None)
+            },
+            SyslogHeader {
+                app_name: Some(long_name),
+                ..header(None)
+            },
+            SyslogHeader {
+                msg_id: Some(""),
+                ..header(None)
+            },
+        ] {
+            assert!(matches!(syslog_pdu.rfc5424(&header), Err(Error::Malformed)));
+        }
+        syslog_pdu.rfc5424(&header(None)).unwrap();
+        for (id, params) in [("", &[][..]), ("a=b", &[]), ("origin", &[("ip\"", "1")])] {
+            assert!(matches!(
+                syslog_pdu.structured_data(id, params),
+                Err(Error::Malformed)
+            ));
+        }
+    }
+
+    #[test]
+    fn oversized() {
+        let mut syslog_pdu = SyslogPdu::new();
+        syslog_pdu.rfc5424(&header(None)).unwrap();
+        let header_len = syslog_pdu.as_bytes().len();
+        let value = core::str::from_utf8(&[b'a'; 1472]).unwrap();
+        assert!(matches!(
+            syslog_pdu.structured_data("origin", &[("x", value)]),
+            Err(Error::Oversized)
+        ));
+        assert!(matches!(syslog_pdu.message(value), Err(Error::Oversized)));
+        // Both are rolled back, leaving the NILVALUE in place
+        assert_eq!(syslog_pdu.as_bytes().len(), header_len);
+        assert!(syslog_pdu.as_bytes().ends_with(b" -"));
+        syslog_pdu.message(&value[..1024]).unwrap();
+
+        // Legacy messages stop at 1024 bytes
+        syslog_pdu.rfc3164(&header(None)).unwrap();
+        assert!(matches!(
+            syslog_pdu.message(&value[..1024]),
+            Err(Error::Oversized)
+        ));
+        let len = 1024 - 1 - syslog_pdu.as_bytes().len();
+        syslog_pdu.message(&value[..len]).unwrap();
+        assert_eq!(syslog_pdu.as_bytes().len(), 1024);
+    }
+    #[test]
+    fn rfc3164_message_without_tag() {
+        let mut header = header(Some(SyslogTimestamp::from_unix(1_700_000_000, 0).unwrap()));
+        header.app_name = None;
+        let mut syslog_pdu = SyslogPdu::new();
+        syslog_pdu.rfc3164(&header).unwrap();
+        syslog_pdu.message("hello").unwrap();
+        assert_eq!(syslog_pdu.as_bytes(), b"<133>Nov 14 22:13:20 nucleo hello");
+
+        let header = SyslogHeader {
+            timestamp: None,
+            hostname: None,
+            app_name: None,
+            ..header
+        };
+        syslog_pdu.rfc3164(&header).unwrap();
+        syslog_pdu.message("hello").unwrap();
+        assert_eq!(syslog_pdu.as_bytes(), b"<133>hello");
+    }
+    #[test]
+    fn timestamp_from_unix_out_of_range() {
+        let timestamp = SyslogTimestamp::from_unix(253_402_300_799, 999_999_999).unwrap();
+        assert_eq!(
+            timestamp,
+            SyslogTimestamp {
+                year: 9999,
+                month: 12,
+                day: 31,
+                hour: 23,
+                minute: 59,
+                second: 59,
+                microsecond: Some(999_999),
+            }
+        );
+        assert_eq!(SyslogTimestamp::from_unix(253_402_300_800, 0), None);
+        assert_eq!(SyslogTimestamp::from_unix(u64::MAX, 0), None);
+        // Whole seconds in nanos are carried over
+        assert_eq!(
+            SyslogTimestamp::from_unix(10, 1_500_000_000),
+            SyslogTimestamp::from_unix(11, 500_000_000)
+        );
+        assert_eq!(
+            SyslogTimestamp::from_unix(253_402_300_799, 1_000_000_000),
+            None
+        );
+        assert_eq!(SyslogTimestamp::from_unix(u64::MAX, u32::MAX), None);
+    }
+}
@@ -14,9 +14,17 @@ pub mod TcpFlag {
     pub const CWR: u8 = 128;
 }
 
+#[allow(non_snake_case)]
+pub mod TcpPort {
+    pub const HTTP: u16 = 80;
+    pub const MODBUS: u16 = 502;
+    pub const MQTT: u16 = 1883;
+}
+
 #[derive(Copy, Clone)]
 pub enum Tcp<'a> {
     Raw(&'a [u8]),
+    Modbus(super::ModbusParser<'a>),
 }
 
 pub struct TcpPdu {
@@ -152,6 +160,16 @@ impl TcpPdu {
                 &self.buffer[0..=15],
                 &self.buffer[18..self.computed_data_offset() + self.inner_size],
             ]),
+            crate::IpPseudoHeader::Ipv6(ipv6) => util::checksum([
+                ipv6.source_address.as_ref(),
+                ipv6.destination_address.as_ref(),
+                ((self.computed_data_offset() + self.inner_size) as u32)
+                    .to_be_bytes()
+                    .as_ref(),
+                [0x00, 0x00, 0x00, ipv6.next_header].as_ref(),
+                &self.buffer[0..=15],
+                &self.buffer[18..self.computed_data_offset() + self.inner_size],
+            ]),
         };
         self.checksum(csum);
     }
@@ -160,11 +178,39 @@ impl TcpPdu {
         self.buffer[18..=19].copy_from_slice(&value.to_be_bytes());
     }
 
-    pub fn options(&mut self, value: &[u8]) {
-        let len = value.len();
-        let data_offset = (20 + len + (20 + len) % 4) / 4;
-        self.data_offset(data_offset as u8);
-        self.buffer[20..20 + len].copy_from_slice(value);
+    pub fn options(&mut self, value: &[TcpOption]) -> Result<()> {
+        let mut options = [0u8; 40];
+        let mut len = 0;
+        for option in value {
+            let option_len = option.len()?;
+            if !matches!(option, TcpOption::Raw { .. } | TcpOption::NoOp) {
+                // NOPs in front so that every option ends on a 32-bit boundary
+                let nops = (4 - (len + option_len) % 4) % 4;
+                if len + nops > 40 {
+                    return Err(Error::Oversized);
+                }
+                options[len..len + nops].fill(1);
+                len += nops;
+            }
+            if len + option_len > 40 {
+                return Err(Error::Oversized);
+            }
+            option.write(&mut options[len..len + option_len]);
+            len += option_len;
+        }
+        // Padded with End of Option List up to a 32-bit boundary
+        let data_offset = 20 + len.div_ceil(4) * 4;
+        let old_data_offset = self.computed_data_offset();
+        if data_offset + self.inner_size > 1480 {
+            return Err(Error::Oversized);
+        }
+        self.buffer.copy_within(
+            old_data_offset..old_data_offset + self.inner_size,
+            data_offset,
+        );
+        self.buffer[20..data_offset].copy_from_slice(&options[..data_offset - 20]);
+        self.data_offset((data_offset / 4) as u8);
+        Ok(())
     }
 
     pub fn inner(&mut self, value: &[u8]) -> Result<()> {
@@ -199,7 +245,11 @@ impl<'a> TcpParser<'a> {
 
     pub fn into_inner(self) -> Result<Tcp<'a>> {
         let rest = &self.buffer[self.computed_data_offset()..];
-        Ok(Tcp::Raw(rest))
+        Ok(match self.destination_port() {
+            // Segments that are not a whole ADU, e.g. during a handshake, stay raw
+            TcpPort::MODBUS => super::ModbusParser::parse(rest).map_or(Tcp::Raw(rest), Tcp::Modbus),
+            _ => Tcp::Raw(rest),
+        })
     }
 
     pub fn source_port(&'a self) -> u16 {
@@ -286,6 +336,14 @@ impl<'a> TcpParser<'a> {
                 &self.buffer[0..=15],
                 &self.buffer[18..],
             ]),
+            crate::Ip::Ipv6(ipv6) => util::checksum([
+                ipv6.source_address().as_ref(),
+                ipv6.destination_address().as_ref(),
+                (self.buffer.len() as u32).to_be_bytes().as_ref(),
+                [0x00, 0x00, 0x00, crate::IpProto::TCP].as_ref(),
+                &self.buffer[0..=15],
+                &self.buffer[18..],
+            ]),
         }
     }
 
@@ -313,6 +371,61 @@ pub enum TcpOption<'a> {
     Timestamp { val: u32, ecr: u32 },
 }
 
+impl<'a> TcpOption<'a> {
+    fn len(&self) -> Result<usize> {
+        Ok(match self {
+            TcpOption::Raw { data, .. } => {
+                if data.is_empty() {
+                    return Err(Error::Malformed);
+                }
+                data.len()
+            }
+            TcpOption::NoOp => 1,
+            TcpOption::Mss { .. } => 4,
+            TcpOption::WindowScale { .. } => 3,
+            TcpOption::SackPermitted => 2,
+            TcpOption::Sack { blocks } => {
+                let count = blocks.iter().take_while(|block| block.is_some()).count();
+                if count == 0 || blocks[count..].iter().any(|block| block.is_some()) {
+                    return Err(Error::Malformed);
+                }
+                2 + count * 8
+            }
+            TcpOption::Timestamp { .. } => 10,
+        })
+    }
+
+    fn write(&self, option: &mut [u8]) {
+        let len = option.len();
+        match self {
+            TcpOption::Raw { option: kind, data } => {
+                option.copy_from_slice(data);
+                option[0] = *kind;
+            }
+            TcpOption::NoOp => option[0] = 1,
+            TcpOption::Mss { size } => {
+                option[0..=1].copy_from_slice(&[2, 4]);
+                option[2..=3].copy_from_slice(&size.to_be_bytes());
+            }
+            TcpOption::WindowScale { shift } => option.copy_from_slice(&[3, 3, *shift]),
+            TcpOption::SackPermitted => option.copy_from_slice(&[4, 2]),
+            TcpOption::Sack { blocks } => {
+                option[0..=1].copy_from_slice(&[5, len as u8]);
+                for (i, (left, right)) in blocks.iter().flatten().enumerate() {
+                    let pos = 2 + i * 8;
+                    option[pos..pos + 4].copy_from_slice(&left.to_be_bytes());
+                    option[pos + 4..pos + 8].copy_from_slice(&right.to_be_bytes());
+                }
+            }
+            TcpOption::Timestamp { val, ecr } => {
+                option[0..=1].copy_from_slice(&[8, 10]);
+                option[2..=5].copy_from_slice(&val.to_be_bytes());
+                option[6..=9].copy_from_slice(&ecr.to_be_bytes());
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct TcpOptionIterator<'a> {
     buffer: &'a [u8],
@@ -469,3 +582,177 @@ pub enum TcpState {
     LastAck,
     TimeWait,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syn_options_round_trip() {
+        let mut tcp_pdu = TcpPdu::new();
+        tcp_pdu.flags(TcpFlag::SYN);
+        tcp_pdu.inner(b"data").unwrap();
+        tcp_pdu
+            .options(&[
+                TcpOption::Mss { size: 1460 },
+                TcpOption::SackPermitted,
+                TcpOption::Timestamp { val: 1, ecr: 0 },
+                TcpOption::WindowScale { shift: 7 },
+            ])
+            .unwrap();
+        let bytes = tcp_pdu.as_bytes();
+        // Each option is preceded by enough NOPs to end on a 32-bit boundary
+        assert_eq!(bytes[20..28], [2, 4, 0x05, 0xB4, 1, 1, 4, 2]);
+        assert_eq!(bytes[28..32], [1, 1, 8, 10]);
+        assert_eq!(bytes[40..44], [1, 3, 3, 7]);
+        let tcp = TcpParser::parse(bytes).unwrap();
+        assert_eq!(tcp.data_offset(), 11);
+        let mut options = tcp
+            .options()
+            .filter(|option| !matches!(option, TcpOption::NoOp));
+        assert!(matches!(
+            options.next(),
+            Some(TcpOption::Mss { size: 1460 })
+        ));
+        assert!(matches!(options.next(), Some(TcpOption::SackPermitted)));
+        assert!(matches!(
+            options.next(),
+            Some(TcpOption::Timestamp { val: 1, ecr: 0 })
+        ));
+        assert!(matches!(
+            options.next(),
+            Some(TcpOption::WindowScale { shift: 7 })
+        ));
+        assert!(options.next().is_none());
+        assert_eq!(bytes[44..], *b"data");
+
+        // Replacing the options moves the payload back
+        tcp_pdu.options(&[TcpOption::Mss { size: 536 }]).unwrap();
+        let tcp = TcpParser::parse(tcp_pdu.as_bytes()).unwrap();
+        assert_eq!(tcp.computed_data_offset(), 24);
+        assert_eq!(tcp_pdu.as_bytes()[24..], *b"data");
+    }
+
+    #[test]
+    fn sack_round_trip() {
+        let blocks = [Some((1000, 2000)), Some((3000, 4000)), None, None];
+        let mut tcp_pdu = TcpPdu::new();
+        tcp_pdu.options(&[TcpOption::Sack { blocks }]).unwrap();
+        let tcp = TcpParser::parse(tcp_pdu.as_bytes()).unwrap();
+        assert_eq!(tcp.computed_data_offset(), 40);
+        let mut options = tcp.options();
+        assert!(matches!(options.next(), Some(TcpOption::NoOp)));
+        assert!(matches!(options.next(), Some(TcpOption::NoOp)));
+        let Some(TcpOption::Sack { blocks: parsed }) = options.next() else {
+            panic!("expected SACK");
+        };
+        assert_eq!(parsed, blocks);
+        assert!(options.next().is_none());
+    }
+
+    #[test]
+    fn builder_rejects_bad_options() {
+        let mut tcp_pdu = TcpPdu::new();
+        assert!(matches!(
+            tcp_pdu.options(&[TcpOption::Sack { blocks: [None; 4] }]),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            tcp_pdu.options(&[TcpOption::Sack {
+                blocks: [Some((1, 2)), None, Some((3, 4)), None],
+            }]),
+            Err(Error::Malformed)
+        ));
+        assert!(matches!(
+            tcp_pdu.options(&[TcpOption::Raw {
+                option: 30,
+                data: &[],
+            }]),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn oversized_options() {
+        let mut tcp_pdu = TcpPdu::new();
+        assert!(matches!(
+            tcp_pdu.options(&[
+                TcpOption::Timestamp { val: 1, ecr: 2 },
+                TcpOption::Sack {
+                    blocks: [Some((1, 2)); 4],
+                },
+            ]),
+            Err(Error::Oversized)
+        ));
+        tcp_pdu.inner(&[0; 1460]).unwrap();
+        assert!(matches!(
+            tcp_pdu.options(&[TcpOption::Mss { size: 1460 }]),
+            Err(Error::Oversized)
+        ));
+        assert_eq!(tcp_pdu.as_bytes().len(), 1480);
+    }
+
+    #[test]
+    fn malformed_options() {
+        let mut tcp_pdu = TcpPdu::new();
+        tcp_pdu
+            .options(&[TcpOption::Raw {
+                option: 30,
+                data: &[0, 4, 0xAA, 0xBB],
+            }])
+            .unwrap();
+        let tcp = TcpParser::parse(tcp_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            tcp.options().next(),
+            Some(TcpOption::Raw {
+                option: 30,
+                data: &[30, 4, 0xAA, 0xBB],
+            })
+        ));
+        // A length below two ends the iteration, as does End of Option List
+        let mut bytes = [0u8; 24];
+        bytes.copy_from_slice(tcp_pdu.as_bytes());
+        bytes[20..24].copy_from_slice(&[1, 2, 1, 0]);
+        let tcp = TcpParser::parse(&bytes).unwrap();
+        let mut options = tcp.options();
+        assert!(matches!(options.next(), Some(TcpOption::NoOp)));
+        assert!(options.next().is_none());
+        bytes[20..24].copy_from_slice(&[1, 0, 2, 4]);
+        let tcp = TcpParser::parse(&bytes).unwrap();
+        assert_eq!(tcp.options().count(), 1);
+        // The options run past the end of the buffer
+        assert!(matches!(
+            TcpParser::parse(&bytes[..22]),
+            Err(Error::Truncated)
+        ));
+    }
+    #[test]
+    fn modbus_dispatch() {
+        let mut modbus_pdu = crate::ModbusPdu::new();
+        modbus_pdu.transaction_id(7);
+        modbus_pdu.read_holding_registers(0x0010, 2).unwrap();
+
+        let mut tcp_pdu = TcpPdu::new();
+        tcp_pdu.destination_port(TcpPort::MODBUS);
+        tcp_pdu.inner(modbus_pdu.as_bytes()).unwrap();
+        let tcp = TcpParser::parse(tcp_pdu.as_bytes()).unwrap();
+        let Ok(Tcp::Modbus(modbus)) = tcp.inner() else {
+            panic!("expected Modbus");
+        };
+        assert_eq!(modbus.transaction_id(), 7);
+
+        // Falls back to the raw payload when it does not parse
+        tcp_pdu.inner(&modbus_pdu.as_bytes()[..6]).unwrap();
+        let tcp = TcpParser::parse(tcp_pdu.as_bytes()).unwrap();
+        let Ok(Tcp::Raw(payload)) = tcp.inner() else {
+            panic!("expected a raw payload");
+        };
+        assert_eq!(payload, &modbus_pdu.as_bytes()[..6]);
+
+        // Other ports are never decoded
+        tcp_pdu.destination_port(40000);
+        tcp_pdu.inner(modbus_pdu.as_bytes()).unwrap();
+        let tcp = TcpParser::parse(tcp_pdu.as_bytes()).unwrap();
+        assert!(matches!(tcp.inner(), Ok(Tcp::Raw(_))));
+    }
+}
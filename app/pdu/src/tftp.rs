@@ -0,0 +1,469 @@
+use core::convert::TryInto;
+
+use crate::{util, Error, Result};
+
+#[allow(non_snake_case)]
+pub mod TftpOpcode {
+    pub const RRQ: u16 = 1;
+    pub const WRQ: u16 = 2;
+    pub const DATA: u16 = 3;
+    pub const ACK: u16 = 4;
+    pub const ERROR: u16 = 5;
+    pub const OACK: u16 = 6;
+}
+
+#[allow(non_snake_case)]
+pub mod TftpErrorCode {
+    pub const NOT_DEFINED: u16 = 0;
+    pub const FILE_NOT_FOUND: u16 = 1;
+    pub const ACCESS_VIOLATION: u16 = 2;
+    pub const DISK_FULL: u16 = 3;
+    pub const ILLEGAL_OPERATION: u16 = 4;
+    pub const UNKNOWN_TRANSFER_ID: u16 = 5;
+    pub const FILE_EXISTS: u16 = 6;
+    pub const NO_SUCH_USER: u16 = 7;
+    pub const OPTION_NEGOTIATION: u16 = 8;
+}
+
+#[allow(non_snake_case)]
+pub mod TftpMode {
+    pub const NETASCII: &str = "netascii";
+    pub const OCTET: &str = "octet";
+    pub const MAIL: &str = "mail";
+}
+
+#[derive(Copy, Clone)]
+pub enum Tftp<'a> {
+    ReadRequest {
+        filename: &'a str,
+        mode: &'a str,
+        options: TftpOptionIterator<'a>,
+    },
+    WriteRequest {
+        filename: &'a str,
+        mode: &'a str,
+        options: TftpOptionIterator<'a>,
+    },
+    Data {
+        block: u16,
+        data: &'a [u8],
+    },
+    Ack {
+        block: u16,
+    },
+    Error {
+        code: u16,
+        message: &'a str,
+    },
+    OptionAck {
+        options: TftpOptionIterator<'a>,
+    },
+}
+
+pub struct TftpPdu {
+    buffer: [u8; 1472],
+    inner_size: usize,
+}
+
+impl TftpPdu {
+    pub fn new() -> Self {
+        TftpPdu {
+            buffer: [0u8; 1472],
+            inner_size: 0,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..2 + self.inner_size]
+    }
+
+    pub fn opcode(&mut self, value: u16) {
+        self.buffer[0..=1].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn read_request(&mut self, filename: &str, mode: &str) -> Result<()> {
+        self.opcode(TftpOpcode::RRQ);
+        self.request(filename, mode)
+    }
+
+    pub fn write_request(&mut self, filename: &str, mode: &str) -> Result<()> {
+        self.opcode(TftpOpcode::WRQ);
+        self.request(filename, mode)
+    }
+
+    fn request(&mut self, filename: &str, mode: &str) -> Result<()> {
+        self.inner_size = 0;
+        self.write_string(filename.as_bytes())?;
+        self.write_string(mode.as_bytes())
+    }
+
+    pub fn option_ack(&mut self) {
+        self.opcode(TftpOpcode::OACK);
+        self.inner_size = 0;
+    }
+
+    pub fn option(&mut self, value: &TftpOption) -> Result<()> {
+        let mut digits = [0u8; 20];
+        let (name, value) = match value {
+            TftpOption::Blksize(blksize) => (
+                "blksize".as_bytes(),
+                util::u64_to_ascii(*blksize as u64, &mut digits),
+            ),
+            TftpOption::Tsize(tsize) => {
+                ("tsize".as_bytes(), util::u64_to_ascii(*tsize, &mut digits))
+            }
+            TftpOption::Timeout(timeout) => (
+                "timeout".as_bytes(),
+                util::u64_to_ascii(*timeout as u64, &mut digits),
+            ),
+            TftpOption::Raw { name, value } => (name.as_bytes(), value.as_bytes()),
+        };
+        let inner_size = self.inner_size;
+        let result = self
+            .write_string(name)
+            .and_then(|_| self.write_string(value));
+        if result.is_err() {
+            self.inner_size = inner_size;
+        }
+        result
+    }
+
+    pub fn data(&mut self, block: u16, value: &[u8]) -> Result<()> {
+        let len = value.len();
+        if len > 1468 {
+            return Err(Error::Oversized);
+        }
+        self.opcode(TftpOpcode::DATA);
+        self.buffer[2..=3].copy_from_slice(&block.to_be_bytes());
+        self.buffer[4..4 + len].copy_from_slice(value);
+        self.inner_size = 2 + len;
+        Ok(())
+    }
+
+    pub fn ack(&mut self, block: u16) {
+        self.opcode(TftpOpcode::ACK);
+        self.buffer[2..=3].copy_from_slice(&block.to_be_bytes());
+        self.inner_size = 2;
+    }
+
+    pub fn error(&mut self, code: u16, message: &str) -> Result<()> {
+        self.opcode(TftpOpcode::ERROR);
+        self.buffer[2..=3].copy_from_slice(&code.to_be_bytes());
+        self.inner_size = 2;
+        self.write_string(message.as_bytes())
+    }
+
+    fn write_string(&mut self, value: &[u8]) -> Result<()> {
+        let pos = 2 + self.inner_size;
+        let len = value.len();
+        if value.contains(&0) {
+            return Err(Error::Malformed);
+        }
+        if pos + len + 1 > 1472 {
+            return Err(Error::Oversized);
+        }
+        self.buffer[pos..pos + len].copy_from_slice(value);
+        self.buffer[pos + len] = 0;
+        self.inner_size += len + 1;
+        Ok(())
+    }
+}
+
+impl Default for TftpPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct TftpParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> TftpParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 4 {
+            return Err(Error::Truncated);
+        }
+        Ok(TftpParser { buffer })
+    }
+
+    pub fn inner(&'a self) -> Result<Tftp<'a>> {
+        (*self).into_inner()
+    }
+
+    pub fn into_inner(self) -> Result<Tftp<'a>> {
+        let buffer = self.buffer;
+        Ok(match self.opcode() {
+            TftpOpcode::RRQ | TftpOpcode::WRQ => {
+                let (filename, rest) = split_string(&buffer[2..])?;
+                let (mode, rest) = split_string(rest)?;
+                let options = TftpOptionIterator::parse(rest)?;
+                if self.opcode() == TftpOpcode::RRQ {
+                    Tftp::ReadRequest {
+                        filename,
+                        mode,
+                        options,
+                    }
+                } else {
+                    Tftp::WriteRequest {
+                        filename,
+                        mode,
+                        options,
+                    }
+                }
+            }
+            TftpOpcode::DATA => Tftp::Data {
+                block: u16::from_be_bytes(buffer[2..=3].try_into().unwrap()),
+                data: &buffer[4..],
+            },
+            TftpOpcode::ACK => Tftp::Ack {
+                block: u16::from_be_bytes(buffer[2..=3].try_into().unwrap()),
+            },
+            TftpOpcode::ERROR => Tftp::Error {
+                code: u16::from_be_bytes(buffer[2..=3].try_into().unwrap()),
+                message: split_string(&buffer[4..])?.0,
+            },
+            TftpOpcode::OACK => Tftp::OptionAck {
+                options: TftpOptionIterator::parse(&buffer[2..])?,
+            },
+            _ => return Err(Error::Malformed),
+        })
+    }
+
+    pub fn opcode(&'a self) -> u16 {
+        u16::from_be_bytes(self.buffer[0..=1].try_into().unwrap())
+    }
+}
+
+fn split_string(buffer: &[u8]) -> Result<(&str, &[u8])> {
+    let end = buffer
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(Error::Truncated)?;
+    let string = core::str::from_utf8(&buffer[..end]).map_err(|_| Error::Malformed)?;
+    Ok((string, &buffer[end + 1..]))
+}
+
+#[derive(Copy, Clone)]
+pub enum TftpOption<'a> {
+    Raw { name: &'a str, value: &'a str },
+    Blksize(u16),
+    Tsize(u64),
+    Timeout(u8),
+}
+
+#[derive(Copy, Clone)]
+pub struct TftpOptionIterator<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> TftpOptionIterator<'a> {
+    fn parse(buffer: &'a [u8]) -> Result<Self> {
+        // Every option must be a complete name/value pair of NUL-terminated strings
+        let mut rest = buffer;
+        while !rest.is_empty() {
+            let (_, value) = split_string(rest)?;
+            rest = split_string(value)?.1;
+        }
+        Ok(TftpOptionIterator { buffer })
+    }
+}
+
+impl<'a> Iterator for TftpOptionIterator<'a> {
+    type Item = TftpOption<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (name, rest) = split_string(self.buffer).ok()?;
+        let (value, rest) = split_string(rest).ok()?;
+        self.buffer = rest;
+        // Option names are case-insensitive (RFC 2347)
+        Some(if name.eq_ignore_ascii_case("blksize") {
+            match value.parse() {
+                Ok(blksize @ 8..=65464) => TftpOption::Blksize(blksize),
+                _ => TftpOption::Raw { name, value },
+            }
+        } else if name.eq_ignore_ascii_case("tsize") {
+            match value.parse() {
+                Ok(tsize) => TftpOption::Tsize(tsize),
+                _ => TftpOption::Raw { name, value },
+            }
+        } else if name.eq_ignore_ascii_case("timeout") {
+            match value.parse() {
+                Ok(timeout @ 1..=255) => TftpOption::Timeout(timeout),
+                _ => TftpOption::Raw { name, value },
+            }
+        } else {
+            TftpOption::Raw { name, value }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_request_round_trip() {
+        let mut tftp_pdu = TftpPdu::new();
+        tftp_pdu
+            .read_request("firmware.bin", TftpMode::OCTET)
+            .unwrap();
+        tftp_pdu.option(&TftpOption::Blksize(1428)).unwrap();
+        tftp_pdu.option(&TftpOption::Tsize(0)).unwrap();
+        tftp_pdu.option(&TftpOption::Timeout(5)).unwrap();
+        tftp_pdu
+            .option(&TftpOption::Raw {
+                name: "windowsize",
+                value: "4",
+            })
+            .unwrap();
+        assert_eq!(
+            tftp_pdu.as_bytes(),
+            b"\x00\x01firmware.bin\0octet\0blksize\x001428\0tsize\x000\0timeout\x005\0windowsize\x004\0"
+        );
+        let mut udp_pdu = crate::UdpPdu::new();
+        udp_pdu.source_port(40000);
+        udp_pdu.destination_port(crate::UdpPort::TFTP);
+        udp_pdu.inner(tftp_pdu.as_bytes()).unwrap();
+        let udp = crate::UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(crate::Udp::Tftp(tftp)) = udp.inner() else {
+            panic!("expected TFTP");
+        };
+        let Ok(Tftp::ReadRequest {
+            filename: "firmware.bin",
+            mode: TftpMode::OCTET,
+            mut options,
+        }) = tftp.inner()
+        else {
+            panic!("expected RRQ");
+        };
+        assert!(matches!(options.next(), Some(TftpOption::Blksize(1428))));
+        assert!(matches!(options.next(), Some(TftpOption::Tsize(0))));
+        assert!(matches!(options.next(), Some(TftpOption::Timeout(5))));
+        assert!(matches!(
+            options.next(),
+            Some(TftpOption::Raw {
+                name: "windowsize",
+                value: "4",
+            })
+        ));
+        assert!(options.next().is_none());
+    }
+
+    #[test]
+    fn transfer_round_trip() {
+        let mut tftp_pdu = TftpPdu::new();
+        tftp_pdu
+            .write_request("log.txt", TftpMode::NETASCII)
+            .unwrap();
+        let tftp = TftpParser::parse(tftp_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            tftp.inner(),
+            Ok(Tftp::WriteRequest {
+                filename: "log.txt",
+                mode: TftpMode::NETASCII,
+                ..
+            })
+        ));
+        tftp_pdu.option_ack();
+        tftp_pdu.option(&TftpOption::Blksize(512)).unwrap();
+        let tftp = TftpParser::parse(tftp_pdu.as_bytes()).unwrap();
+        let Ok(Tftp::OptionAck { mut options }) = tftp.inner() else {
+            panic!("expected OACK");
+        };
+        assert!(matches!(options.next(), Some(TftpOption::Blksize(512))));
+        tftp_pdu.data(1, b"hello").unwrap();
+        let tftp = TftpParser::parse(tftp_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            tftp.inner(),
+            Ok(Tftp::Data {
+                block: 1,
+                data: b"hello",
+            })
+        ));
+        tftp_pdu.ack(1);
+        let tftp = TftpParser::parse(tftp_pdu.as_bytes()).unwrap();
+        assert!(matches!(tftp.inner(), Ok(Tftp::Ack { block: 1 })));
+        tftp_pdu
+            .error(TftpErrorCode::DISK_FULL, "Disk full")
+            .unwrap();
+        let tftp = TftpParser::parse(tftp_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            tftp.inner(),
+            Ok(Tftp::Error {
+                code: TftpErrorCode::DISK_FULL,
+                message: "Disk full",
+            })
+        ));
+    }
+
+    #[test]
+    fn out_of_range_options_are_raw() {
+        let tftp = TftpParser::parse(b"\x00\x06blksize\x0065465\0timeout\x000\0").unwrap();
+        let Ok(Tftp::OptionAck { mut options }) = tftp.inner() else {
+            panic!("expected OACK");
+        };
+        assert!(matches!(
+            options.next(),
+            Some(TftpOption::Raw {
+                name: "blksize",
+                value: "65465",
+            })
+        ));
+        assert!(matches!(
+            options.next(),
+            Some(TftpOption::Raw {
+                name: "timeout",
+                value: "0",
+            })
+        ));
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(matches!(
+            TftpParser::parse(b"\x00\x04\x00"),
+            Err(Error::Truncated)
+        ));
+        let tftp = TftpParser::parse(b"\x00\x01file\0octet").unwrap();
+        assert!(matches!(tftp.inner(), Err(Error::Truncated)));
+        // An option name without a value
+        let tftp = TftpParser::parse(b"\x00\x01file\0octet\0blksize\0").unwrap();
+        assert!(matches!(tftp.inner(), Err(Error::Truncated)));
+        let tftp = TftpParser::parse(b"\x00\x05\x00\x01File not found").unwrap();
+        assert!(matches!(tftp.inner(), Err(Error::Truncated)));
+    }
+
+    #[test]
+    fn malformed() {
+        let tftp = TftpParser::parse(b"\x00\x07\x00\x00").unwrap();
+        assert!(matches!(tftp.inner(), Err(Error::Malformed)));
+        let tftp = TftpParser::parse(b"\x00\x01\xFF\0octet\0").unwrap();
+        assert!(matches!(tftp.inner(), Err(Error::Malformed)));
+        let mut tftp_pdu = TftpPdu::new();
+        assert!(matches!(
+            tftp_pdu.read_request("a\0b", TftpMode::OCTET),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn oversized() {
+        let mut tftp_pdu = TftpPdu::new();
+        assert!(tftp_pdu.data(1, &[0u8; 1468]).is_ok());
+        assert!(matches!(
+            tftp_pdu.data(1, &[0u8; 1469]),
+            Err(Error::Oversized)
+        ));
+        tftp_pdu.read_request("file", TftpMode::OCTET).unwrap();
+        let len = tftp_pdu.as_bytes().len();
+        let value = core::str::from_utf8(&[b'a'; 1460]).unwrap();
+        assert!(matches!(
+            tftp_pdu.option(&TftpOption::Raw { name: "x", value }),
+            Err(Error::Oversized)
+        ));
+        // The partially written option is rolled back
+        assert_eq!(tftp_pdu.as_bytes().len(), len);
+    }
+}
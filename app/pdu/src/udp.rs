@@ -2,9 +2,29 @@ use core::convert::TryInto;
 
 use crate::{util, Error, Result};
 
+#[allow(non_snake_case)]
+pub mod UdpPort {
+    pub const ECHO: u16 = 7;
+    pub const DISCARD: u16 = 9;
+    pub const DNS: u16 = 53;
+    pub const DHCP_SERVER: u16 = 67;
+    pub const DHCP_CLIENT: u16 = 68;
+    pub const TFTP: u16 = 69;
+    pub const NTP: u16 = 123;
+    pub const COAP: u16 = 5683;
+    pub const SYSLOG: u16 = 514;
+    pub const MDNS: u16 = 5353;
+}
+
 #[derive(Copy, Clone)]
 pub enum Udp<'a> {
     Raw(&'a [u8]),
+    Dhcp(super::DhcpParser<'a>),
+    Dns(super::DnsParser<'a>),
+    Ntp(super::NtpParser<'a>),
+    Tftp(super::TftpParser<'a>),
+    Coap(super::CoapParser<'a>),
+    Wol(super::WolParser<'a>),
 }
 
 pub struct UdpPdu {
@@ -54,6 +74,15 @@ impl UdpPdu {
                 &self.buffer[0..=5],
                 &self.buffer[8..8 + self.inner_size],
             ]),
+            crate::IpPseudoHeader::Ipv6(ipv6) => util::checksum([
+                ipv6.source_address.as_ref(),
+                ipv6.destination_address.as_ref(),
+                [0x00, 0x00].as_ref(),
+                &self.buffer[4..=5],
+                [0x00, 0x00, 0x00, ipv6.next_header].as_ref(),
+                &self.buffer[0..=5],
+                &self.buffer[8..8 + self.inner_size],
+            ]),
         };
         if csum == 0 {
             csum = 0xFFFF
@@ -92,8 +121,31 @@ impl<'a> UdpParser<'a> {
     }
 
     pub fn into_inner(self) -> Result<Udp<'a>> {
-        let rest = &self.buffer[8..self.length() as usize];
-        Ok(Udp::Raw(rest))
+        let rest = self.payload()?;
+        Ok(match self.destination_port() {
+            UdpPort::DHCP_SERVER | UdpPort::DHCP_CLIENT => {
+                super::DhcpParser::parse(rest).map_or(Udp::Raw(rest), Udp::Dhcp)
+            }
+            UdpPort::DNS | UdpPort::MDNS => {
+                super::DnsParser::parse(rest).map_or(Udp::Raw(rest), Udp::Dns)
+            }
+            UdpPort::NTP => super::NtpParser::parse(rest).map_or(Udp::Raw(rest), Udp::Ntp),
+            // Only requests use the well-known port; transfers continue on ephemeral ports
+            UdpPort::TFTP => super::TftpParser::parse(rest).map_or(Udp::Raw(rest), Udp::Tftp),
+            UdpPort::COAP => super::CoapParser::parse(rest).map_or(Udp::Raw(rest), Udp::Coap),
+            // Magic packets sent to the echo port are left to be echoed like any other datagram
+            UdpPort::DISCARD => super::WolParser::parse(rest).map_or(Udp::Raw(rest), Udp::Wol),
+            _ => Udp::Raw(rest),
+        })
+    }
+
+    pub fn payload(&self) -> Result<&'a [u8]> {
+        let length = self.length() as usize;
+        if length < 8 || self.buffer.len() < length {
+            // e.g. the 8 octets of a datagram quoted in an ICMP error
+            return Err(Error::Truncated);
+        }
+        Ok(&self.buffer[8..length])
     }
 
     pub fn source_port(&'a self) -> u16 {
@@ -122,6 +174,15 @@ impl<'a> UdpParser<'a> {
                 &self.buffer[0..=5],
                 &self.buffer[8..],
             ]),
+            crate::Ip::Ipv6(ipv6) => util::checksum([
+                ipv6.source_address().as_ref(),
+                ipv6.destination_address().as_ref(),
+                [0x00, 0x00].as_ref(),
+                self.length().to_be_bytes().as_ref(),
+                [0x00, 0x00, 0x00, crate::IpProto::UDP].as_ref(),
+                &self.buffer[0..=5],
+                &self.buffer[8..],
+            ]),
         };
         if csum == 0 {
             0xFFFF
@@ -130,3 +191,197 @@ impl<'a> UdpParser<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn datagram(source_port: u16, destination_port: u16, payload: &[u8]) -> UdpPdu {
+        let mut udp_pdu = UdpPdu::new();
+        udp_pdu.source_port(source_port);
+        udp_pdu.destination_port(destination_port);
+        udp_pdu.inner(payload).unwrap();
+        udp_pdu
+    }
+
+    #[test]
+    fn payload() {
+        let udp_pdu = datagram(40000, UdpPort::ECHO, b"hello");
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        assert_eq!(udp.length(), 13);
+        assert_eq!(udp.payload().unwrap(), b"hello");
+
+        // The length field claims more than was captured
+        let udp = UdpParser::parse(&udp_pdu.as_bytes()[..10]).unwrap();
+        assert!(matches!(udp.payload(), Err(Error::Truncated)));
+        assert!(matches!(udp.inner(), Err(Error::Truncated)));
+    }
+
+    #[test]
+    fn dhcp_dispatch() {
+        let mut dhcp_pdu = crate::DhcpPdu::new();
+        dhcp_pdu.op(crate::DhcpOp::BOOTREQUEST);
+        dhcp_pdu.end().unwrap();
+
+        let udp_pdu = datagram(
+            UdpPort::DHCP_CLIENT,
+            UdpPort::DHCP_SERVER,
+            dhcp_pdu.as_bytes(),
+        );
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        assert!(matches!(udp.inner(), Ok(Udp::Dhcp(_))));
+
+        // Only the destination port selects the protocol
+        let udp_pdu = datagram(UdpPort::DHCP_SERVER, 40000, dhcp_pdu.as_bytes());
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Raw(payload)) = udp.inner() else {
+            panic!("expected a raw payload");
+        };
+        assert_eq!(payload, dhcp_pdu.as_bytes());
+
+        // Falls back to the raw payload when it does not parse
+        let udp_pdu = datagram(UdpPort::DHCP_CLIENT, UdpPort::DHCP_SERVER, b"hello");
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Raw(payload)) = udp.inner() else {
+            panic!("expected a raw payload");
+        };
+        assert_eq!(payload, b"hello");
+    }
+    #[test]
+    fn dns_dispatch() {
+        let mut dns_pdu = crate::DnsPdu::new();
+        dns_pdu.id(0xBEEF);
+        dns_pdu
+            .question(
+                &crate::DnsName::dotted("example.com"),
+                crate::DnsType::A,
+                crate::DnsClass::IN,
+            )
+            .unwrap();
+
+        let udp_pdu = datagram(40000, UdpPort::DNS, dns_pdu.as_bytes());
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Dns(dns)) = udp.inner() else {
+            panic!("expected DNS");
+        };
+        assert_eq!(dns.id(), 0xBEEF);
+
+        let udp_pdu = datagram(UdpPort::MDNS, UdpPort::MDNS, dns_pdu.as_bytes());
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        assert!(matches!(udp.inner(), Ok(Udp::Dns(_))));
+
+        // A response from port 53 goes to an ephemeral port
+        let udp_pdu = datagram(UdpPort::DNS, 40000, dns_pdu.as_bytes());
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        assert!(matches!(udp.inner(), Ok(Udp::Raw(_))));
+
+        // Falls back to the raw payload when it does not parse
+        let udp_pdu = datagram(40000, UdpPort::DNS, &dns_pdu.as_bytes()[..20]);
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Raw(payload)) = udp.inner() else {
+            panic!("expected a raw payload");
+        };
+        assert_eq!(payload, &dns_pdu.as_bytes()[..20]);
+    }
+    #[test]
+    fn ntp_dispatch() {
+        let mut ntp_pdu = crate::NtpPdu::new();
+        ntp_pdu.mode(crate::NtpMode::CLIENT);
+
+        let udp_pdu = datagram(40000, UdpPort::NTP, ntp_pdu.as_bytes());
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Ntp(ntp)) = udp.inner() else {
+            panic!("expected NTP");
+        };
+        assert_eq!(ntp.mode(), crate::NtpMode::CLIENT);
+
+        // A response from port 123 goes to an ephemeral port
+        let udp_pdu = datagram(UdpPort::NTP, 40000, ntp_pdu.as_bytes());
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        assert!(matches!(udp.inner(), Ok(Udp::Raw(_))));
+
+        // Falls back to the raw payload when it does not parse
+        let udp_pdu = datagram(40000, UdpPort::NTP, &ntp_pdu.as_bytes()[..47]);
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Raw(payload)) = udp.inner() else {
+            panic!("expected a raw payload");
+        };
+        assert_eq!(payload.len(), 47);
+    }
+    #[test]
+    fn tftp_dispatch() {
+        let mut tftp_pdu = crate::TftpPdu::new();
+        tftp_pdu
+            .read_request("boot.bin", crate::TftpMode::OCTET)
+            .unwrap();
+
+        let udp_pdu = datagram(40000, UdpPort::TFTP, tftp_pdu.as_bytes());
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Tftp(tftp)) = udp.inner() else {
+            panic!("expected TFTP");
+        };
+        assert_eq!(tftp.opcode(), crate::TftpOpcode::RRQ);
+
+        // Falls back to the raw payload when it does not parse
+        let udp_pdu = datagram(40000, UdpPort::TFTP, &[0x00, 0x01]);
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Raw(payload)) = udp.inner() else {
+            panic!("expected a raw payload");
+        };
+        assert_eq!(payload, [0x00, 0x01]);
+    }
+    #[test]
+    fn coap_dispatch() {
+        let mut coap_pdu = crate::CoapPdu::new();
+        coap_pdu.message_type(crate::CoapType::CONFIRMABLE);
+        coap_pdu.code(crate::CoapCode::GET);
+        coap_pdu.message_id(0x1234);
+
+        let udp_pdu = datagram(40000, UdpPort::COAP, coap_pdu.as_bytes());
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Coap(coap)) = udp.inner() else {
+            panic!("expected CoAP");
+        };
+        assert_eq!(coap.message_id(), 0x1234);
+
+        // A response from port 5683 goes to an ephemeral port
+        let udp_pdu = datagram(UdpPort::COAP, 40000, coap_pdu.as_bytes());
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        assert!(matches!(udp.inner(), Ok(Udp::Raw(_))));
+
+        // Falls back to the raw payload when it does not parse
+        let udp_pdu = datagram(40000, UdpPort::COAP, b"hello");
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Raw(payload)) = udp.inner() else {
+            panic!("expected a raw payload");
+        };
+        assert_eq!(payload, b"hello");
+    }
+    #[test]
+    fn wol_dispatch() {
+        let mut wol_pdu = crate::WolPdu::new();
+        wol_pdu.target([0x02, 0, 0, 0, 0, 0x01]);
+
+        let udp_pdu = datagram(40000, UdpPort::DISCARD, wol_pdu.as_bytes());
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Wol(wol)) = udp.inner() else {
+            panic!("expected a magic packet");
+        };
+        assert_eq!(wol.target(), [0x02, 0, 0, 0, 0, 0x01]);
+
+        // The echo port keeps its payload raw so that it is echoed back unchanged
+        let udp_pdu = datagram(40000, UdpPort::ECHO, wol_pdu.as_bytes());
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Raw(payload)) = udp.inner() else {
+            panic!("expected a raw payload");
+        };
+        assert_eq!(payload, wol_pdu.as_bytes());
+
+        // Falls back to the raw payload when it does not parse
+        let udp_pdu = datagram(40000, UdpPort::DISCARD, b"hello");
+        let udp = UdpParser::parse(udp_pdu.as_bytes()).unwrap();
+        let Ok(Udp::Raw(payload)) = udp.inner() else {
+            panic!("expected a raw payload");
+        };
+        assert_eq!(payload, b"hello");
+    }
+}
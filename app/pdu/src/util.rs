@@ -74,22 +74,199 @@ impl Xorshift32 {
     }
 }
 
-pub fn get_digit_from_usize(val: usize) -> u8 {
+pub fn u64_to_ascii(val: u64, buffer: &mut [u8; 20]) -> &[u8] {
     let mut num = val;
-    let mut digit = 0;
-    while num != 0 {
+    let mut pos = buffer.len();
+    loop {
+        pos -= 1;
+        buffer[pos] = b'0' + (num % 10) as u8;
         num /= 10;
-        digit += 1;
+        if num == 0 {
+            break;
+        }
+    }
+    &buffer[pos..]
+}
+
+pub fn sha1<I, J>(spans: I) -> [u8; 20]
+where
+    I: IntoIterator<Item = J>,
+    J: AsRef<[u8]>,
+{
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut block = [0u8; 64];
+    let mut block_size = 0;
+    let mut len = 0u64;
+
+    for span in spans.into_iter() {
+        for &byte in span.as_ref() {
+            block[block_size] = byte;
+            block_size += 1;
+            if block_size == 64 {
+                sha1_block(&mut state, &block);
+                block_size = 0;
+            }
+        }
+        len += span.as_ref().len() as u64;
+    }
+
+    block[block_size] = 0x80;
+    block[block_size + 1..].fill(0);
+    if block_size >= 56 {
+        sha1_block(&mut state, &block);
+        block = [0u8; 64];
+    }
+    block[56..].copy_from_slice(&(len * 8).to_be_bytes());
+    sha1_block(&mut state, &block);
+
+    let mut digest = [0u8; 20];
+    for (bytes, word) in digest.chunks_mut(4).zip(state.iter()) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn sha1_block(state: &mut [u32; 5], block: &[u8; 64]) {
+    let mut w = [0u32; 80];
+    for (i, word) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes(word.try_into().unwrap());
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, &word) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), 0x5A827999),
+            20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+            _ => (b ^ c ^ d, 0xCA62C1D6),
+        };
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
     }
-    digit
+
+    for (word, value) in state.iter_mut().zip([a, b, c, d, e]) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+pub fn base64_encode<'a>(data: &[u8], buffer: &'a mut [u8]) -> Option<&'a [u8]> {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    if buffer.len() < data.len().div_ceil(3) * 4 {
+        return None;
+    }
+    let mut len = 0;
+    for group in data.chunks(3) {
+        let bits = group.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | (byte as u32) << (16 - i * 8)
+        });
+        for i in 0..4 {
+            buffer[len + i] = if i <= group.len() {
+                ALPHABET[(bits >> (18 - i * 6)) as usize & 0x3F]
+            } else {
+                b'='
+            };
+        }
+        len += 4;
+    }
+    Some(&buffer[..len])
+}
+
+pub fn base64_decode<'a>(data: &[u8], buffer: &'a mut [u8]) -> Option<&'a [u8]> {
+    if data.len() % 4 != 0 {
+        return None;
+    }
+    let mut len = 0;
+    for (n, group) in data.chunks(4).enumerate() {
+        let is_last = n == data.len() / 4 - 1;
+        let padding = group.iter().rev().take_while(|&&b| b == b'=').count();
+        if padding > 2 || (padding > 0 && !is_last) {
+            return None;
+        }
+        let mut bits = 0u32;
+        for &b in &group[..4 - padding] {
+            let value = match b {
+                b'A'..=b'Z' => b - b'A',
+                b'a'..=b'z' => b - b'a' + 26,
+                b'0'..=b'9' => b - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                _ => return None,
+            };
+            bits = bits << 6 | value as u32;
+        }
+        bits <<= padding * 6;
+        let bytes = &bits.to_be_bytes()[1..4 - padding];
+        buffer
+            .get_mut(len..len + bytes.len())?
+            .copy_from_slice(bytes);
+        len += bytes.len();
+    }
+    Some(&buffer[..len])
 }
 
-pub fn usize_to_bytes(val: usize) -> [u8; 4] {
-    let mut ans = [0u8; 4];
-    for i in (0..4).rev() {
-        let digit_num = (val as u32) % 10u32.pow(i + 1) / 10u32.pow(i);
-        let chr = core::char::from_digit(digit_num, 10).unwrap();
-        ans[3 - i as usize] = chr as u8;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE64: [(&[u8], &[u8]); 7] = [
+        (b"", b""),
+        (b"f", b"Zg=="),
+        (b"fo", b"Zm8="),
+        (b"foo", b"Zm9v"),
+        (b"foob", b"Zm9vYg=="),
+        (b"fooba", b"Zm9vYmE="),
+        (b"foobar", b"Zm9vYmFy"),
+    ];
+
+    #[test]
+    fn sha1_digests() {
+        assert_eq!(
+            sha1([b"abc"]),
+            [
+                0xA9, 0x99, 0x3E, 0x36, 0x47, 0x06, 0x81, 0x6A, 0xBA, 0x3E, 0x25, 0x71, 0x78, 0x50,
+                0xC2, 0x6C, 0x9C, 0xD0, 0xD8, 0x9D
+            ]
+        );
+        // 56 bytes need a second block for the length
+        let message = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        let digest = [
+            0x84, 0x98, 0x3E, 0x44, 0x1C, 0x3B, 0xD2, 0x6E, 0xBA, 0xAE, 0x4A, 0xA1, 0xF9, 0x51,
+            0x29, 0xE5, 0xE5, 0x46, 0x70, 0xF1,
+        ];
+        assert_eq!(sha1([&message[..]]), digest);
+        assert_eq!(sha1([&message[..10], &message[10..], &[]]), digest);
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        for (data, encoded) in BASE64 {
+            let mut buffer = [0u8; 8];
+            assert_eq!(base64_encode(data, &mut buffer), Some(encoded));
+            let mut buffer = [0u8; 6];
+            assert_eq!(base64_decode(encoded, &mut buffer), Some(data));
+        }
+    }
+
+    #[test]
+    fn base64_errors() {
+        let mut buffer = [0u8; 7];
+        assert_eq!(base64_encode(b"foobar!", &mut buffer), None);
+        assert_eq!(base64_encode(b"f", &mut buffer[..3]), None);
+        assert_eq!(base64_decode(b"Zm9vYmFy", &mut buffer[..5]), None);
+        for data in [&b"Zm9"[..], b"Zg=a", b"Z===", b"Zg==Zg==", b"Zm9*"] {
+            assert_eq!(base64_decode(data, &mut buffer), None);
+        }
     }
-    ans
 }
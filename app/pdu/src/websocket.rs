@@ -0,0 +1,845 @@
+use core::convert::TryInto;
+
+use crate::{util, Error, HttpMethod, HttpParser, HttpPdu, HttpStatus, Result};
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+#[allow(non_snake_case)]
+pub mod WebSocketOpcode {
+    pub const CONTINUATION: u8 = 0x0;
+    pub const TEXT: u8 = 0x1;
+    pub const BINARY: u8 = 0x2;
+    pub const CLOSE: u8 = 0x8;
+    pub const PING: u8 = 0x9;
+    pub const PONG: u8 = 0xA;
+}
+
+#[allow(non_snake_case)]
+pub mod WebSocketCloseCode {
+    pub const NORMAL: u16 = 1000;
+    pub const GOING_AWAY: u16 = 1001;
+    pub const PROTOCOL_ERROR: u16 = 1002;
+    pub const UNSUPPORTED_DATA: u16 = 1003;
+    pub const INVALID_PAYLOAD: u16 = 1007;
+    pub const POLICY_VIOLATION: u16 = 1008;
+    pub const MESSAGE_TOO_BIG: u16 = 1009;
+    pub const MANDATORY_EXTENSION: u16 = 1010;
+    pub const INTERNAL_ERROR: u16 = 1011;
+}
+
+#[derive(Copy, Clone)]
+pub enum WebSocket<'a> {
+    Continuation {
+        fin: bool,
+        payload: WebSocketPayload<'a>,
+    },
+    Text {
+        fin: bool,
+        payload: WebSocketPayload<'a>,
+    },
+    Binary {
+        fin: bool,
+        payload: WebSocketPayload<'a>,
+    },
+    Close {
+        code: Option<u16>,
+        reason: WebSocketPayload<'a>,
+    },
+    Ping(WebSocketPayload<'a>),
+    Pong(WebSocketPayload<'a>),
+}
+
+#[derive(Copy, Clone)]
+pub struct WebSocketHandshake<'a> {
+    key: &'a str,
+    protocols: Option<&'a str>,
+}
+
+impl<'a> WebSocketHandshake<'a> {
+    pub fn parse(request: &'a HttpParser<'a>) -> Result<Self> {
+        // Opening handshake requirements (RFC 6455, Section 4.2.1)
+        if request.method() != HttpMethod::GET
+            || !is_http_1_1_or_later(request.version())
+            || request.header("host").is_none()
+            || !has_token(request.header("upgrade"), "websocket")
+            || !has_token(request.header("connection"), "upgrade")
+            || request.header("sec-websocket-version") != Some("13")
+        {
+            return Err(Error::Malformed);
+        }
+        let key = request
+            .header("sec-websocket-key")
+            .ok_or(Error::Malformed)?;
+        // The key is a base64-encoded 16-byte nonce
+        let mut nonce = [0u8; 18];
+        match util::base64_decode(key.as_bytes(), &mut nonce) {
+            Some(nonce) if nonce.len() == 16 => {}
+            _ => return Err(Error::Malformed),
+        }
+        Ok(WebSocketHandshake {
+            key,
+            protocols: request.header("sec-websocket-protocol"),
+        })
+    }
+
+    pub fn key(&self) -> &'a str {
+        self.key
+    }
+
+    pub fn protocols(&self) -> impl Iterator<Item = &'a str> {
+        self.protocols
+            .unwrap_or("")
+            .split(',')
+            .map(|protocol| protocol.trim_matches(|c| c == ' ' || c == '\t'))
+            .filter(|protocol| !protocol.is_empty())
+    }
+
+    pub fn accept(&self) -> [u8; 28] {
+        let digest = util::sha1([self.key.as_bytes(), GUID.as_bytes()]);
+        let mut accept = [0u8; 28];
+        // 20 bytes always encode to exactly 28 characters
+        util::base64_encode(&digest, &mut accept).unwrap();
+        accept
+    }
+
+    pub fn response(&self, http_pdu: &mut HttpPdu, protocol: Option<&str>) -> Result<()> {
+        let accept = self.accept();
+        http_pdu.status(HttpStatus::SWITCHING_PROTOCOLS)?;
+        http_pdu.header("Upgrade", "websocket")?;
+        http_pdu.connection("Upgrade")?;
+        // base64 output is always ASCII
+        http_pdu.header(
+            "Sec-WebSocket-Accept",
+            core::str::from_utf8(&accept).unwrap(),
+        )?;
+        if let Some(protocol) = protocol {
+            http_pdu.header("Sec-WebSocket-Protocol", protocol)?;
+        }
+        http_pdu.inner(&[])
+    }
+}
+
+fn is_http_1_1_or_later(version: &str) -> bool {
+    match version.as_bytes() {
+        [b'H', b'T', b'T', b'P', b'/', major @ b'0'..=b'9', b'.', minor @ b'0'..=b'9'] => {
+            (*major, *minor) >= (b'1', b'1')
+        }
+        _ => false,
+    }
+}
+
+fn has_token(value: Option<&str>, token: &str) -> bool {
+    value.is_some_and(|value| {
+        value.split(',').any(|item| {
+            item.trim_matches(|c| c == ' ' || c == '\t')
+                .eq_ignore_ascii_case(token)
+        })
+    })
+}
+
+const HEADER_SPACE: usize = 14;
+
+pub struct WebSocketPdu {
+    buffer: [u8; 1460],
+    header_size: usize,
+    inner_size: usize,
+    payload_length: u64,
+    payload_offset: u64,
+    masking_key: Option<[u8; 4]>,
+}
+
+impl WebSocketPdu {
+    pub fn new() -> Self {
+        let mut websocket_pdu = WebSocketPdu {
+            buffer: [0u8; 1460],
+            header_size: 0,
+            inner_size: 0,
+            payload_length: 0,
+            payload_offset: 0,
+            masking_key: None,
+        };
+        websocket_pdu.write_header();
+        websocket_pdu.fin(true);
+        websocket_pdu.opcode(WebSocketOpcode::TEXT);
+        websocket_pdu
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        // Only the first segment of a streamed payload carries the header
+        let start = if self.payload_offset == 0 {
+            HEADER_SPACE - self.header_size
+        } else {
+            HEADER_SPACE
+        };
+        &self.buffer[start..HEADER_SPACE + self.inner_size]
+    }
+
+    pub fn fin(&mut self, value: bool) {
+        let pos = HEADER_SPACE - self.header_size;
+        self.buffer[pos] = self.buffer[pos] & 0x7F | (value as u8) << 7;
+    }
+
+    pub fn opcode(&mut self, value: u8) {
+        let pos = HEADER_SPACE - self.header_size;
+        self.buffer[pos] = self.buffer[pos] & 0xF0 | value & 0x0F;
+    }
+
+    pub fn masking_key(&mut self, value: Option<[u8; 4]>) {
+        let payload = &mut self.buffer[HEADER_SPACE..HEADER_SPACE + self.inner_size];
+        // Undoing the previous mask first keeps the payload intact whatever the call order
+        let offset = self.payload_offset as usize;
+        if let Some(masking_key) = self.masking_key {
+            apply_mask(payload, masking_key, offset);
+        }
+        if let Some(masking_key) = value {
+            apply_mask(payload, masking_key, offset);
+        }
+        self.masking_key = value;
+        self.write_header();
+    }
+
+    pub fn payload_length(&mut self, value: u64) {
+        // The payload itself follows piece by piece through inner()
+        self.inner_size = 0;
+        self.payload_length = value;
+        self.payload_offset = 0;
+        self.write_header();
+    }
+
+    pub fn inner(&mut self, value: &[u8]) -> Result<()> {
+        let len = value.len();
+        if HEADER_SPACE + len > 1460 {
+            return Err(Error::Oversized);
+        }
+        let offset = self.payload_offset + self.inner_size as u64;
+        if offset < self.payload_length {
+            // The next piece of a payload declared with payload_length()
+            if offset + len as u64 > self.payload_length {
+                return Err(Error::Oversized);
+            }
+            self.payload_offset = offset;
+        } else {
+            self.payload_length = len as u64;
+            self.payload_offset = 0;
+        }
+        self.inner_size = len;
+        let payload = &mut self.buffer[HEADER_SPACE..HEADER_SPACE + len];
+        payload.copy_from_slice(value);
+        if let Some(masking_key) = self.masking_key {
+            apply_mask(payload, masking_key, self.payload_offset as usize);
+        }
+        self.write_header();
+        Ok(())
+    }
+
+    pub fn close(&mut self, code: Option<u16>, reason: &str) -> Result<()> {
+        let mut payload = [0u8; 125];
+        let len = match code {
+            Some(code) => {
+                if 2 + reason.len() > 125 {
+                    return Err(Error::Oversized);
+                }
+                payload[0..=1].copy_from_slice(&code.to_be_bytes());
+                payload[2..2 + reason.len()].copy_from_slice(reason.as_bytes());
+                2 + reason.len()
+            }
+            // A reason can only be given along with a status code
+            None if reason.is_empty() => 0,
+            None => return Err(Error::Malformed),
+        };
+        self.control(WebSocketOpcode::CLOSE, &payload[..len])
+    }
+
+    pub fn ping(&mut self, value: &[u8]) -> Result<()> {
+        self.control(WebSocketOpcode::PING, value)
+    }
+
+    pub fn pong(&mut self, value: &[u8]) -> Result<()> {
+        self.control(WebSocketOpcode::PONG, value)
+    }
+
+    fn control(&mut self, opcode: u8, value: &[u8]) -> Result<()> {
+        // Control frames are never fragmented and carry at most 125 bytes (RFC 6455, Section 5.5)
+        if value.len() > 125 {
+            return Err(Error::Oversized);
+        }
+        self.fin(true);
+        self.opcode(opcode);
+        self.inner(value)
+    }
+
+    fn write_header(&mut self) {
+        let first = self.buffer[HEADER_SPACE - self.header_size];
+        let mask = if self.masking_key.is_some() { 0x80 } else { 0 };
+        let mut header = [0u8; HEADER_SPACE];
+        header[0] = first;
+        let mut header_size = match self.payload_length {
+            len @ 0..=125 => {
+                header[1] = mask | len as u8;
+                2
+            }
+            len @ 126..=0xFFFF => {
+                header[1] = mask | 126;
+                header[2..=3].copy_from_slice(&(len as u16).to_be_bytes());
+                4
+            }
+            len => {
+                header[1] = mask | 127;
+                header[2..=9].copy_from_slice(&len.to_be_bytes());
+                10
+            }
+        };
+        if let Some(masking_key) = self.masking_key {
+            header[header_size..header_size + 4].copy_from_slice(&masking_key);
+            header_size += 4;
+        }
+        self.header_size = header_size;
+        self.buffer[HEADER_SPACE - header_size..HEADER_SPACE]
+            .copy_from_slice(&header[..header_size]);
+    }
+}
+
+impl Default for WebSocketPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn apply_mask(buffer: &mut [u8], masking_key: [u8; 4], offset: usize) {
+    for (i, b) in buffer.iter_mut().enumerate() {
+        *b ^= masking_key[(offset + i) % 4];
+    }
+}
+
+fn header_length(buffer: &[u8]) -> usize {
+    let len = match buffer[1] & 0x7F {
+        126 => 4,
+        127 => 10,
+        _ => 2,
+    };
+    if buffer[1] & 0x80 != 0 {
+        len + 4
+    } else {
+        len
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct WebSocketParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> WebSocketParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 2 || buffer.len() < header_length(buffer) {
+            return Err(Error::Truncated);
+        }
+        let pdu = WebSocketParser { buffer };
+        let len = pdu.payload_length();
+        // Extended lengths must use the shortest form, with the top bit of 64-bit ones clear
+        let is_minimal = match buffer[1] & 0x7F {
+            126 => len >= 126,
+            127 => len > 0xFFFF && len >> 63 == 0,
+            _ => true,
+        };
+        let is_control = pdu.opcode() & 0x08 != 0;
+        if !is_minimal
+            || pdu.rsv() != 0
+            || !matches!(pdu.opcode(), 0x0..=0x2 | 0x8..=0xA)
+            || (is_control && (!pdu.fin() || len > 125))
+            || (pdu.opcode() == WebSocketOpcode::CLOSE && len == 1)
+        {
+            return Err(Error::Malformed);
+        }
+        if (buffer.len() as u64) < pdu.computed_header_length() as u64 + len {
+            return Err(Error::Truncated);
+        }
+        if let Some(code) = pdu.close_code() {
+            // Codes that may appear on the wire (RFC 6455, Section 7.4)
+            if !matches!(code, 1000..=1003 | 1007..=1011 | 3000..=4999) {
+                return Err(Error::Malformed);
+            }
+        }
+        Ok(pdu)
+    }
+
+    pub fn inner(&'a self) -> Result<WebSocket<'a>> {
+        (*self).into_inner()
+    }
+
+    pub fn into_inner(self) -> Result<WebSocket<'a>> {
+        let fin = self.fin();
+        let payload = self.payload();
+        Ok(match self.opcode() {
+            WebSocketOpcode::CONTINUATION => WebSocket::Continuation { fin, payload },
+            WebSocketOpcode::TEXT => WebSocket::Text { fin, payload },
+            WebSocketOpcode::BINARY => WebSocket::Binary { fin, payload },
+            WebSocketOpcode::CLOSE => WebSocket::Close {
+                code: self.close_code(),
+                reason: payload.skip_bytes(2),
+            },
+            WebSocketOpcode::PING => WebSocket::Ping(payload),
+            WebSocketOpcode::PONG => WebSocket::Pong(payload),
+            _ => return Err(Error::Malformed),
+        })
+    }
+
+    pub fn computed_header_length(&self) -> usize {
+        header_length(self.buffer)
+    }
+
+    pub fn computed_length(&'a self) -> usize {
+        self.computed_header_length() + self.payload_length() as usize
+    }
+
+    pub fn fin(&self) -> bool {
+        self.buffer[0] & 0x80 != 0
+    }
+
+    pub fn rsv(&self) -> u8 {
+        (self.buffer[0] >> 4) & 0x07
+    }
+
+    pub fn opcode(&self) -> u8 {
+        self.buffer[0] & 0x0F
+    }
+
+    pub fn is_masked(&'a self) -> bool {
+        self.buffer[1] & 0x80 != 0
+    }
+
+    pub fn masking_key(&self) -> Option<[u8; 4]> {
+        if self.buffer[1] & 0x80 == 0 {
+            return None;
+        }
+        let pos = self.computed_header_length() - 4;
+        Some(self.buffer[pos..pos + 4].try_into().unwrap())
+    }
+
+    pub fn payload_length(&self) -> u64 {
+        match self.buffer[1] & 0x7F {
+            126 => u16::from_be_bytes(self.buffer[2..=3].try_into().unwrap()) as u64,
+            127 => u64::from_be_bytes(self.buffer[2..=9].try_into().unwrap()),
+            len => len as u64,
+        }
+    }
+
+    pub fn payload(&self) -> WebSocketPayload<'a> {
+        let start = self.computed_header_length();
+        WebSocketPayload {
+            buffer: &self.buffer[start..start + self.payload_length() as usize],
+            masking_key: self.masking_key(),
+            offset: 0,
+        }
+    }
+
+    fn close_code(&self) -> Option<u16> {
+        if self.opcode() != WebSocketOpcode::CLOSE || self.payload_length() < 2 {
+            return None;
+        }
+        let mut payload = self.payload();
+        Some(u16::from_be_bytes([payload.next()?, payload.next()?]))
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct WebSocketPayload<'a> {
+    buffer: &'a [u8],
+    masking_key: Option<[u8; 4]>,
+    offset: usize,
+}
+
+impl<'a> WebSocketPayload<'a> {
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    pub fn decode_into(self, buffer: &mut [u8]) -> Result<&[u8]> {
+        let len = self.buffer.len();
+        let output = buffer.get_mut(..len).ok_or(Error::Oversized)?;
+        output.copy_from_slice(self.buffer);
+        if let Some(masking_key) = self.masking_key {
+            apply_mask(output, masking_key, self.offset);
+        }
+        Ok(output)
+    }
+
+    fn skip_bytes(self, len: usize) -> Self {
+        let len = len.min(self.buffer.len());
+        WebSocketPayload {
+            buffer: &self.buffer[len..],
+            masking_key: self.masking_key,
+            offset: self.offset + len,
+        }
+    }
+}
+
+impl<'a> Iterator for WebSocketPayload<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&b, rest) = self.buffer.split_first()?;
+        let b = match self.masking_key {
+            Some(masking_key) => b ^ masking_key[self.offset % 4],
+            None => b,
+        };
+        self.buffer = rest;
+        self.offset += 1;
+        Some(b)
+    }
+}
+
+pub struct WebSocketStream {
+    buffer: [u8; 2048],
+    len: usize,
+}
+
+impl WebSocketStream {
+    pub fn new() -> Self {
+        WebSocketStream {
+            buffer: [0u8; 2048],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) -> Result<()> {
+        let len = data.len();
+        if self.len + len > 2048 {
+            return Err(Error::Oversized);
+        }
+        self.buffer[self.len..self.len + len].copy_from_slice(data);
+        self.len += len;
+        Ok(())
+    }
+
+    pub fn frame(&self) -> Result<Option<WebSocketParser<'_>>> {
+        let buffer = &self.buffer[..self.len];
+        match WebSocketParser::parse(buffer) {
+            Ok(pdu) => Ok(Some(pdu)),
+            Err(Error::Truncated) => {
+                // A frame larger than the buffer would never complete
+                if buffer.len() >= 2 && buffer.len() >= header_length(buffer) {
+                    let pdu = WebSocketParser { buffer };
+                    let len = pdu.computed_header_length() as u64 + pdu.payload_length();
+                    if len > self.buffer.len() as u64 {
+                        return Err(Error::Oversized);
+                    }
+                }
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn consume(&mut self) {
+        let len = match self.frame() {
+            Ok(Some(pdu)) => pdu.computed_length(),
+            Ok(None) => return,
+            // A frame that fails validation must fail the connection anyway
+            Err(_) => self.len,
+        };
+        self.buffer.copy_within(len..self.len, 0);
+        self.len -= len;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Default for WebSocketStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UPGRADE: &[u8] = b"GET /chat HTTP/1.1\r\nHost: nucleo\r\nUpgrade: WebSocket\r\nConnection: keep-alive, Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Protocol: chat, superchat\r\nSec-WebSocket-Version: 13\r\n\r\n";
+
+    #[test]
+    fn handshake() {
+        let request = HttpParser::parse(UPGRADE).unwrap();
+        let handshake = WebSocketHandshake::parse(&request).unwrap();
+        assert_eq!(handshake.key(), "dGhlIHNhbXBsZSBub25jZQ==");
+        assert!(handshake.protocols().eq(["chat", "superchat"]));
+        // The example from RFC 6455, Section 1.3
+        assert_eq!(&handshake.accept(), b"s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        let mut http_pdu = HttpPdu::new();
+        handshake.response(&mut http_pdu, Some("chat")).unwrap();
+        assert_eq!(
+            http_pdu.as_bytes(),
+            b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\nSec-WebSocket-Protocol: chat\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn handshake_malformed() {
+        for (from, to) in [
+            ("GET ", "POST "),
+            ("HTTP/1.1\r\nHost", "HTTP/1.0\r\nHost"),
+            ("Host: nucleo", "X-Host: nucleo"),
+            ("Upgrade: WebSocket", "Upgrade: h2c"),
+            ("keep-alive, Upgrade", "keep-alive"),
+            ("Version: 13", "Version: 8"),
+            ("Key: dGhlIHNhbXBsZSBub25jZQ==", "Key: dGhlIHNhbXBsZQ=="),
+            ("Key: dGhlIHNhbXBsZSBub25jZQ==", "Key: not base64!"),
+        ] {
+            let mut request = [0u8; 256];
+            let upgrade = core::str::from_utf8(UPGRADE).unwrap();
+            let (head, tail) = upgrade.split_once(from).unwrap();
+            let mut len = 0;
+            for part in [head, to, tail] {
+                request[len..len + part.len()].copy_from_slice(part.as_bytes());
+                len += part.len();
+            }
+            let request = HttpParser::parse(&request[..len]).unwrap();
+            assert!(
+                matches!(WebSocketHandshake::parse(&request), Err(Error::Malformed)),
+                "{}",
+                to
+            );
+        }
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let mut websocket_pdu = WebSocketPdu::new();
+        websocket_pdu.inner(b"Hello").unwrap();
+        assert_eq!(websocket_pdu.as_bytes(), b"\x81\x05Hello");
+        // The masked example from RFC 6455, Section 5.7
+        websocket_pdu.masking_key(Some([0x37, 0xFA, 0x21, 0x3D]));
+        assert_eq!(
+            websocket_pdu.as_bytes(),
+            [0x81, 0x85, 0x37, 0xFA, 0x21, 0x3D, 0x7F, 0x9F, 0x4D, 0x51, 0x58]
+        );
+        let websocket = WebSocketParser::parse(websocket_pdu.as_bytes()).unwrap();
+        assert!(websocket.is_masked());
+        assert_eq!(websocket.masking_key(), Some([0x37, 0xFA, 0x21, 0x3D]));
+        let Ok(WebSocket::Text { fin: true, payload }) = websocket.inner() else {
+            panic!("expected a text frame");
+        };
+        assert!(payload.eq(b"Hello".iter().copied()));
+        let mut buffer = [0u8; 5];
+        assert_eq!(payload.decode_into(&mut buffer).unwrap(), b"Hello");
+        assert!(matches!(
+            payload.decode_into(&mut buffer[..4]),
+            Err(Error::Oversized)
+        ));
+
+        websocket_pdu.masking_key(None);
+        websocket_pdu.fin(false);
+        websocket_pdu.opcode(WebSocketOpcode::BINARY);
+        websocket_pdu.inner(&[0xAA; 300]).unwrap();
+        assert_eq!(websocket_pdu.as_bytes()[..4], [0x02, 0x7E, 0x01, 0x2C]);
+        let websocket = WebSocketParser::parse(websocket_pdu.as_bytes()).unwrap();
+        assert_eq!(websocket.computed_length(), 304);
+        let Ok(WebSocket::Binary {
+            fin: false,
+            payload,
+        }) = websocket.inner()
+        else {
+            panic!("expected a binary frame");
+        };
+        assert_eq!(payload.len(), 300);
+    }
+
+    #[test]
+    fn streamed_payload_length() {
+        let mut websocket_pdu = WebSocketPdu::new();
+        websocket_pdu.opcode(WebSocketOpcode::BINARY);
+        websocket_pdu.payload_length(0x0001_0000);
+        assert_eq!(
+            websocket_pdu.as_bytes(),
+            [0x82, 0x7F, 0, 0, 0, 0, 0, 0x01, 0x00, 0x00]
+        );
+        assert!(matches!(
+            WebSocketParser::parse(websocket_pdu.as_bytes()),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            websocket_pdu.inner(&[0; 1447]),
+            Err(Error::Oversized)
+        ));
+        websocket_pdu.inner(&[0; 1446]).unwrap();
+    }
+
+    #[test]
+    fn control_frames() {
+        let mut websocket_pdu = WebSocketPdu::new();
+        websocket_pdu
+            .close(Some(WebSocketCloseCode::GOING_AWAY), "bye")
+            .unwrap();
+        let websocket = WebSocketParser::parse(websocket_pdu.as_bytes()).unwrap();
+        let Ok(WebSocket::Close {
+            code: Some(WebSocketCloseCode::GOING_AWAY),
+            reason,
+        }) = websocket.inner()
+        else {
+            panic!("expected a close frame");
+        };
+        assert!(reason.eq(b"bye".iter().copied()));
+        // The reason stays readable behind a mask
+        websocket_pdu.masking_key(Some([1, 2, 3, 4]));
+        let websocket = WebSocketParser::parse(websocket_pdu.as_bytes()).unwrap();
+        let Ok(WebSocket::Close { reason, .. }) = websocket.inner() else {
+            panic!("expected a close frame");
+        };
+        let mut buffer = [0u8; 3];
+        assert_eq!(reason.decode_into(&mut buffer).unwrap(), b"bye");
+
+        websocket_pdu.close(None, "").unwrap();
+        let websocket = WebSocketParser::parse(websocket_pdu.as_bytes()).unwrap();
+        assert!(matches!(
+            websocket.inner(),
+            Ok(WebSocket::Close { code: None, .. })
+        ));
+        assert!(matches!(
+            websocket_pdu.close(None, "bye"),
+            Err(Error::Malformed)
+        ));
+        let reason = core::str::from_utf8(&[b'a'; 124]).unwrap();
+        assert!(matches!(
+            websocket_pdu.close(Some(WebSocketCloseCode::NORMAL), reason),
+            Err(Error::Oversized)
+        ));
+        websocket_pdu.ping(&[0; 125]).unwrap();
+        assert!(matches!(
+            websocket_pdu.ping(&[0; 126]),
+            Err(Error::Oversized)
+        ));
+        websocket_pdu.pong(b"pong").unwrap();
+        let websocket = WebSocketParser::parse(websocket_pdu.as_bytes()).unwrap();
+        assert!(matches!(websocket.inner(), Ok(WebSocket::Pong(_))));
+    }
+
+    #[test]
+    fn truncated_and_malformed() {
+        assert!(matches!(
+            WebSocketParser::parse(&[0x81]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            WebSocketParser::parse(&[0x81, 0x7E, 0x01]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            WebSocketParser::parse(&[0x81, 0x85, 0x37, 0xFA, 0x21, 0x3D, 0x7F]),
+            Err(Error::Truncated)
+        ));
+        for frame in [
+            // A 16-bit length that would fit in 7 bits
+            &[0x81, 0x7E, 0x00, 0x05][..],
+            // A 64-bit length with the top bit set
+            &[0x82, 0x7F, 0x80, 0, 0, 0, 0, 0x01, 0x00, 0x00],
+            // RSV1 without a negotiated extension
+            &[0xC1, 0x00],
+            // A reserved opcode
+            &[0x83, 0x00],
+            // A fragmented ping
+            &[0x09, 0x00],
+            // A control frame over 125 bytes
+            &[0x89, 0x7E, 0x00, 0x7E],
+            // A close frame with a one-byte body
+            &[0x88, 0x01, 0x03],
+            // Close code 1005 must not be sent
+            &[0x88, 0x02, 0x03, 0xED],
+        ] {
+            assert!(
+                matches!(WebSocketParser::parse(frame), Err(Error::Malformed)),
+                "{:02X?}",
+                frame
+            );
+        }
+    }
+
+    #[test]
+    fn stream_reassembles_frames() {
+        let mut segment = [0u8; 32];
+        segment[..7].copy_from_slice(b"\x81\x05Hello");
+        segment[7..13].copy_from_slice(b"\x8A\x04pong");
+        let mut stream = WebSocketStream::new();
+        stream.push(&segment[..4]).unwrap();
+        assert!(matches!(stream.frame(), Ok(None)));
+        stream.push(&segment[4..9]).unwrap();
+        let Ok(Some(websocket)) = stream.frame() else {
+            panic!("expected a complete frame");
+        };
+        assert!(matches!(websocket.inner(), Ok(WebSocket::Text { .. })));
+        stream.consume();
+        assert!(matches!(stream.frame(), Ok(None)));
+        stream.push(&segment[9..13]).unwrap();
+        let Ok(Some(websocket)) = stream.frame() else {
+            panic!("expected a complete frame");
+        };
+        assert!(matches!(websocket.inner(), Ok(WebSocket::Pong(_))));
+        stream.consume();
+        assert!(matches!(stream.frame(), Ok(None)));
+    }
+
+    #[test]
+    fn stream_reports_oversized_frames() {
+        let mut stream = WebSocketStream::new();
+        // A binary frame of 4000 bytes can never fit the buffer
+        stream.push(&[0x82, 0x7E, 0x0F, 0xA0, 0x00]).unwrap();
+        assert!(matches!(stream.frame(), Err(Error::Oversized)));
+        stream.consume();
+        assert!(matches!(stream.frame(), Ok(None)));
+        // Malformed frames are dropped along with everything after them
+        stream.push(&[0xC1, 0x00, 0x81, 0x00]).unwrap();
+        assert!(matches!(stream.frame(), Err(Error::Malformed)));
+        stream.consume();
+        assert!(matches!(stream.frame(), Ok(None)));
+        assert!(matches!(stream.push(&[0u8; 2049]), Err(Error::Oversized)));
+    }
+    #[test]
+    fn handshake_later_version() {
+        let mut request = [0u8; 256];
+        request[..UPGRADE.len()].copy_from_slice(UPGRADE);
+        // "GET /chat HTTP/1." is followed by the minor version
+        request[17] = b'2';
+        let request = HttpParser::parse(&request[..UPGRADE.len()]).unwrap();
+        assert_eq!(request.version(), "HTTP/1.2");
+        WebSocketHandshake::parse(&request).unwrap();
+        assert!(!is_http_1_1_or_later("HTTP/0.9"));
+        assert!(is_http_1_1_or_later("HTTP/2.0"));
+    }
+
+    #[test]
+    fn streamed_masked_payload() {
+        let mut websocket_pdu = WebSocketPdu::new();
+        websocket_pdu.masking_key(Some([1, 2, 3, 4]));
+        websocket_pdu.payload_length(6);
+        let mut frame = [0u8; 12];
+        websocket_pdu.inner(b"hel").unwrap();
+        assert_eq!(websocket_pdu.as_bytes().len(), 6 + 3);
+        frame[..9].copy_from_slice(websocket_pdu.as_bytes());
+        // Later pieces carry no header and continue the mask where the previous one stopped
+        websocket_pdu.inner(b"lo!").unwrap();
+        assert_eq!(websocket_pdu.as_bytes(), [b'l' ^ 4, b'o' ^ 1, b'!' ^ 2]);
+        frame[9..].copy_from_slice(websocket_pdu.as_bytes());
+        let websocket = WebSocketParser::parse(&frame).unwrap();
+        let Ok(WebSocket::Text { fin: true, payload }) = websocket.inner() else {
+            panic!("expected a text frame");
+        };
+        assert!(payload.eq(b"hello!".iter().copied()));
+        // Unmasking the current piece uses the same offset
+        websocket_pdu.masking_key(None);
+        assert_eq!(websocket_pdu.as_bytes(), b"lo!");
+
+        // Nothing may go past the declared length
+        websocket_pdu.payload_length(4);
+        websocket_pdu.inner(b"abc").unwrap();
+        assert!(matches!(websocket_pdu.inner(b"de"), Err(Error::Oversized)));
+        websocket_pdu.inner(b"d").unwrap();
+        // Once the payload is complete the next call starts a new frame
+        websocket_pdu.inner(b"Hello").unwrap();
+        assert_eq!(websocket_pdu.as_bytes(), b"\x81\x05Hello");
+    }
+}
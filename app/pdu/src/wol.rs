@@ -0,0 +1,178 @@
+use core::convert::TryInto;
+
+use crate::{Error, Result};
+
+pub struct WolPdu {
+    buffer: [u8; 108],
+    password_size: usize,
+}
+
+impl WolPdu {
+    pub fn new() -> Self {
+        let mut wol_pdu = WolPdu {
+            buffer: [0u8; 108],
+            password_size: 0,
+        };
+        wol_pdu.buffer[0..=5].copy_from_slice(&[0xFF; 6]);
+        wol_pdu
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..102 + self.password_size]
+    }
+
+    pub fn target(&mut self, value: [u8; 6]) {
+        for i in 0..16 {
+            let pos = 6 + i * 6;
+            self.buffer[pos..pos + 6].copy_from_slice(&value);
+        }
+    }
+
+    pub fn password(&mut self, value: &[u8]) -> Result<()> {
+        let len = value.len();
+        if len != 0 && len != 4 && len != 6 {
+            // SecureOn passwords are either four or six bytes long
+            return Err(Error::Malformed);
+        }
+        self.buffer[102..102 + len].copy_from_slice(value);
+        self.password_size = len;
+        Ok(())
+    }
+}
+
+impl Default for WolPdu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct WolParser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> WolParser<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        if buffer.len() < 102 {
+            return Err(Error::Truncated);
+        }
+        // The synchronization stream may appear anywhere in the payload
+        let pos = (0..=buffer.len() - 102)
+            .find(|&pos| is_magic_packet(&buffer[pos..]))
+            .ok_or(Error::Malformed)?;
+        Ok(WolParser {
+            buffer: &buffer[pos..],
+        })
+    }
+
+    pub fn target(&'a self) -> [u8; 6] {
+        self.buffer[6..=11].try_into().unwrap()
+    }
+
+    pub fn password(&'a self) -> Option<&'a [u8]> {
+        // Whatever trails the repetitions is the password, so frames must come without their FCS
+        match self.buffer.len() - 102 {
+            0..=3 | 5 => None,
+            4 => Some(&self.buffer[102..106]),
+            _ => Some(&self.buffer[102..108]),
+        }
+    }
+}
+
+fn is_magic_packet(buffer: &[u8]) -> bool {
+    buffer[0..=5] == [0xFF; 6]
+        && buffer[6..102]
+            .chunks(6)
+            .all(|address| address == &buffer[6..=11])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: [u8; 6] = [0x02, 0x00, 0x5E, 0x10, 0x00, 0x01];
+
+    fn udp_parser<'a>(
+        udp_pdu: &'a mut crate::UdpPdu,
+        port: u16,
+        payload: &[u8],
+    ) -> crate::UdpParser<'a> {
+        udp_pdu.source_port(40000);
+        udp_pdu.destination_port(port);
+        udp_pdu.inner(payload).unwrap();
+        crate::UdpParser::parse(udp_pdu.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn round_trip_over_udp() {
+        let mut wol_pdu = WolPdu::new();
+        wol_pdu.target(TARGET);
+        wol_pdu.password(&[1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(wol_pdu.as_bytes().len(), 108);
+        let mut udp_pdu = crate::UdpPdu::new();
+        let udp = udp_parser(&mut udp_pdu, crate::UdpPort::DISCARD, wol_pdu.as_bytes());
+        let Ok(crate::Udp::Wol(wol)) = udp.inner() else {
+            panic!("expected a magic packet");
+        };
+        assert_eq!(wol.target(), TARGET);
+        assert_eq!(wol.password(), Some(&[1, 2, 3, 4, 5, 6][..]));
+
+        wol_pdu.password(&[]).unwrap();
+        let wol = WolParser::parse(wol_pdu.as_bytes()).unwrap();
+        assert_eq!(wol.password(), None);
+    }
+
+    #[test]
+    fn magic_packet_after_a_prefix() {
+        let mut wol_pdu = WolPdu::new();
+        wol_pdu.target(TARGET);
+        wol_pdu.password(&[1, 2, 3, 4]).unwrap();
+        let mut payload = [0u8; 110];
+        payload[..4].copy_from_slice(b"wake");
+        payload[4..110].copy_from_slice(wol_pdu.as_bytes());
+        let mut udp_pdu = crate::UdpPdu::new();
+        let udp = udp_parser(&mut udp_pdu, crate::UdpPort::DISCARD, &payload);
+        let Ok(crate::Udp::Wol(wol)) = udp.inner() else {
+            panic!("expected a magic packet");
+        };
+        assert_eq!(wol.target(), TARGET);
+        assert_eq!(wol.password(), Some(&[1, 2, 3, 4][..]));
+    }
+
+    #[test]
+    fn other_traffic_on_discard_is_raw() {
+        let mut udp_pdu = crate::UdpPdu::new();
+        let udp = udp_parser(&mut udp_pdu, crate::UdpPort::DISCARD, b"hello");
+        assert!(matches!(udp.inner(), Ok(crate::Udp::Raw(b"hello"))));
+    }
+
+    #[test]
+    fn round_trip_over_ethernet() {
+        let mut wol_pdu = WolPdu::new();
+        wol_pdu.target(TARGET);
+        let mut ethernet_pdu = crate::EthernetPdu::new(crate::EtherType::WAKE_ON_LAN);
+        ethernet_pdu.destination_address([0xFF; 6]);
+        ethernet_pdu.inner(wol_pdu.as_bytes()).unwrap();
+        let ethernet = crate::EthernetParser::parse(ethernet_pdu.as_bytes()).unwrap();
+        let Ok(crate::Ethernet::Wol(wol)) = ethernet.inner() else {
+            panic!("expected a magic packet");
+        };
+        assert_eq!(wol.target(), TARGET);
+    }
+
+    #[test]
+    fn truncated_and_malformed() {
+        let mut wol_pdu = WolPdu::new();
+        wol_pdu.target(TARGET);
+        assert!(matches!(wol_pdu.password(&[0; 5]), Err(Error::Malformed)));
+        let bytes = wol_pdu.as_bytes();
+        assert!(matches!(
+            WolParser::parse(&bytes[..101]),
+            Err(Error::Truncated)
+        ));
+        // The last repetition differs from the first
+        let mut bytes: [u8; 102] = bytes.try_into().unwrap();
+        bytes[101] ^= 0x01;
+        assert!(matches!(WolParser::parse(&bytes), Err(Error::Malformed)));
+    }
+}
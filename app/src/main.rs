@@ -96,10 +96,15 @@ fn main() -> ! {
                     Ok(Ipv4::Icmp(icmp_rcvd)) => {
                         if icmp_rcvd.message_type() == IcmpType::ECHO_REQUEST {
                             let mut icmp_pdu = IcmpPdu::new();
-                            icmp_pdu.message_type(IcmpType::ECHO_REPLY);
                             match icmp_rcvd.inner() {
-                                Ok(Icmp::Raw(raw_rcvd)) => {
-                                    icmp_pdu.inner(raw_rcvd).unwrap();
+                                Ok(Icmp::EchoRequest {
+                                    identifier,
+                                    sequence_number,
+                                    data,
+                                }) => {
+                                    icmp_pdu
+                                        .echo_reply(identifier, sequence_number, data)
+                                        .unwrap();
                                 }
                                 _ => {}
                             }
@@ -124,11 +129,8 @@ fn main() -> ! {
                         let mut udp_pdu = UdpPdu::new();
                         udp_pdu.source_port(udp_rcvd.destination_port());
                         udp_pdu.destination_port(udp_rcvd.source_port());
-                        match udp_rcvd.inner() {
-                            Ok(Udp::Raw(raw_rcvd)) => {
-                                udp_pdu.inner(raw_rcvd).unwrap();
-                            }
-                            _ => {}
+                        if let Ok(payload) = udp_rcvd.payload() {
+                            udp_pdu.inner(payload).unwrap();
                         }
                         udp_pdu.compute_checksum(&IpPseudoHeader::Ipv4(Ipv4PseudoHeader {
                             source_address: ipv4_rcvd.destination_address(),
@@ -206,29 +208,30 @@ fn main() -> ! {
                                 match tcp_rcvd.inner() {
                                     Ok(Tcp::Raw(raw_rcvd)) => match HttpParser::parse(raw_rcvd) {
                                         Ok(http_rcvd) => {
-                                            if let Some(method) = http_rcvd.method() {
-                                                if method == "GET" {
-                                                    let mut http_pdu = HttpPdu::new();
-                                                    http_pdu
-                                                        .inner(
-                                                            "<!DOCTYPE html>\r\n\
-                                                            <html>\r\n\
-                                                            <head>\r\n\
-                                                            <title>HelloWorld!</title>\r\n\
-                                                            <meta charset=\"utf-8\" />\r\n\
-                                                            <link rel=\"icon\" href=\"data:,\">\r\n\
-                                                            </head>\r\n\
-                                                            <body>\r\n\
-                                                            <h1>HelloWorld!</h1>\r\n\
-                                                            </body>\r\n\
-                                                            </html>"
-                                                                .as_bytes(),
-                                                        )
-                                                        .unwrap();
-
-                                                    tcp_pdu.psh(true);
-                                                    tcp_pdu.inner(http_pdu.as_bytes()).unwrap();
-                                                }
+                                            if http_rcvd.method() == HttpMethod::GET {
+                                                let mut http_pdu = HttpPdu::new();
+                                                http_pdu
+                                                    .content_type(HttpContentType::TEXT_HTML)
+                                                    .unwrap();
+                                                http_pdu
+                                                    .inner(
+                                                        "<!DOCTYPE html>\r\n\
+                                                        <html>\r\n\
+                                                        <head>\r\n\
+                                                        <title>HelloWorld!</title>\r\n\
+                                                        <meta charset=\"utf-8\" />\r\n\
+                                                        <link rel=\"icon\" href=\"data:,\">\r\n\
+                                                        </head>\r\n\
+                                                        <body>\r\n\
+                                                        <h1>HelloWorld!</h1>\r\n\
+                                                        </body>\r\n\
+                                                        </html>"
+                                                            .as_bytes(),
+                                                    )
+                                                    .unwrap();
+
+                                                tcp_pdu.psh(true);
+                                                tcp_pdu.inner(http_pdu.as_bytes()).unwrap();
                                             }
                                         }
                                         _ => {}